@@ -0,0 +1,50 @@
+#version 420 core
+
+in vec2 ndc;
+
+uniform mat4 inverseProjection;
+// Inverse of the view matrix without the translation (the background is infinitely far away)
+uniform mat4 inverseViewRotation;
+// Rotation of the background around the Y axis in radians
+uniform float rotation;
+// Exposure in stops (EV), applied before the tone mapping
+uniform float exposure;
+
+// Equirectangular HDR image in linear space
+layout (binding = 0) uniform sampler2D environment;
+
+out vec4 FragColor;
+
+const float PI = 3.14159265359;
+
+// Converts the linear color to the sRGB encoding of the output
+vec3 linearToSrgb(vec3 c) {
+    c = clamp(c, 0.0, 1.0);
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+void main() {
+    // Two points on the ray of the pixel, inside of the depth range of every projection (reversed, infinite)
+    vec4 a = inverseProjection * vec4(ndc, 0.25, 1.0);
+    vec4 b = inverseProjection * vec4(ndc, 0.75, 1.0);
+    vec3 viewDir = b.xyz / b.w - a.xyz / a.w;
+    // The camera looks in the -Z direction of the view space
+    if (viewDir.z > 0.0) {
+        viewDir = -viewDir;
+    }
+    vec3 dir = normalize(mat3(inverseViewRotation) * viewDir);
+
+    float c = cos(rotation);
+    float s = sin(rotation);
+    dir = vec3(c * dir.x + s * dir.z, dir.y, -s * dir.x + c * dir.z);
+
+    // The first row of the image is the top (+Y), the textures are uploaded as they are stored
+    vec2 uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, acos(clamp(dir.y, -1.0, 1.0)) / PI);
+
+    // The level is fixed, the derivatives of the coordinates jump at the seam
+    vec3 hdr = textureLod(environment, uv, 0.0).rgb * exp2(exposure);
+
+    // Reinhard tone mapping
+    vec3 ldr = hdr / (1.0 + hdr);
+    FragColor = vec4(linearToSrgb(ldr), 1.0);
+}
@@ -0,0 +1,16 @@
+#version 420 core
+
+in vec2 local;
+
+// Darkness of the center of the blob
+uniform float opacity;
+
+out vec4 FragColor;
+
+void main() {
+    // The shadow fades out towards the edge of the ellipse
+    float dist = length(local);
+    float alpha = opacity * (1.0 - smoothstep(0.2, 1.0, dist));
+
+    FragColor = vec4(0.0, 0.0, 0.0, alpha);
+}
@@ -4,16 +4,172 @@ in VsOut {
     vec2 texCoords;
     vec3 normal;
     vec3 fragPos;
+    vec3 weightsColor;
+    vec4 color;
 } vsOut;
 
+// Has to match the layout of SettingsStd140
+layout (std140, binding = 3) uniform Settings {
+    int doSkinning;
+    int shadingMode;
+    int selectedJoint;
+    int flipV;
+    int gammaCorrect;
+    int applyVertexColors;
+    int alphaToCoverage;
+    float uvCheckerScale;
+    float pointSize;
+    int pointSizeAttenuation;
+};
+
+layout (std140, binding = 1) uniform Transforms {
+    mat4 projection;
+    mat4 view;
+    mat4 model;
+    // Inverse-transpose of the model matrix (only the upper 3x3 is used)
+    mat4 normalMatrix;
+};
+
 layout (std140, binding = 4) uniform Material {
     uniform vec4 texBaseColorFactor;
+    uniform float metallicFactor;
+    uniform float roughnessFactor;
+    uniform int hasMetallicRoughnessTex;
+    uniform int hasOcclusionTex;
+    uniform float occlusionStrength;
+    uniform float normalScale;
+    uniform int hasNormalTex;
+    // Negative if the material isn't masked
+    uniform float alphaCutoff;
+    uniform vec3 emissiveFactor;
+    // KHR_materials_unlit
+    uniform int isUnlit;
+};
+
+layout (std140, binding = 5) uniform Lighting {
     uniform vec3 lightPos;
+    uniform vec3 ambientColor;
+    uniform float ambientIntensity;
+    uniform vec3 fogColor;
+    uniform float fogDensity;
+    uniform int fogMode;
+    uniform float fogStart;
+    uniform float fogEnd;
+    // The lights of the asset and the spot lights of the user (MAX_LIGHTS)
+    uniform int lightCount;
+    // Index of the light casting the shadows, -1 is the light at lightPos
+    uniform int shadowLight;
+    // If the light at lightPos is used (the asset lights replace it)
+    uniform int viewerLight;
+    // Position and the type of the light (LIGHT_* values)
+    uniform vec4 lightPositions[8];
+    // Direction the light is shining in and its range (0 is unlimited)
+    uniform vec4 lightDirections[8];
+    // Color multiplied by the intensity
+    uniform vec4 lightColors[8];
+    // Scale and offset of the cosine of the spot angle
+    uniform vec4 lightSpots[8];
 };
 
+// Have to match the values of the ShadingMode enum
+const int SHADING_LIT = 0;
+const int SHADING_UNLIT = 1;
+const int SHADING_NORMALS = 2;
+const int SHADING_UVS = 3;
+const int SHADING_WEIGHTS = 4;
+const int SHADING_WEIGHT_PAINT = 5;
+
 out vec4 FragColor;
 
+// Converts the sRGB encoded color to linear space (exact sRGB transfer function)
+vec3 srgbToLinear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+// Converts the linear color to the sRGB encoding of the output
+vec3 linearToSrgb(vec3 c) {
+    c = clamp(c, 0.0, 1.0);
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+// The cutout of the MASK materials. With alpha-to-coverage (MSAA) the alpha is sharpened around the cutoff,
+// so the edge is smoothed by the coverage of about one pixel, otherwise the fragments under the cutoff are discarded.
+vec4 alphaMask(vec4 color) {
+    if (alphaCutoff < 0.0) {
+        return color;
+    }
+
+    if (alphaToCoverage != 0) {
+        float alpha = (color.a - alphaCutoff) / max(fwidth(color.a), 0.0001) + 0.5;
+        return vec4(color.rgb, clamp(alpha, 0.0, 1.0));
+    }
+
+    if (color.a < alphaCutoff) {
+        discard;
+    }
+
+    return vec4(color.rgb, 1.0);
+}
+
+// A checkerboard in the UV space replacing the base color (reveals the stretching and the seams of the UVs)
+vec4 uvChecker() {
+    ivec2 cell = ivec2(floor(vsOut.texCoords * uvCheckerScale));
+    float shade = ((cell.x + cell.y) & 1) == 0 ? 0.8 : 0.2;
+    return vec4(vec3(shade), 1.0);
+}
+
+// Have to match the values of the FogMode enum
+const int FOG_OFF = 0;
+const int FOG_LINEAR = 1;
+const int FOG_EXPONENTIAL = 2;
+
+// Fades the encoded color into the fog color with the view-space depth of the fragment
+vec4 applyFog(vec4 color) {
+    if (fogMode == FOG_OFF) {
+        return color;
+    }
+
+    float depth = -(view * vec4(vsOut.fragPos, 1.0)).z;
+    float visibility = fogMode == FOG_LINEAR
+        ? clamp((fogEnd - depth) / max(fogEnd - fogStart, 0.0001), 0.0, 1.0)
+        : exp(-fogDensity * max(depth, 0.0));
+
+    return vec4(mix(fogColor, color.rgb, visibility), color.a);
+}
+
+// Encodes the final shaded color and fades it into the fog (the debug views are written as they are)
+vec4 outputColor(vec4 color) {
+    if (gammaCorrect != 0) {
+        color.rgb = linearToSrgb(color.rgb);
+    }
+
+    // The fog color is in the encoding of the output, like the background
+    return applyFog(color);
+}
+
 void main() {
-    vec4 texColor = texBaseColorFactor;
-    FragColor = texColor;
+    // The base color factor and the vertex colors are linear
+    vec4 texColor = uvCheckerScale > 0.0 ? uvChecker() : alphaMask(texBaseColorFactor * vsOut.color);
+
+    switch (shadingMode) {
+        case SHADING_NORMALS:
+            FragColor = vec4(normalize(vsOut.normal) * 0.5 + 0.5, 1.0);
+            break;
+        case SHADING_UVS:
+            FragColor = vec4(fract(vsOut.texCoords), 0.0, 1.0);
+            break;
+        case SHADING_WEIGHTS:
+        case SHADING_WEIGHT_PAINT:
+            FragColor = vec4(vsOut.weightsColor, 1.0);
+            break;
+        case SHADING_UNLIT:
+            FragColor = outputColor(texColor);
+            break;
+        default:
+            // Unlit materials use the base color only
+            FragColor = isUnlit != 0
+                ? outputColor(texColor)
+                : outputColor(vec4(texColor.rgb + emissiveFactor, texColor.a));
+            break;
+    }
 }
@@ -0,0 +1,145 @@
+#version 420 core
+
+in vec2 texCoords;
+
+// The rendered scene, in the encoding of the output
+layout (binding = 0) uniform sampler2D sceneTex;
+// Size of a pixel in the texture coordinates
+uniform vec2 inverseScreenSize;
+
+out vec4 FragColor;
+
+// The edges with a lower contrast aren't filtered
+const float EDGE_THRESHOLD_MIN = 0.0312;
+const float EDGE_THRESHOLD_MAX = 0.125;
+// How much of the sub-pixel aliasing is removed (1 is the softest)
+const float SUBPIXEL_QUALITY = 0.75;
+// The steps of the search for the ends of the edge, in pixels
+const int ITERATIONS = 12;
+const float QUALITY[ITERATIONS] = float[](1.0, 1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 2.0, 4.0, 8.0);
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+float lumaAt(vec2 uv) {
+    return luma(textureLod(sceneTex, uv, 0.0).rgb);
+}
+
+float lumaOffset(ivec2 offset) {
+    return luma(textureLodOffset(sceneTex, texCoords, 0.0, offset).rgb);
+}
+
+// FXAA - finds the edges by the contrast of the luma and blends the pixels across them,
+// the blend factor depends on the distance from the ends of the edge
+void main() {
+    vec3 colorCenter = textureLod(sceneTex, texCoords, 0.0).rgb;
+    float lumaCenter = luma(colorCenter);
+
+    float lumaDown = lumaOffset(ivec2(0, -1));
+    float lumaUp = lumaOffset(ivec2(0, 1));
+    float lumaLeft = lumaOffset(ivec2(-1, 0));
+    float lumaRight = lumaOffset(ivec2(1, 0));
+
+    float lumaMin = min(lumaCenter, min(min(lumaDown, lumaUp), min(lumaLeft, lumaRight)));
+    float lumaMax = max(lumaCenter, max(max(lumaDown, lumaUp), max(lumaLeft, lumaRight)));
+    float lumaRange = lumaMax - lumaMin;
+
+    if (lumaRange < max(EDGE_THRESHOLD_MIN, lumaMax * EDGE_THRESHOLD_MAX)) {
+        FragColor = vec4(colorCenter, 1.0);
+        return;
+    }
+
+    float lumaDownLeft = lumaOffset(ivec2(-1, -1));
+    float lumaUpRight = lumaOffset(ivec2(1, 1));
+    float lumaUpLeft = lumaOffset(ivec2(-1, 1));
+    float lumaDownRight = lumaOffset(ivec2(1, -1));
+
+    float lumaDownUp = lumaDown + lumaUp;
+    float lumaLeftRight = lumaLeft + lumaRight;
+    float lumaLeftCorners = lumaDownLeft + lumaUpLeft;
+    float lumaDownCorners = lumaDownLeft + lumaDownRight;
+    float lumaRightCorners = lumaDownRight + lumaUpRight;
+    float lumaUpCorners = lumaUpRight + lumaUpLeft;
+
+    // The orientation of the edge from the second derivatives of the luma
+    float edgeHorizontal = abs(-2.0 * lumaLeft + lumaLeftCorners)
+        + abs(-2.0 * lumaCenter + lumaDownUp) * 2.0
+        + abs(-2.0 * lumaRight + lumaRightCorners);
+    float edgeVertical = abs(-2.0 * lumaUp + lumaUpCorners)
+        + abs(-2.0 * lumaCenter + lumaLeftRight) * 2.0
+        + abs(-2.0 * lumaDown + lumaDownCorners);
+    bool isHorizontal = edgeHorizontal >= edgeVertical;
+
+    // The side of the pixel the edge is on is the one with the steeper gradient
+    float luma1 = isHorizontal ? lumaDown : lumaLeft;
+    float luma2 = isHorizontal ? lumaUp : lumaRight;
+    float gradient1 = luma1 - lumaCenter;
+    float gradient2 = luma2 - lumaCenter;
+    bool is1Steepest = abs(gradient1) >= abs(gradient2);
+    float gradientScaled = 0.25 * max(abs(gradient1), abs(gradient2));
+
+    float stepLength = isHorizontal ? inverseScreenSize.y : inverseScreenSize.x;
+    float lumaLocalAverage = 0.5 * (luma2 + lumaCenter);
+    if (is1Steepest) {
+        stepLength = -stepLength;
+        lumaLocalAverage = 0.5 * (luma1 + lumaCenter);
+    }
+
+    // The search starts half a pixel towards the edge
+    vec2 edgeUv = texCoords;
+    vec2 offset;
+    if (isHorizontal) {
+        edgeUv.y += stepLength * 0.5;
+        offset = vec2(inverseScreenSize.x, 0.0);
+    } else {
+        edgeUv.x += stepLength * 0.5;
+        offset = vec2(0.0, inverseScreenSize.y);
+    }
+
+    // Walks along the edge in both directions until the luma changes (the end of the edge)
+    vec2 uv1 = edgeUv - offset * QUALITY[0];
+    vec2 uv2 = edgeUv + offset * QUALITY[0];
+    float lumaEnd1 = lumaAt(uv1) - lumaLocalAverage;
+    float lumaEnd2 = lumaAt(uv2) - lumaLocalAverage;
+    bool reached1 = abs(lumaEnd1) >= gradientScaled;
+    bool reached2 = abs(lumaEnd2) >= gradientScaled;
+
+    for (int i = 1; i < ITERATIONS && !(reached1 && reached2); i++) {
+        if (!reached1) {
+            uv1 -= offset * QUALITY[i];
+            lumaEnd1 = lumaAt(uv1) - lumaLocalAverage;
+            reached1 = abs(lumaEnd1) >= gradientScaled;
+        }
+        if (!reached2) {
+            uv2 += offset * QUALITY[i];
+            lumaEnd2 = lumaAt(uv2) - lumaLocalAverage;
+            reached2 = abs(lumaEnd2) >= gradientScaled;
+        }
+    }
+
+    float distance1 = isHorizontal ? texCoords.x - uv1.x : texCoords.y - uv1.y;
+    float distance2 = isHorizontal ? uv2.x - texCoords.x : uv2.y - texCoords.y;
+    bool isDirection1 = distance1 < distance2;
+    float pixelOffset = 0.5 - min(distance1, distance2) / (distance1 + distance2);
+
+    // Only the pixels whose luma varies the other way than at the nearer end are moved across the edge
+    bool isLumaCenterSmaller = lumaCenter < lumaLocalAverage;
+    bool correctVariation = ((isDirection1 ? lumaEnd1 : lumaEnd2) < 0.0) != isLumaCenterSmaller;
+    float finalOffset = correctVariation ? pixelOffset : 0.0;
+
+    // The sub-pixel aliasing (thin lines, single pixels) from the difference with the average of the neighbors
+    float lumaAverage = (1.0 / 12.0) * (2.0 * (lumaDownUp + lumaLeftRight) + lumaLeftCorners + lumaRightCorners);
+    float subPixel = clamp(abs(lumaAverage - lumaCenter) / lumaRange, 0.0, 1.0);
+    subPixel = (-2.0 * subPixel + 3.0) * subPixel * subPixel;
+    finalOffset = max(finalOffset, subPixel * subPixel * SUBPIXEL_QUALITY);
+
+    vec2 finalUv = texCoords;
+    if (isHorizontal) {
+        finalUv.y += finalOffset * stepLength;
+    } else {
+        finalUv.x += finalOffset * stepLength;
+    }
+
+    FragColor = vec4(textureLod(sceneTex, finalUv, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,10 @@
+#version 420 core
+
+// ID of the drawn primitive, 0 is the background
+uniform uint objectId;
+
+out uint FragId;
+
+void main() {
+    FragId = objectId;
+}
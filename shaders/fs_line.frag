@@ -0,0 +1,15 @@
+#version 420 core
+
+uniform vec4 lineColor;
+// Width of the lines in pixels
+uniform float lineWidth;
+
+in float edgeDistance;
+
+out vec4 FragColor;
+
+void main() {
+    // Fades out over one pixel at the edges of the line
+    float coverage = clamp(lineWidth * 0.5 + 0.5 - abs(edgeDistance), 0.0, 1.0);
+    FragColor = vec4(lineColor.rgb, lineColor.a * coverage);
+}
@@ -0,0 +1,5 @@
+#version 420 core
+
+// Only the depth is written into the shadow maps
+void main() {
+}
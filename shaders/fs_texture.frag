@@ -4,32 +4,370 @@ in VsOut {
     vec2 texCoords;
     vec3 normal;
     vec3 fragPos;
+    vec3 weightsColor;
+    vec4 color;
 } vsOut;
 
+// Has to match the layout of SettingsStd140
+layout (std140, binding = 3) uniform Settings {
+    int doSkinning;
+    int shadingMode;
+    int selectedJoint;
+    int flipV;
+    int gammaCorrect;
+    int applyVertexColors;
+    int alphaToCoverage;
+    float uvCheckerScale;
+    float pointSize;
+    int pointSizeAttenuation;
+};
+
+layout (std140, binding = 1) uniform Transforms {
+    mat4 projection;
+    mat4 view;
+    mat4 model;
+    // Inverse-transpose of the model matrix (only the upper 3x3 is used)
+    mat4 normalMatrix;
+};
+
 layout (std140, binding = 4) uniform Material {
     uniform vec4 texBaseColorFactor;
+    uniform float metallicFactor;
+    uniform float roughnessFactor;
+    uniform int hasMetallicRoughnessTex;
+    uniform int hasOcclusionTex;
+    uniform float occlusionStrength;
+    uniform float normalScale;
+    uniform int hasNormalTex;
+    // Negative if the material isn't masked
+    uniform float alphaCutoff;
+    uniform vec3 emissiveFactor;
+    // KHR_materials_unlit
+    uniform int isUnlit;
 };
 
 layout (std140, binding = 5) uniform Lighting {
     uniform vec3 lightPos;
+    uniform vec3 ambientColor;
+    uniform float ambientIntensity;
+    uniform vec3 fogColor;
+    uniform float fogDensity;
+    uniform int fogMode;
+    uniform float fogStart;
+    uniform float fogEnd;
+    // The lights of the asset and the spot lights of the user (MAX_LIGHTS)
+    uniform int lightCount;
+    // Index of the light casting the shadows, -1 is the light at lightPos
+    uniform int shadowLight;
+    // If the light at lightPos is used (the asset lights replace it)
+    uniform int viewerLight;
+    // Position and the type of the light (LIGHT_* values)
+    uniform vec4 lightPositions[8];
+    // Direction the light is shining in and its range (0 is unlimited)
+    uniform vec4 lightDirections[8];
+    // Color multiplied by the intensity
+    uniform vec4 lightColors[8];
+    // Scale and offset of the cosine of the spot angle
+    uniform vec4 lightSpots[8];
 };
 
+// Has to match the layout written by Shadows::update
+layout (std140, binding = 6) uniform Shadows {
+    // World space to the clip space of the light, for each cascade (MAX_CASCADES)
+    mat4 lightSpace[4];
+    // The far view-space distance of each cascade
+    vec4 cascadeSplits;
+    int cascadeCount;
+    int cascadeDebug;
+    float shadowBias;
+    int zeroToOneDepth;
+};
+
+// Have to match the values of the ShadingMode enum
+const int SHADING_LIT = 0;
+const int SHADING_UNLIT = 1;
+const int SHADING_NORMALS = 2;
+const int SHADING_UVS = 3;
+const int SHADING_WEIGHTS = 4;
+const int SHADING_WEIGHT_PAINT = 5;
+
 uniform sampler2D myTexture;
+// The channels are defined by the renderer (OrmChannels)
+layout (binding = 1) uniform sampler2D metallicRoughnessTex;
+layout (binding = 2) uniform sampler2D occlusionTex;
+// Tangent space normals
+layout (binding = 3) uniform sampler2D normalTex;
+// Depth of the shadow casters, a layer for each cascade
+layout (binding = 4) uniform sampler2DArrayShadow shadowMaps;
 
 out vec4 FragColor;
 
-void main() {
-    vec4 texColor = texture(myTexture, vsOut.texCoords) * texBaseColorFactor;
+// Converts the sRGB encoded color to linear space (exact sRGB transfer function)
+vec3 srgbToLinear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+// Converts the linear color to the sRGB encoding of the output
+vec3 linearToSrgb(vec3 c) {
+    c = clamp(c, 0.0, 1.0);
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+// The cutout of the MASK materials. With alpha-to-coverage (MSAA) the alpha is sharpened around the cutoff,
+// so the edge is smoothed by the coverage of about one pixel, otherwise the fragments under the cutoff are discarded.
+vec4 alphaMask(vec4 color) {
+    if (alphaCutoff < 0.0) {
+        return color;
+    }
+
+    if (alphaToCoverage != 0) {
+        float alpha = (color.a - alphaCutoff) / max(fwidth(color.a), 0.0001) + 0.5;
+        return vec4(color.rgb, clamp(alpha, 0.0, 1.0));
+    }
+
+    if (color.a < alphaCutoff) {
+        discard;
+    }
+
+    return vec4(color.rgb, 1.0);
+}
+
+// A checkerboard in the UV space replacing the base color (reveals the stretching and the seams of the UVs)
+vec4 uvChecker() {
+    ivec2 cell = ivec2(floor(vsOut.texCoords * uvCheckerScale));
+    float shade = ((cell.x + cell.y) & 1) == 0 ? 0.8 : 0.2;
+    return vec4(vec3(shade), 1.0);
+}
+
+// Have to match the values of the FogMode enum
+const int FOG_OFF = 0;
+const int FOG_LINEAR = 1;
+const int FOG_EXPONENTIAL = 2;
+
+// Fades the encoded color into the fog color with the view-space depth of the fragment
+vec4 applyFog(vec4 color) {
+    if (fogMode == FOG_OFF) {
+        return color;
+    }
+
+    float depth = -(view * vec4(vsOut.fragPos, 1.0)).z;
+    float visibility = fogMode == FOG_LINEAR
+        ? clamp((fogEnd - depth) / max(fogEnd - fogStart, 0.0001), 0.0, 1.0)
+        : exp(-fogDensity * max(depth, 0.0));
+
+    return vec4(mix(fogColor, color.rgb, visibility), color.a);
+}
+
+// Encodes the final shaded color and fades it into the fog (the debug views are written as they are)
+vec4 outputColor(vec4 color) {
+    if (gammaCorrect != 0) {
+        color.rgb = linearToSrgb(color.rgb);
+    }
+
+    // The fog color is in the encoding of the output, like the background
+    return applyFog(color);
+}
+
+// Perturbs the normal by the normal texture.
+// The tangent frame is derived from the screen-space derivatives, because the tangents aren't loaded.
+vec3 mappedNormal(vec3 norm) {
+    vec3 tangentNormal = texture(normalTex, vsOut.texCoords).xyz * 2.0 - 1.0;
+    tangentNormal.xy *= normalScale;
+
+    vec3 dPosX = dFdx(vsOut.fragPos);
+    vec3 dPosY = dFdy(vsOut.fragPos);
+    vec2 dUvX = dFdx(vsOut.texCoords);
+    vec2 dUvY = dFdy(vsOut.texCoords);
+
+    vec3 dPosYPerp = cross(dPosY, norm);
+    vec3 dPosXPerp = cross(norm, dPosX);
+    vec3 tangent = dPosYPerp * dUvX.x + dPosXPerp * dUvY.x;
+    vec3 bitangent = dPosYPerp * dUvX.y + dPosXPerp * dUvY.y;
+
+    float invMax = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    mat3 tbn = mat3(tangent * invMax, bitangent * invMax, norm);
+
+    return normalize(tbn * tangentNormal);
+}
+
+// Index of the shadow cascade containing the fragment, -1 if it's farther than the shadows reach
+int shadowCascade() {
+    float depth = -(view * vec4(vsOut.fragPos, 1.0)).z;
+    for (int i = 0; i < cascadeCount; i++) {
+        if (depth < cascadeSplits[i]) {
+            return i;
+        }
+    }
+
+    return -1;
+}
+
+// Fraction of the light reaching the fragment (1 is fully lit), filtered over 3x3 texels
+float shadowFactor(int cascade, vec3 norm, vec3 lightDir) {
+    if (cascade < 0) {
+        return 1.0;
+    }
+
+    vec4 lightClip = lightSpace[cascade] * vec4(vsOut.fragPos, 1.0);
+    vec3 coords = lightClip.xyz / lightClip.w;
+    coords.xy = coords.xy * 0.5 + 0.5;
+    if (zeroToOneDepth == 0) {
+        coords.z = coords.z * 0.5 + 0.5;
+    }
+
+    // The surfaces at a grazing angle to the light need a larger bias
+    float slope = 1.0 - max(dot(norm, lightDir), 0.0);
+    float reference = coords.z - shadowBias * (1.0 + 4.0 * slope);
+
+    vec2 texel = 1.0 / vec2(textureSize(shadowMaps, 0).xy);
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 uv = coords.xy + vec2(x, y) * texel;
+            lit += texture(shadowMaps, vec4(uv, float(cascade), reference));
+        }
+    }
+
+    return lit / 9.0;
+}
+
+// Colors of the cascades in the debug view
+const vec3 CASCADE_COLORS[4] = vec3[](
+    vec3(1.0, 0.3, 0.3),
+    vec3(0.3, 1.0, 0.3),
+    vec3(0.3, 0.3, 1.0),
+    vec3(1.0, 1.0, 0.3)
+);
+
+// Have to match the values written by Lighting::update
+const int LIGHT_DIRECTIONAL = 0;
+const int LIGHT_SPOT = 2;
+
+// The radiance of the asset light reaching the fragment and the direction towards the light (KHR_lights_punctual)
+vec3 lightRadiance(int i, out vec3 lightDir) {
+    if (int(lightPositions[i].w) == LIGHT_DIRECTIONAL) {
+        lightDir = -lightDirections[i].xyz;
+        return lightColors[i].rgb;
+    }
+
+    vec3 toLight = lightPositions[i].xyz - vsOut.fragPos;
+    float dist = max(length(toLight), 0.0001);
+    lightDir = toLight / dist;
+
+    // The inverse square law, smoothly cut off at the range
+    float attenuation = 1.0 / (dist * dist);
+    float range = lightDirections[i].w;
+    if (range > 0.0) {
+        attenuation *= clamp(1.0 - pow(dist / range, 4.0), 0.0, 1.0);
+    }
+
+    if (int(lightPositions[i].w) == LIGHT_SPOT) {
+        float cosAngle = dot(lightDirections[i].xyz, -lightDir);
+        float spot = clamp(cosAngle * lightSpots[i].x + lightSpots[i].y, 0.0, 1.0);
+        attenuation *= spot * spot;
+    }
+
+    return lightColors[i].rgb * attenuation;
+}
+
+// The diffuse and the specular term of a light - Blinn-Phong with the exponent derived from the roughness
+vec3 directLight(vec3 lightDir, vec3 norm, vec3 viewDir, vec3 diffuseColor, vec3 f0, float shininess) {
+    float diffuseK = max(dot(norm, lightDir), 0);
+    vec3 halfDir = normalize(lightDir + viewDir);
+    vec3 specular = f0 * pow(max(dot(norm, halfDir), 0), shininess) * diffuseK;
+
+    return diffuseColor * diffuseK + specular;
+}
+
+vec4 litColor(vec4 texColor) {
+    float metallic = metallicFactor;
+    float roughness = roughnessFactor;
+    if (hasMetallicRoughnessTex != 0) {
+        vec4 metallicRoughness = texture(metallicRoughnessTex, vsOut.texCoords);
+        roughness *= metallicRoughness[ROUGHNESS_CHANNEL];
+        metallic *= metallicRoughness[METALLIC_CHANNEL];
+    }
+
+    float occlusion = 1.0;
+    if (hasOcclusionTex != 0) {
+        float sampledOcclusion = texture(occlusionTex, vsOut.texCoords)[OCCLUSION_CHANNEL];
+        occlusion = mix(1.0, sampledOcclusion, occlusionStrength);
+    }
 
     // ambient
-    vec4 ambientColor = texColor * 0.4;
+    vec3 ambient = texColor.rgb * ambientColor * ambientIntensity * occlusion;
 
-    vec3 lightDir = normalize(lightPos - vsOut.fragPos);
     vec3 norm = normalize(vsOut.normal);
+    if (hasNormalTex != 0) {
+        norm = mappedNormal(norm);
+    }
 
-    // diffuse
-    float diffuseK = max(dot(norm, lightDir), 0);
-    vec4 diffuseColor = texColor * vec4(vec3(diffuseK), 1.0);
+    // metals don't have a diffuse component
+    vec3 diffuseColor = texColor.rgb * (1.0 - metallic);
+
+    vec3 cameraPos = inverse(view)[3].xyz;
+    vec3 viewDir = normalize(cameraPos - vsOut.fragPos);
+
+    float alpha = max(roughness * roughness, 0.01);
+    // pow(0, 0) is undefined
+    float shininess = max(2.0 / (alpha * alpha) - 2.0, 0.001);
+    vec3 f0 = mix(vec3(0.04), texColor.rgb, metallic);
+
+    // The ambient light isn't shadowed
+    int cascade = shadowCascade();
+    vec3 direct = vec3(0.0);
+    if (viewerLight != 0) {
+        vec3 lightDir = normalize(lightPos - vsOut.fragPos);
+        float shadow = shadowLight < 0 ? shadowFactor(cascade, norm, lightDir) : 1.0;
+        direct = directLight(lightDir, norm, viewDir, diffuseColor, f0, shininess) * shadow;
+    }
+
+    for (int i = 0; i < lightCount; i++) {
+        vec3 lightDir;
+        vec3 radiance = lightRadiance(i, lightDir);
+        float shadow = i == shadowLight ? shadowFactor(cascade, norm, lightDir) : 1.0;
+        direct += directLight(lightDir, norm, viewDir, diffuseColor, f0, shininess) * radiance * shadow;
+    }
+
+    vec3 color = ambient + direct + emissiveFactor;
+
+    if (cascadeDebug != 0 && cascade >= 0) {
+        color = mix(color, CASCADE_COLORS[cascade], 0.35);
+    }
+
+    return vec4(color, texColor.a);
+}
+
+void main() {
+    // The base color textures have an sRGB internal format, they are decoded to linear space when sampled
+    vec4 sampled = texture(myTexture, vsOut.texCoords);
+    if (gammaCorrect == 0) {
+        // Without the gamma correction the colors are shaded in their stored (sRGB) encoding
+        sampled.rgb = linearToSrgb(sampled.rgb);
+    }
+
+    // The base color factor and the vertex colors are linear
+    vec4 texColor = uvCheckerScale > 0.0
+        ? uvChecker()
+        : alphaMask(sampled * texBaseColorFactor * vsOut.color);
 
-    FragColor = ambientColor + diffuseColor;
+    switch (shadingMode) {
+        case SHADING_UNLIT:
+            FragColor = outputColor(texColor);
+            break;
+        case SHADING_NORMALS:
+            FragColor = vec4(normalize(vsOut.normal) * 0.5 + 0.5, 1.0);
+            break;
+        case SHADING_UVS:
+            FragColor = vec4(fract(vsOut.texCoords), 0.0, 1.0);
+            break;
+        case SHADING_WEIGHTS:
+        case SHADING_WEIGHT_PAINT:
+            FragColor = vec4(vsOut.weightsColor, 1.0);
+            break;
+        default:
+            // Unlit materials use the base color only
+            FragColor = isUnlit != 0 ? outputColor(texColor) : outputColor(litColor(texColor));
+            break;
+    }
 }
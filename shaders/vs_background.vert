@@ -0,0 +1,11 @@
+#version 420 core
+
+// Position of the fragment in normalized device coordinates
+out vec2 ndc;
+
+// A single triangle covering the whole viewport, generated from the vertex index
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    ndc = pos;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
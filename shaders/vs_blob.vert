@@ -0,0 +1,24 @@
+#version 420 core
+
+layout (std140, binding = 1) uniform Transforms {
+    mat4 projection;
+    mat4 view;
+    mat4 model;
+    // Inverse-transpose of the model matrix (only the upper 3x3 is used)
+    mat4 normalMatrix;
+};
+
+// World space position of the center of the blob on the ground
+uniform vec3 center;
+// Half-sizes of the blob along the X and Z axes
+uniform vec2 radii;
+
+// Position inside of the blob, the ellipse has a radius of 1
+out vec2 local;
+
+// A horizontal quad around the center, generated from the vertex index (triangle strip)
+void main() {
+    local = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1) * 2.0 - 1.0;
+    vec3 pos = center + vec3(local.x * radii.x, 0.0, local.y * radii.y);
+    gl_Position = projection * view * vec4(pos, 1.0);
+}
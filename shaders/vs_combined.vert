@@ -5,33 +5,87 @@ layout(location = 1) in vec2 inTexcoords;
 layout(location = 2) in vec3 inNormal;
 layout(location = 3) in uvec4 inJoints;
 layout(location = 4) in vec4 inWeights;
+// Linear vertex colors (white if the primitive doesn't have them)
+layout(location = 5) in vec4 inColor;
 
 layout (std140, binding = 1) uniform Transforms {
     mat4 projection;
     mat4 view;
     mat4 model;
+    // Inverse-transpose of the model matrix (only the upper 3x3 is used)
+    mat4 normalMatrix;
 };
 
 layout (std140, binding = 2) uniform JointMatrices {
     mat4 jointMatrices[256];
 };
 
+// Has to match the layout of SettingsStd140
 layout (std140, binding = 3) uniform Settings {
     int doSkinning;
+    int shadingMode;
+    int selectedJoint;
+    int flipV;
+    int gammaCorrect;
+    int applyVertexColors;
+    int alphaToCoverage;
+    float uvCheckerScale;
+    float pointSize;
+    int pointSizeAttenuation;
 };
 
 out VsOut {
     vec2 texCoords;
     vec3 normal;
     vec3 fragPos;
+    vec3 weightsColor;
+    vec4 color;
 } vsOut;
 
+// Has to match the value of ShadingMode::WeightPaint
+const int SHADING_WEIGHT_PAINT = 5;
+
+// Spreads the hues of the joints by the golden ratio, so that neighbouring joints get distinct colors
+vec3 jointColor(uint joint) {
+    float hue = fract(float(joint) * 0.618034);
+    return clamp(abs(mod(hue * 6.0 + vec3(0.0, 4.0, 2.0), 6.0) - 3.0) - 1.0, 0.0, 1.0);
+}
+
+// Color of the joint with the largest weight
+vec3 dominantJointColor() {
+    uint joint = inJoints.x;
+    float weight = inWeights.x;
+
+    for (int i = 1; i < 4; i++) {
+        if (inWeights[i] > weight) {
+            joint = inJoints[i];
+            weight = inWeights[i];
+        }
+    }
+
+    return jointColor(joint);
+}
+
+// Blue (weight 0) to red (weight 1) depending on the influence of the selected joint
+vec3 selectedJointWeightColor() {
+    float weight = 0.0;
+
+    for (int i = 0; i < 4; i++) {
+        if (inJoints[i] == uint(selectedJoint)) {
+            weight += inWeights[i];
+        }
+    }
+
+    return mix(vec3(0.0, 0.0, 1.0), vec3(1.0, 0.0, 0.0), weight);
+}
+
 void main() {
     // https://www.khronos.org/registry/glTF/specs/2.0/glTF-2.0.html#joint-hierarchy
     // "Only the joint transforms are applied to the skinned mesh; the transform of the
     // skinned mesh node MUST be ignored."
 
     mat4 modelTransform;
+    mat3 normalTransform;
 
     if (doSkinning == 1) {
         modelTransform =
@@ -39,13 +93,38 @@ void main() {
             (inWeights.y * jointMatrices[int(inJoints.y)]) +
             (inWeights.z * jointMatrices[int(inJoints.z)]) +
             (inWeights.w * jointMatrices[int(inJoints.w)]);
+
+        // The blended matrix differs for every vertex, so the normal matrix can't be precomputed
+        normalTransform = transpose(inverse(mat3(modelTransform)));
+
+        if (shadingMode == SHADING_WEIGHT_PAINT) {
+            vsOut.weightsColor = selectedJointWeightColor();
+        } else {
+            vsOut.weightsColor = dominantJointColor();
+        }
     } else {
         modelTransform = model;
+        normalTransform = mat3(normalMatrix);
+        vsOut.weightsColor = vec3(0.5);
     }
 
     gl_Position = projection * view * modelTransform * vec4(inPos, 1.0);
 
-    vsOut.texCoords = inTexcoords;
-    vsOut.normal = mat3(transpose(inverse(modelTransform))) * inNormal;
+    // Only used by the POINTS primitives, the attenuated size is the size at the distance of 1 (W is 1 in orthographic views)
+    if (pointSizeAttenuation != 0) {
+        gl_PointSize = max(pointSize / max(gl_Position.w, 0.001), 1.0);
+    } else {
+        gl_PointSize = pointSize;
+    }
+
+    if (flipV == 1) {
+        vsOut.texCoords = vec2(inTexcoords.x, 1.0 - inTexcoords.y);
+    } else {
+        vsOut.texCoords = inTexcoords;
+    }
+
+    vsOut.color = applyVertexColors != 0 ? inColor : vec4(1.0);
+    // Normalized in the fragment shaders, the normal matrix doesn't preserve the length
+    vsOut.normal = normalTransform * inNormal;
     vsOut.fragPos = vec3(modelTransform * vec4(inPos, 1.0));
 }
@@ -0,0 +1,10 @@
+#version 420 core
+
+out vec2 texCoords;
+
+// A single triangle covering the whole viewport, generated from the vertex index
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    texCoords = pos * 0.5 + 0.5;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
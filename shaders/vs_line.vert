@@ -0,0 +1,16 @@
+#version 420 core
+
+// World space position of the end of a line segment
+layout(location = 0) in vec3 inPos;
+
+layout (std140, binding = 1) uniform Transforms {
+    mat4 projection;
+    mat4 view;
+    mat4 model;
+    // Inverse-transpose of the model matrix (only the upper 3x3 is used)
+    mat4 normalMatrix;
+};
+
+void main() {
+    gl_Position = projection * view * vec4(inPos, 1.0);
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+/// Number of consecutive frames over the budget after which a feature is disabled
+const FRAMES_OVER_BUDGET: u32 = 30;
+/// Number of consecutive frames with headroom after which a feature is enabled again
+const FRAMES_WITH_HEADROOM: u32 = 180;
+/// Frames faster than this fraction of the target have enough headroom to enable a feature again
+const HEADROOM: f32 = 0.6;
+
+/// Rendering features that can be disabled automatically when the frames take too long.
+///
+/// New expensive passes should be added to `ALL` according to their cost.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QualityFeature {
+    /// The cascaded shadow maps (a depth pass over the model for every cascade)
+    ShadowMaps,
+    /// The wireframe overlay (an extra pass over every primitive)
+    Wireframe,
+    /// The FXAA post-process (the scene is rendered directly into the target)
+    Fxaa,
+    /// Anisotropic filtering of the textures (the textures are sampled with 1x)
+    Anisotropy,
+}
+
+impl QualityFeature {
+    /// The features in the order they are disabled (the most expensive first)
+    pub const ALL: [QualityFeature; 4] = [
+        QualityFeature::ShadowMaps,
+        QualityFeature::Wireframe,
+        QualityFeature::Fxaa,
+        QualityFeature::Anisotropy,
+    ];
+}
+
+/// Monitors the frame time and disables the expensive features one by one when
+/// it exceeds the target for several frames.
+///
+/// The features are enabled again (in the reverse order) when there is enough headroom.
+pub struct AutoQuality {
+    /// If the features are disabled automatically
+    pub enabled: bool,
+    /// The frame time budget in milliseconds
+    pub target_ms: f32,
+    /// Duration of the last frame in milliseconds
+    frame_ms: f32,
+    /// Features disabled automatically, in the order they were disabled
+    disabled: Vec<QualityFeature>,
+    /// Number of consecutive frames over the budget
+    frames_over: u32,
+    /// Number of consecutive frames with headroom
+    frames_under: u32,
+}
+
+impl AutoQuality {
+    pub fn new(target_ms: f32) -> Self {
+        Self {
+            enabled: false,
+            target_ms,
+            frame_ms: 0.,
+            disabled: Vec::new(),
+            frames_over: 0,
+            frames_under: 0,
+        }
+    }
+
+    /// Records the duration of the last frame and disables / enables a feature if needed
+    pub fn update(&mut self, frame_time: Duration) {
+        self.frame_ms = frame_time.as_secs_f32() * 1000.;
+
+        if !self.enabled {
+            self.disabled.clear();
+            self.frames_over = 0;
+            self.frames_under = 0;
+            return;
+        }
+
+        if self.frame_ms > self.target_ms {
+            self.frames_over += 1;
+            self.frames_under = 0;
+        } else if self.frame_ms < self.target_ms * HEADROOM {
+            self.frames_under += 1;
+            self.frames_over = 0;
+        } else {
+            self.frames_over = 0;
+            self.frames_under = 0;
+        }
+
+        if self.frames_over >= FRAMES_OVER_BUDGET {
+            self.frames_over = 0;
+
+            let next = QualityFeature::ALL
+                .into_iter()
+                .find(|feature| !self.disabled.contains(feature));
+
+            match next {
+                Some(feature) => {
+                    eprintln!(
+                        "WARN: frame time {:.1} ms exceeds the budget of {:.1} ms, disabling {feature:?}",
+                        self.frame_ms, self.target_ms
+                    );
+                    self.disabled.push(feature);
+                }
+                None => eprintln!(
+                    "WARN: frame time {:.1} ms exceeds the budget of {:.1} ms with all features disabled",
+                    self.frame_ms, self.target_ms
+                ),
+            }
+        }
+
+        if self.frames_under >= FRAMES_WITH_HEADROOM {
+            self.frames_under = 0;
+
+            if let Some(feature) = self.disabled.pop() {
+                println!("Frame time is within the budget again, enabling {feature:?}");
+            }
+        }
+    }
+
+    /// If the feature is currently disabled because of the frame time
+    pub fn is_disabled(&self, feature: QualityFeature) -> bool {
+        self.disabled.contains(&feature)
+    }
+
+    /// The features disabled automatically, in the order they were disabled
+    pub fn disabled(&self) -> &[QualityFeature] {
+        &self.disabled
+    }
+
+    /// Duration of the last frame in milliseconds
+    pub fn frame_ms(&self) -> f32 {
+        self.frame_ms
+    }
+}
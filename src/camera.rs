@@ -1,5 +1,7 @@
 use glam::{Mat4, Vec3};
 
+use crate::model::{Aabb, AssetCamera, CameraProjection};
+
 /// A component encapsulating the camera transformations
 pub struct Camera {
     /// Position of the camera
@@ -24,6 +26,18 @@ pub struct Camera {
     changed: bool,
     /// Cache of the view matrix
     view_matrix: Mat4,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Distance of the near plane
+    pub near: f32,
+    /// Distance of the far plane
+    pub far: f32,
+    /// Maps the near plane to depth 1 and the far plane to depth 0 for better depth precision
+    pub reversed_z: bool,
+    /// Uses an orthographic projection instead of the perspective one
+    pub orthographic: bool,
+    /// Half of the height of the orthographic view volume
+    pub ortho_half_height: f32,
 }
 
 impl Camera {
@@ -47,9 +61,58 @@ impl Camera {
             zenith: 0.,
             changed: true,
             view_matrix: Mat4::IDENTITY,
+            fov: 60.,
+            near: 0.1,
+            far: 3000.,
+            reversed_z: false,
+            orthographic: false,
+            ortho_half_height: 1.,
+        }
+    }
+
+    /// Returns the projection matrix for the specific aspect ratio
+    pub fn projection_mat(&self, aspect_ratio: f32) -> Mat4 {
+        if self.orthographic {
+            let h = self.ortho_half_height;
+            let w = h * aspect_ratio;
+
+            return if self.reversed_z {
+                Mat4::orthographic_rh(-w, w, -h, h, self.far, self.near)
+            } else {
+                Mat4::orthographic_rh(-w, w, -h, h, self.near, self.far)
+            };
+        }
+
+        let fov = self.fov.to_radians();
+
+        if self.reversed_z {
+            // Swapping the planes maps the near plane to 1 and the far plane to 0
+            Mat4::perspective_rh(fov, aspect_ratio, self.far, self.near)
+        } else {
+            Mat4::perspective_rh(fov, aspect_ratio, self.near, self.far)
         }
     }
 
+    /// Sets the near and far planes so that the whole bounding box is visible
+    /// while keeping the depth range as tight as possible
+    pub fn fit_planes_to_bounds(&mut self, aabb: &Aabb) {
+        if aabb.is_empty() {
+            return;
+        }
+
+        let corner_distances = aabb.corners().map(|c| c.distance(self.pos));
+        let farthest = corner_distances.iter().fold(0f32, |a, b| a.max(*b));
+
+        // Distance to the closest point of the box (0 if the camera is inside)
+        let closest = (aabb.min - self.pos)
+            .max(self.pos - aabb.max)
+            .max(Vec3::ZERO)
+            .length();
+
+        self.far = (farthest * 1.1).max(1.);
+        self.near = (closest * 0.9).max(self.far * 1e-5);
+    }
+
     /// Returns the view matrix (either cached or recomputed)
     pub fn view_mat(&mut self) -> Mat4 {
         if self.changed {
@@ -60,12 +123,151 @@ impl Camera {
         self.view_matrix
     }
 
+    /// Position of the camera
+    pub fn pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    /// Direction the camera is looking in
+    pub fn dir(&self) -> Vec3 {
+        self.dir
+    }
+
     /// Sets the position of the camera
     pub fn set_pos(&mut self, pos: Vec3) {
         self.pos = pos;
         self.changed = true;
     }
 
+    /// Places the camera in front of the bounding box (looking in the -Z direction)
+    /// so that the whole box is visible
+    pub fn frame_bounds(&mut self, aabb: &Aabb, aspect_ratio: f32) {
+        if aabb.is_empty() {
+            return;
+        }
+
+        self.set_dir(Vec3::new(0., 0., -1.));
+        self.focus_bounds(aabb, aspect_ratio);
+        self.fit_planes_to_bounds(aabb);
+    }
+
+    /// Moves the camera (keeping its direction) so that the whole bounding box is visible
+    pub fn focus_bounds(&mut self, aabb: &Aabb, aspect_ratio: f32) {
+        if aabb.is_empty() {
+            return;
+        }
+
+        let radius = (aabb.size().length() / 2.).max(1e-3);
+        let half_fov_y = self.fov.to_radians() / 2.;
+        let half_fov_x = (half_fov_y.tan() * aspect_ratio).atan();
+        let distance = radius / half_fov_y.min(half_fov_x).sin();
+
+        self.set_pos(aabb.center() - self.dir * distance);
+    }
+
+    /// Points the camera in the direction of `dir`.
+    ///
+    /// The vertical angle is clamped the same way as in `adjust_look`.
+    pub fn set_dir(&mut self, dir: Vec3) {
+        let dir = dir.normalize();
+
+        self.zenith = dir.y.asin().to_degrees().clamp(-89., 89.);
+        self.azimuth = dir.z.atan2(dir.x).to_degrees() - 270.;
+
+        self.adjust_dir();
+    }
+
+    /// Switches to the orthographic projection and looks at the bounding box in the direction of `dir`
+    /// so that the whole box fills the view.
+    ///
+    /// The top and bottom views are tilted by 1 degree, because the vertical angle
+    /// is clamped the same way as in `adjust_look`.
+    pub fn view_along_axis(&mut self, dir: Vec3, aabb: &Aabb, aspect_ratio: f32) {
+        if aabb.is_empty() {
+            return;
+        }
+
+        self.orthographic = true;
+        self.set_dir(dir);
+
+        let radius = (aabb.size().length() / 2.).max(1e-3);
+        let center = aabb.center();
+        self.set_pos(center - self.dir * radius * 2.);
+
+        // Extents of the box projected onto the view plane
+        let right = self.dir.cross(self.up).normalize();
+        let up = right.cross(self.dir);
+        let (half_width, half_height) = aabb.corners().iter().fold((0f32, 0f32), |(w, h), c| {
+            let offset = *c - center;
+            (w.max(offset.dot(right).abs()), h.max(offset.dot(up).abs()))
+        });
+
+        self.ortho_half_height = (half_height.max(half_width / aspect_ratio) * 1.05).max(1e-3);
+        self.fit_planes_to_bounds(aabb);
+    }
+
+    /// Horizontal angle of the view direction in degrees (0 = looking in the -Z direction)
+    pub fn yaw(&self) -> f32 {
+        self.azimuth.rem_euclid(360.)
+    }
+
+    /// Vertical angle of the view direction in degrees (positive = looking up)
+    pub fn pitch(&self) -> f32 {
+        self.zenith
+    }
+
+    /// Points the camera using the horizontal and vertical angles in degrees.
+    ///
+    /// The vertical angle is clamped the same way as in `adjust_look`.
+    pub fn set_yaw_pitch(&mut self, yaw: f32, pitch: f32) {
+        self.azimuth = yaw;
+        self.zenith = pitch.clamp(-89., 89.);
+
+        self.adjust_dir();
+    }
+
+    /// Moves the camera to the view of a camera defined in the gltf file of a model.
+    ///
+    /// The distances are scaled by the model transform, the aspect ratio of the window is kept.
+    pub fn use_asset_camera(&mut self, asset_camera: &AssetCamera, model_transform: Mat4) {
+        let dir = model_transform.transform_vector3(asset_camera.dir);
+        let scale = dir.length();
+        if scale < f32::EPSILON {
+            return;
+        }
+
+        self.set_pos(model_transform.transform_point3(asset_camera.pos));
+        self.set_dir(dir);
+
+        match asset_camera.projection {
+            CameraProjection::Perspective { yfov, znear, zfar } => {
+                self.orthographic = false;
+                self.fov = yfov.to_degrees();
+                self.near = znear * scale;
+                // Infinite projections keep the current far plane
+                if let Some(zfar) = zfar {
+                    self.far = zfar * scale;
+                }
+            }
+            CameraProjection::Orthographic { ymag, znear, zfar } => {
+                self.orthographic = true;
+                self.ortho_half_height = ymag * scale;
+                self.near = znear * scale;
+                self.far = zfar * scale;
+            }
+        }
+
+        self.far = self.far.max(self.near * 2.);
+    }
+
+    /// Moves the camera onto the `axis` so that it looks at the world origin from the same distance
+    pub fn snap_to_axis(&mut self, axis: Vec3) {
+        let distance = self.pos.length();
+
+        self.set_dir(-axis);
+        self.set_pos(-self.dir * distance);
+    }
+
     /// Moves the camera forward
     pub fn move_forward(&mut self, d: f32) {
         self.pos += self.dir * d * self.move_speed;
@@ -0,0 +1,175 @@
+use std::fs;
+
+use eyre::{Context, Result};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::{Handedness, NormalizeScale},
+    renderer::{FogMode, SpotLight, BACKGROUND_COLOR},
+};
+
+/// Path of the settings file (relative to the working directory like the resources)
+const CONFIG_PATH: &str = "leoric_settings.ron";
+
+/// Application settings persisted between runs.
+///
+/// Loaded at startup and saved on exit. Missing fields use the default values.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Size and position of the window, None lets the window pick its default size
+    pub window: Option<WindowConfig>,
+    /// If the buffer swaps are synchronized with the display refresh rate
+    pub vsync: bool,
+    /// Number of the MSAA samples of the window (0 disables the multisampling), applied at startup
+    pub msaa_samples: u8,
+    /// If the final image is smoothed by FXAA (a cheaper alternative to MSAA)
+    pub fxaa: bool,
+    /// If the animations stop while the window is minimized or unfocused (otherwise they keep running)
+    pub pause_animations_when_inactive: bool,
+    /// Models that were opened at runtime, they are loaded again at startup
+    pub opened_models: Vec<String>,
+    /// Conversions applied to the models opened at runtime
+    pub import_handedness: Handedness,
+    /// The common size the loaded models are scaled to, None keeps the authored scale
+    pub import_normalize_scale: Option<NormalizeScale>,
+    /// Saved camera views
+    pub camera_bookmarks: Vec<CameraBookmark>,
+    /// Toggles of the GUI
+    pub gui: GuiConfig,
+}
+
+impl AppConfig {
+    /// Loads the settings file.
+    ///
+    /// A missing or corrupt file falls back to the default settings.
+    pub fn load() -> Self {
+        let text = match fs::read_to_string(CONFIG_PATH) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+
+        match ron::from_str(&text) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("WARN: couldn't parse the settings file '{CONFIG_PATH}', using the defaults: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Saves the settings file
+    pub fn save(&self) -> Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .wrap_err("Couldn't serialize the settings")?;
+
+        fs::write(CONFIG_PATH, text)
+            .wrap_err_with(|| format!("Couldn't save the settings file '{CONFIG_PATH}'"))
+    }
+}
+
+/// Size and position of the window
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A saved position and direction of the camera
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub pos: Vec3,
+    pub dir: Vec3,
+}
+
+/// The persisted toggles of the GUI
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiConfig {
+    pub draw_skeleton: bool,
+    pub mesh_visible: bool,
+    pub wireframe: bool,
+    pub wireframe_color: [f32; 4],
+    pub wireframe_width: f32,
+    pub overlay_line_width: f32,
+    pub ambient_color: [f32; 3],
+    pub ambient_intensity: f32,
+    pub depth_test: bool,
+    pub face_culling: bool,
+    pub crossfade: bool,
+    pub crossfade_duration: f32,
+    pub snap_to_keyframes: bool,
+    pub anisotropy: f32,
+    pub dark_mode: bool,
+    pub gamma_correct: bool,
+    pub apply_vertex_colors: bool,
+    pub alpha_to_coverage: bool,
+    pub fog_mode: FogMode,
+    pub fog_color: [f32; 3],
+    pub fog_density: f32,
+    pub fog_start: f32,
+    pub fog_end: f32,
+    pub shadows: bool,
+    pub shadow_cascades: usize,
+    pub shadow_split_lambda: f32,
+    pub shadow_distance: f32,
+    pub point_size: f32,
+    pub point_size_attenuation: bool,
+    pub blob_shadow: bool,
+    pub blob_shadow_opacity: f32,
+    pub background_path: Option<String>,
+    pub background_rotation: f32,
+    pub background_exposure: f32,
+    pub asset_lights: bool,
+    pub asset_light_intensity: f32,
+    pub spot_lights: Vec<SpotLight>,
+}
+
+impl Default for GuiConfig {
+    fn default() -> Self {
+        Self {
+            draw_skeleton: false,
+            mesh_visible: true,
+            wireframe: false,
+            wireframe_color: [0.9, 0.9, 0.9, 1.],
+            wireframe_width: 1.,
+            overlay_line_width: 2.,
+            ambient_color: [1., 1., 1.],
+            ambient_intensity: 0.4,
+            depth_test: true,
+            face_culling: true,
+            crossfade: false,
+            crossfade_duration: 0.3,
+            snap_to_keyframes: false,
+            anisotropy: 1.,
+            dark_mode: true,
+            gamma_correct: false,
+            apply_vertex_colors: true,
+            alpha_to_coverage: true,
+            fog_mode: FogMode::Off,
+            // Distant models fade into the background
+            fog_color: BACKGROUND_COLOR,
+            fog_density: 0.05,
+            fog_start: 10.,
+            fog_end: 50.,
+            shadows: false,
+            shadow_cascades: 3,
+            shadow_split_lambda: 0.75,
+            shadow_distance: 30.,
+            point_size: 4.,
+            point_size_attenuation: false,
+            blob_shadow: false,
+            blob_shadow_opacity: 0.5,
+            background_path: None,
+            background_rotation: 0.,
+            background_exposure: 0.,
+            asset_lights: true,
+            asset_light_intensity: 1.,
+            spot_lights: Vec::new(),
+        }
+    }
+}
@@ -1,13 +1,38 @@
-use std::time::Instant;
+use std::collections::VecDeque;
 
-use egui::{CollapsingHeader, CtxRef, RichText, Slider, Ui};
-use glam::{Quat, Vec3};
+use egui::{
+    Align2, CollapsingHeader, Color32, CtxRef, RichText, Sense, Slider, Stroke, TextStyle, Ui,
+};
+use glam::{Mat3, Quat, Vec3};
+use gltf::animation::Interpolation;
 
 use crate::{
+    auto_quality::AutoQuality,
     camera::Camera,
-    model::{AnimationControl, Animations, Joint, Model, Node},
+    config::{AppConfig, CameraBookmark},
+    model::{
+        Aabb, Animation, AnimationControl, AnimationMarker, Animations, BlendPoint, Handedness,
+        ImportOptions, Joint, JointAxis, LightKind, LookAt, MirrorAxis, Model, Node, NormalMode,
+        NormalizeScale, UpAxis,
+    },
+    ogl::{self, uniform_buffer::UniformBufferElement},
+    recording::ClipRecording,
+    renderer::{
+        CulledFace, FogMode, PickTarget, Renderer, Settings, ShadingMode, SpotLight, MAX_CASCADES,
+        MAX_LIGHTS,
+    },
+    scene_state::SceneState,
+    window::MyWindow,
 };
 
+use self::timeline::{clip_overview, timeline};
+
+/// A timeline scrubber widget for animations.
+mod timeline;
+
+/// Number of the recent animation events shown in the animations panel
+const RECENT_EVENTS: usize = 5;
+
 /// Contains the current state of the GUI.
 /// Implements methods for displaying the widgets.
 pub struct Gui {
@@ -17,241 +42,2344 @@ pub struct Gui {
     pub draw_skeleton: bool,
     /// If the mesh should be visible
     pub mesh_visible: bool,
+    /// If the edges of the mesh should be drawn over it
+    pub wireframe: bool,
+    /// Color of the wireframe lines
+    pub wireframe_color: [f32; 4],
+    /// Width of the wireframe lines (clamped to the supported range)
+    pub wireframe_width: f32,
+    /// Width of the lines of the debug overlays (bones, bounding boxes) in pixels
+    pub overlay_line_width: f32,
+    /// Debug shading mode of the meshes
+    pub shading_mode: ShadingMode,
+    /// If the shading is computed in linear space (sRGB decoded textures and sRGB encoded output)
+    pub gamma_correct: bool,
+    /// If the base color is multiplied by the vertex colors (COLOR_0)
+    pub apply_vertex_colors: bool,
+    /// If the base color is replaced by a checkerboard in the UV space
+    pub uv_checker: bool,
+    /// Number of the cells of the UV checkerboard per UV unit
+    pub uv_checker_scale: f32,
+    /// Color of the constant ambient light
+    pub ambient_color: [f32; 3],
+    /// Multiplier of the ambient light color
+    pub ambient_intensity: f32,
+    /// Fading of the distant fragments (depth cueing)
+    pub fog_mode: FogMode,
+    /// Color of the fog
+    pub fog_color: [f32; 3],
+    /// Density of the exponential fog
+    pub fog_density: f32,
+    /// Distance where the linear fog starts
+    pub fog_start: f32,
+    /// Distance where the linear fog fully covers the models
+    pub fog_end: f32,
+    /// If the lit primitives are shadowed by the model (cascaded shadow maps)
+    pub shadows: bool,
+    /// Number of the shadow cascades the view frustum is split into
+    pub shadow_cascades: usize,
+    /// Blend between the uniform (0) and the logarithmic (1) distribution of the cascade splits
+    pub shadow_split_lambda: f32,
+    /// Distance from the camera covered by the shadows
+    pub shadow_distance: f32,
+    /// If the fragments are tinted by the color of their shadow cascade
+    pub shadow_cascade_debug: bool,
+    /// Size of the points of the POINTS primitives (point clouds) in pixels
+    pub point_size: f32,
+    /// If the size of the points decreases with their distance (it's the size at the distance of 1)
+    pub point_size_attenuation: bool,
+    /// If a soft dark ellipse is drawn on the ground below the model (cheaper than the shadow maps)
+    pub blob_shadow: bool,
+    /// Darkness of the center of the blob shadow
+    pub blob_shadow_opacity: f32,
+    /// Path of the equirectangular HDR image drawn behind the models
+    pub background_path: Option<String>,
+    /// Rotation of the background around the vertical axis in degrees
+    pub background_rotation: f32,
+    /// Exposure of the background in stops
+    pub background_exposure: f32,
+    /// If the lights of the gltf file replace the light of the viewer (if the model has any)
+    pub asset_lights: bool,
+    /// Multiplier of the intensity of the asset lights
+    pub asset_light_intensity: f32,
+    /// Spot lights placed by the user, added to the other lights
+    pub spot_lights: Vec<SpotLight>,
+    /// If depth testing is enabled
+    pub depth_test: bool,
+    /// If face culling is enabled
+    pub face_culling: bool,
+    /// If the primitives outside of the view frustum are skipped
+    pub frustum_culling: bool,
+    /// If the bounding box of the selected model (in the bind pose) should be drawn
+    pub draw_model_bounds: bool,
+    /// If the bounds of the primitives used for the frustum culling should be drawn
+    pub draw_primitive_bounds: bool,
+    /// If the recent path of the root of the selected model should be drawn
+    pub draw_root_trail: bool,
+    /// Number of the frames kept in the root trail
+    pub root_trail_length: usize,
+    /// Color of the newest part of the root trail
+    pub root_trail_color: [f32; 4],
+    /// Color of the bounding boxes inside of the view frustum
+    pub bounds_color: [f32; 4],
+    /// Color of the bounding boxes outside of the view frustum
+    pub culled_bounds_color: [f32; 4],
+    /// Which faces are culled (when culling is enabled and the skeleton isn't drawn)
+    pub culled_face: CulledFace,
+    /// Index of the joint selected in the joints window (highlighted by the debug views)
+    pub selected_joint: usize,
+    /// Text of the node search box in the model hierarchy window
+    node_search: String,
+    /// The primitive of the selected model that was clicked on
+    pub picked: Option<PickTarget>,
+    /// If switching animation clips should crossfade instead of resetting the pose
+    pub crossfade: bool,
+    /// Duration of the crossfade in seconds
+    pub crossfade_duration: f32,
+    /// If scrubbing the timeline should snap to keyframes
+    pub snap_to_keyframes: bool,
+    /// Time the animation moves by when stepping frame by frame (in milliseconds)
+    pub frame_step_ms: f32,
+    /// If the looping animations advance by a fixed step every frame instead of the real elapsed time
+    /// (reproducible playback for recording frame sequences)
+    pub fixed_timestep: bool,
+    /// The step of the fixed timestep playback (in milliseconds)
+    pub fixed_timestep_ms: f32,
+    /// The clip being recorded into an image sequence (with the fixed timestep)
+    pub recording: Option<ClipRecording>,
+    /// Interpolation forcing all animation channels (for comparison), None uses the authored ones
+    pub interpolation_override: Option<Interpolation>,
+    /// Debugging switches - disabled animation channels use the rest pose of the nodes
+    pub disable_translation: bool,
+    pub disable_rotation: bool,
+    pub disable_scale: bool,
+    /// Anisotropic filtering level of the textures (clamped to the hardware maximum)
+    pub anisotropy: f32,
+    /// The highest anisotropy level supported by the hardware, None if it's unsupported
+    max_anisotropy: Option<f32>,
+    /// Disables expensive features when the frames exceed the time budget
+    pub auto_quality: AutoQuality,
+    /// Message shown in the notification window (errors of loading models)
+    notification: Option<String>,
+    /// If the render settings window is open
+    render_settings_open: bool,
+    /// If the window with the GPU and driver info is open
+    system_info_open: bool,
+    /// If the buffer swaps are synchronized with the display refresh rate
+    pub vsync: bool,
+    /// Number of the MSAA samples of the window, applied after a restart
+    pub msaa_samples: u8,
+    /// If the final image is smoothed by FXAA (the MSAA samples are 0 then)
+    pub fxaa: bool,
+    /// If the masked (cutout) materials are smoothed by alpha-to-coverage when MSAA is active
+    pub alpha_to_coverage: bool,
+    /// If the animations stop while the window is minimized or unfocused
+    pub pause_animations_when_inactive: bool,
+    /// Paths of the models opened at runtime
+    pub opened_models: Vec<String>,
+    /// Conversions applied to the opened models (left-handed / mirrored assets)
+    pub import_handedness: Handedness,
+    /// The common size the loaded models are scaled to, None keeps the authored scale
+    pub import_normalize_scale: Option<NormalizeScale>,
+    /// Saved camera views
+    pub camera_bookmarks: Vec<CameraBookmark>,
+    /// Descriptions of the last animation events of all models, newest last
+    recent_events: VecDeque<String>,
+    /// Joint names of the retargeting source and the names of the matching joints of the selected model
+    retarget_names: Vec<(String, String)>,
 }
 
 impl Gui {
     pub fn new() -> Self {
+        Self::with_config(&AppConfig::default())
+    }
+
+    /// Creates the GUI state with the toggles restored from the settings
+    pub fn with_config(config: &AppConfig) -> Self {
+        let gui = &config.gui;
+
         Self {
             selected_model: 0,
-            draw_skeleton: false,
-            mesh_visible: true,
+            draw_skeleton: gui.draw_skeleton,
+            mesh_visible: gui.mesh_visible,
+            wireframe: gui.wireframe,
+            wireframe_color: gui.wireframe_color,
+            wireframe_width: gui.wireframe_width,
+            overlay_line_width: gui.overlay_line_width,
+            shading_mode: ShadingMode::Lit,
+            gamma_correct: gui.gamma_correct,
+            apply_vertex_colors: gui.apply_vertex_colors,
+            uv_checker: false,
+            uv_checker_scale: 8.,
+            alpha_to_coverage: gui.alpha_to_coverage,
+            ambient_color: gui.ambient_color,
+            ambient_intensity: gui.ambient_intensity,
+            fog_mode: gui.fog_mode,
+            fog_color: gui.fog_color,
+            fog_density: gui.fog_density,
+            fog_start: gui.fog_start,
+            fog_end: gui.fog_end,
+            shadows: gui.shadows,
+            shadow_cascades: gui.shadow_cascades,
+            shadow_split_lambda: gui.shadow_split_lambda,
+            shadow_distance: gui.shadow_distance,
+            shadow_cascade_debug: false,
+            point_size: gui.point_size,
+            point_size_attenuation: gui.point_size_attenuation,
+            blob_shadow: gui.blob_shadow,
+            blob_shadow_opacity: gui.blob_shadow_opacity,
+            background_path: gui.background_path.clone(),
+            background_rotation: gui.background_rotation,
+            background_exposure: gui.background_exposure,
+            asset_lights: gui.asset_lights,
+            asset_light_intensity: gui.asset_light_intensity,
+            spot_lights: gui.spot_lights.clone(),
+            depth_test: gui.depth_test,
+            face_culling: gui.face_culling,
+            frustum_culling: true,
+            draw_model_bounds: false,
+            draw_primitive_bounds: false,
+            draw_root_trail: false,
+            root_trail_length: 300,
+            root_trail_color: [1.0, 0.6, 0.1, 1.0],
+            bounds_color: [0.2, 0.9, 0.2, 1.0],
+            culled_bounds_color: [0.9, 0.2, 0.2, 1.0],
+            culled_face: CulledFace::Back,
+            selected_joint: 0,
+            node_search: String::new(),
+            picked: None,
+            crossfade: gui.crossfade,
+            crossfade_duration: gui.crossfade_duration,
+            snap_to_keyframes: gui.snap_to_keyframes,
+            frame_step_ms: 1000. / 60.,
+            fixed_timestep: false,
+            fixed_timestep_ms: 1000. / 60.,
+            recording: None,
+            interpolation_override: None,
+            disable_translation: false,
+            disable_rotation: false,
+            disable_scale: false,
+            anisotropy: gui.anisotropy,
+            max_anisotropy: ogl::max_anisotropy(),
+            auto_quality: AutoQuality::new(1000. / 30.),
+            notification: None,
+            render_settings_open: false,
+            system_info_open: false,
+            vsync: config.vsync,
+            msaa_samples: config.msaa_samples,
+            fxaa: config.fxaa,
+            pause_animations_when_inactive: config.pause_animations_when_inactive,
+            opened_models: config.opened_models.clone(),
+            import_handedness: config.import_handedness,
+            import_normalize_scale: config.import_normalize_scale,
+            camera_bookmarks: config.camera_bookmarks.clone(),
+            recent_events: VecDeque::new(),
+            retarget_names: Vec::new(),
         }
     }
 
+    /// Stores the persisted parts of the GUI state into the settings
+    pub fn save_config(&self, config: &mut AppConfig) {
+        config.vsync = self.vsync;
+        config.msaa_samples = self.msaa_samples;
+        config.fxaa = self.fxaa;
+        config.pause_animations_when_inactive = self.pause_animations_when_inactive;
+        config.opened_models = self.opened_models.clone();
+        config.import_handedness = self.import_handedness;
+        config.import_normalize_scale = self.import_normalize_scale;
+        config.camera_bookmarks = self.camera_bookmarks.clone();
+
+        let gui = &mut config.gui;
+        gui.draw_skeleton = self.draw_skeleton;
+        gui.mesh_visible = self.mesh_visible;
+        gui.wireframe = self.wireframe;
+        gui.wireframe_color = self.wireframe_color;
+        gui.wireframe_width = self.wireframe_width;
+        gui.overlay_line_width = self.overlay_line_width;
+        gui.ambient_color = self.ambient_color;
+        gui.ambient_intensity = self.ambient_intensity;
+        gui.fog_mode = self.fog_mode;
+        gui.fog_color = self.fog_color;
+        gui.fog_density = self.fog_density;
+        gui.fog_start = self.fog_start;
+        gui.fog_end = self.fog_end;
+        gui.shadows = self.shadows;
+        gui.shadow_cascades = self.shadow_cascades;
+        gui.shadow_split_lambda = self.shadow_split_lambda;
+        gui.shadow_distance = self.shadow_distance;
+        gui.point_size = self.point_size;
+        gui.point_size_attenuation = self.point_size_attenuation;
+        gui.blob_shadow = self.blob_shadow;
+        gui.blob_shadow_opacity = self.blob_shadow_opacity;
+        gui.background_path = self.background_path.clone();
+        gui.background_rotation = self.background_rotation;
+        gui.background_exposure = self.background_exposure;
+        gui.asset_lights = self.asset_lights;
+        gui.asset_light_intensity = self.asset_light_intensity;
+        gui.spot_lights = self.spot_lights.clone();
+        gui.depth_test = self.depth_test;
+        gui.face_culling = self.face_culling;
+        gui.crossfade = self.crossfade;
+        gui.crossfade_duration = self.crossfade_duration;
+        gui.snap_to_keyframes = self.snap_to_keyframes;
+        gui.anisotropy = self.anisotropy;
+        gui.gamma_correct = self.gamma_correct;
+        gui.apply_vertex_colors = self.apply_vertex_colors;
+        gui.alpha_to_coverage = self.alpha_to_coverage;
+    }
+
     /// Creates the GUI.
     ///
     /// Immediate mode GUI - is called every frame.
-    pub fn create_gui(&mut self, scene: &mut [Model], camera: &mut Camera, egui_ctx: &mut CtxRef) {
-        self.gui_model_hierarchy_window(scene, egui_ctx);
+    pub fn create_gui(
+        &mut self,
+        scene: &mut Vec<Model>,
+        camera: &mut Camera,
+        renderer: &Renderer,
+        egui_ctx: &mut CtxRef,
+    ) {
+        self.collect_animation_events(scene);
+        self.gui_render_settings_window(&scene[self.selected_model], renderer, egui_ctx);
+        self.gui_model_hierarchy_window(scene, camera, egui_ctx);
         self.gui_joints_window(&mut scene[self.selected_model], egui_ctx);
         self.gui_side_panel(scene, camera, egui_ctx);
+        self.gui_axis_gizmo(camera, egui_ctx);
+        self.handle_view_hotkeys(&scene[self.selected_model], camera, egui_ctx);
+        self.handle_step_hotkeys(&mut scene[self.selected_model], egui_ctx);
+        self.gui_system_info_window(renderer, egui_ctx);
+        self.gui_notification(egui_ctx);
     }
 
-    /// Create the subwindow containing the model hierarchy
-    fn gui_model_hierarchy_window(&mut self, scene: &[Model], egui_ctx: &mut CtxRef) {
-        let model = &scene[self.selected_model];
-
-        egui::Window::new("Model Hierarchy")
-            .scroll2([false, true])
-            .resizable(true)
-            .show(egui_ctx, |ui| {
-                self.gui_node(&model.root, ui);
-            });
-    }
+    /// Handles the hotkeys of the orthographic views of the selected model (like in Blender):
+    /// - 1 / Ctrl+1 - front / back
+    /// - 3 / Ctrl+3 - right / left
+    /// - 7 / Ctrl+7 - top / bottom
+    /// - 5 - toggles between the orthographic and the perspective projection
+    ///
+    /// The numpad keys are translated to the same keys.
+    fn handle_view_hotkeys(&self, model: &Model, camera: &mut Camera, egui_ctx: &CtxRef) {
+        if egui_ctx.wants_keyboard_input() {
+            return;
+        }
 
-    /// Recusrive - creates the node hierarchy inside the model hierarchy window
-    fn gui_node(&mut self, node: &Node, ui: &mut Ui) {
-        let default_open = node.children.len() == 1;
+        let input = egui_ctx.input();
+        let opposite = input.modifiers.ctrl;
+        let screen_rect = input.screen_rect();
+        let aspect_ratio = screen_rect.width() / screen_rect.height();
 
-        ui.horizontal(|ui| {
-            if !&node.children.is_empty() {
-                CollapsingHeader::new(&node.name)
-                    .id_source(node.index)
-                    .default_open(default_open)
-                    .selectable(true)
-                    .show(ui, |ui| {
-                        for child_node in &node.children {
-                            self.gui_node(child_node, ui);
-                        }
-                    });
-            } else {
-                ui.label(&node.name);
+        let axis = if input.key_pressed(egui::Key::Num1) {
+            Vec3::Z
+        } else if input.key_pressed(egui::Key::Num3) {
+            Vec3::X
+        } else if input.key_pressed(egui::Key::Num7) {
+            Vec3::Y
+        } else {
+            if input.key_pressed(egui::Key::Num5) {
+                camera.orthographic = !camera.orthographic;
             }
 
-            if let Some(mesh) = &node.mesh {
-                ui.separator();
+            return;
+        };
 
-                let mesh_name = mesh.name.as_deref().unwrap_or("N/A");
-                ui.add(egui::Label::new(mesh_name));
+        // The camera is placed on the side of the axis and looks back
+        let dir = if opposite { axis } else { -axis };
+        camera.view_along_axis(dir, &model.bounds(), aspect_ratio);
+    }
 
-                ui.end_row()
-            }
-        });
+    /// Handles the hotkeys stepping the active animation of the selected model frame by frame:
+    /// - Right arrow - one frame forward
+    /// - Left arrow - one frame back
+    fn handle_step_hotkeys(&self, model: &mut Model, egui_ctx: &CtxRef) {
+        if egui_ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let input = egui_ctx.input();
+        let step = self.frame_step_ms / 1000.;
+
+        if input.key_pressed(egui::Key::ArrowRight) {
+            model.animations.step(step);
+        } else if input.key_pressed(egui::Key::ArrowLeft) {
+            model.animations.step(-step);
+        }
     }
 
-    fn gui_joints_window(&mut self, model: &mut Model, egui_ctx: &mut CtxRef) {
-        self.gui_joints_window_helper(&mut model.root, &mut model.animations, egui_ctx);
+    /// Drains the animation events of all models into the list of the recent events
+    fn collect_animation_events(&mut self, scene: &mut [Model]) {
+        for model in scene {
+            let animations = &mut model.animations;
+
+            for event in animations.events.drain(..) {
+                if self.recent_events.len() == RECENT_EVENTS {
+                    self.recent_events.pop_front();
+                }
+
+                let animation = animations.animations[event.animation]
+                    .name
+                    .as_deref()
+                    .unwrap_or("N/A");
+
+                self.recent_events.push_back(format!(
+                    "{} ({animation}): '{}' at {:.2} s",
+                    model.name, event.label, event.time
+                ));
+            }
+        }
     }
 
-    /// Recursive - creates the joints window and it's nodes
-    fn gui_joints_window_helper(
+    /// Creates the window editing the rendering settings uniform buffer.
+    ///
+    /// The renderer uploads the edited values in the next frame.
+    fn gui_render_settings_window(
         &mut self,
-        node: &mut Node,
-        animations: &mut Animations,
+        model: &Model,
+        renderer: &Renderer,
         egui_ctx: &mut CtxRef,
     ) {
-        if let Some(joints) = &mut node.joints {
-            egui::Window::new("Joints").show(egui_ctx, |ui| {
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    for joint in joints.joints.iter_mut() {
-                        let joint_name = &joint.name;
+        let joint_count = model
+            .nodes()
+            .find_map(|(node, _)| node.joints.as_ref())
+            .map(|joints| joints.joints.len())
+            .unwrap_or(0);
 
-                        // FIXME: for some reason there is an ID collision when rendering the droid model... maybe a bug in egui ?
-                        CollapsingHeader::new(joint_name).show(ui, |ui| {
-                            Self::show_joint_transforms(joint, animations, ui);
-                        });
+        egui::Window::new("Render Settings")
+            .open(&mut self.render_settings_open)
+            .resizable(false)
+            .show(egui_ctx, |ui| {
+                egui::ComboBox::from_label("Shading")
+                    .selected_text(format!("{:?}", self.shading_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in ShadingMode::ALL {
+                            ui.selectable_value(&mut self.shading_mode, mode, format!("{mode:?}"));
+                        }
+                    });
+
+                ui.checkbox(&mut self.gamma_correct, "Gamma-correct shading")
+                    .on_hover_text(
+                        "Shades in linear space and encodes the output to sRGB, vertex colors stay linear",
+                    );
+
+                ui.checkbox(&mut self.apply_vertex_colors, "Apply vertex colors")
+                    .on_hover_text("Multiplies the base color (and the textures) by COLOR_0");
+
+                let msaa_active = renderer.msaa_samples() > 1;
+                ui.add_enabled(
+                    msaa_active,
+                    egui::Checkbox::new(&mut self.alpha_to_coverage, "Alpha to coverage"),
+                )
+                .on_hover_text("Smooths the edges of the cutout (MASK) materials")
+                .on_disabled_hover_text("Requires MSAA (in the side panel)");
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.uv_checker, "UV checker")
+                        .on_hover_text("Replaces the base color by a checkerboard in the UV space");
+                    ui.add_enabled(
+                        self.uv_checker,
+                        Slider::new(&mut self.uv_checker_scale, 1.0..=64.0)
+                            .logarithmic(true)
+                            .text("Cells per UV"),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(
+                        Slider::new(&mut self.point_size, 1.0..=64.0)
+                            .logarithmic(true)
+                            .text("Point size"),
+                    )
+                    .on_hover_text("Size of the points of the point clouds (POINTS primitives) in pixels");
+                    ui.checkbox(&mut self.point_size_attenuation, "Attenuate")
+                        .on_hover_text("Divides the size by the distance, the slider sets the size at 1 unit");
+                });
+
+                ui.add_enabled(
+                    joint_count > 0,
+                    Slider::new(&mut self.selected_joint, 0..=joint_count.saturating_sub(1))
+                        .text("Selected joint"),
+                );
+
+                ui.checkbox(&mut self.frustum_culling, "Frustum culling")
+                    .on_hover_text("Skinned meshes are bounded by spheres around their joints");
+                ui.label(format!(
+                    "Culled primitives: {}",
+                    renderer.culled_primitives()
+                ));
+                ui.label(format!(
+                    "Model: {} triangles, {} vertices",
+                    model.triangle_count, model.vertex_count
+                ));
+
+                let state_changes = renderer.state_changes();
+                ui.label(format!(
+                    "Material uploads: {} (skipped {})",
+                    state_changes.material_uploads, state_changes.skipped_material_uploads
+                ));
+                ui.label(format!(
+                    "Texture binds: {} (skipped {})",
+                    state_changes.texture_binds, state_changes.skipped_texture_binds
+                ));
+                ui.label(format!(
+                    "Mirrored meshes: {}",
+                    state_changes.front_face_flips
+                ))
+                .on_hover_text("Drawn with clockwise front faces");
+
+                ui.separator();
+
+                let settings = renderer.settings();
+                ui.label(format!(
+                    "Skinning: {} (set for every mesh)",
+                    if settings.do_skinning { "on" } else { "off" }
+                ));
+
+                ui.separator();
+
+                let block = settings.std140();
+                let bytes = block.as_bytes();
+                ui.label(format!(
+                    "Uniform block 'Settings' - binding {}, {} bytes",
+                    Settings::BINDING,
+                    bytes.len()
+                ));
+
+                let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+                for row in bytes.chunks(16) {
+                    ui.monospace(row.join(" "));
+                }
+            });
+    }
+
+    /// Creates the window with the description of the GPU and the driver (for the bug reports)
+    fn gui_system_info_window(&mut self, renderer: &Renderer, egui_ctx: &mut CtxRef) {
+        let info = renderer.gpu_info();
+
+        egui::Window::new("System")
+            .open(&mut self.system_info_open)
+            .resizable(false)
+            .show(egui_ctx, |ui| {
+                egui::Grid::new("gpu_info").num_columns(2).show(ui, |ui| {
+                    for (name, value) in [
+                        ("Renderer", &info.renderer),
+                        ("Vendor", &info.vendor),
+                        ("Version", &info.version),
+                        ("GLSL version", &info.glsl_version),
+                    ] {
+                        ui.label(name);
+                        ui.label(value);
+                        ui.end_row();
                     }
                 });
+
+                ui.separator();
+
+                egui::Grid::new("gpu_extensions")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (ext, supported) in &info.extensions {
+                            ui.label(*ext);
+                            match supported {
+                                true => ui.label(RichText::new("yes").color(Color32::GREEN)),
+                                false => ui.label(RichText::new("no").color(Color32::LIGHT_RED)),
+                            };
+                            ui.end_row();
+                        }
+                    });
             });
-        } else {
-            // I assume there is only 1 skeleton in the models we are going to work with
-            for child_node in &mut node.children {
-                self.gui_joints_window_helper(child_node, animations, egui_ctx);
-            }
-        }
     }
 
-    /// Creates the gui for transforms of a specific joint
-    fn show_joint_transforms(joint: &mut Joint, animations: &mut Animations, ui: &mut Ui) {
-        let trans = &mut joint.transform.translation;
-        let (axis, angle) = joint.transform.rotation.to_axis_angle();
-        let mut angle = angle.to_degrees();
+    /// Creates the notification window if there is a message to show
+    fn gui_notification(&mut self, egui_ctx: &mut CtxRef) {
+        let message = match &self.notification {
+            Some(message) => message,
+            None => return,
+        };
 
-        let response = ui.group(|ui| {
-            ui.label("Translation");
-            ui.horizontal(|ui| {
-                ui.label("x");
-                ui.add(egui::DragValue::new(&mut trans.x).speed(0.03));
-                ui.label("y");
-                ui.add(egui::DragValue::new(&mut trans.y).speed(0.03));
-                ui.label("z");
-                ui.add(egui::DragValue::new(&mut trans.z).speed(0.03));
+        let mut dismissed = false;
+        egui::Window::new("Notification")
+            .anchor(Align2::CENTER_TOP, [0., 10.])
+            .collapsible(false)
+            .resizable(false)
+            .show(egui_ctx, |ui| {
+                ui.label(RichText::new(message).color(Color32::LIGHT_RED));
+
+                if ui.button("OK").clicked() {
+                    dismissed = true;
+                }
             });
 
-            ui.label("Rotation");
-            ui.horizontal(|ui| {
-                ui.label("angle");
-                ui.add(
-                    egui::DragValue::new(&mut angle)
-                        .speed(1.0)
-                        .clamp_range((0.1)..=(359.9)),
-                );
+        if dismissed {
+            self.notification = None;
+        }
+    }
+
+    /// Adding, editing and removing the spot lights of the user.
+    ///
+    /// The new spots shine from the camera in its direction, like a flashlight.
+    fn show_spot_lights(&mut self, camera: &Camera, ui: &mut Ui) {
+        CollapsingHeader::new(format!("Spot lights ({})", self.spot_lights.len()))
+            .id_source("spot_lights")
+            .show(ui, |ui| {
+                if ui.button("Add at the camera").clicked() {
+                    self.spot_lights
+                        .push(SpotLight::new(camera.pos(), camera.dir()));
+                }
+
+                let mut removed = None;
+                let mut shadow_caster = None;
+                for (i, spot) in self.spot_lights.iter_mut().enumerate() {
+                    CollapsingHeader::new(format!("Spot {}", i + 1))
+                        .id_source(("spot_light", i))
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut spot.enabled, "Enabled");
+                                ui.color_edit_button_rgb(&mut spot.color);
+                            });
+
+                            ui.add_enabled_ui(spot.enabled, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Position");
+                                    ui.add(egui::DragValue::new(&mut spot.pos.x).speed(0.05));
+                                    ui.add(egui::DragValue::new(&mut spot.pos.y).speed(0.05));
+                                    ui.add(egui::DragValue::new(&mut spot.pos.z).speed(0.05));
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Direction");
+                                    ui.add(egui::DragValue::new(&mut spot.dir.x).speed(0.01));
+                                    ui.add(egui::DragValue::new(&mut spot.dir.y).speed(0.01));
+                                    ui.add(egui::DragValue::new(&mut spot.dir.z).speed(0.01));
+                                });
+
+                                ui.add(
+                                    Slider::new(&mut spot.intensity, 0.0..=10000.0)
+                                        .logarithmic(true)
+                                        .text("Intensity")
+                                        .suffix(" cd"),
+                                );
+                                ui.add(
+                                    Slider::new(&mut spot.range, 0.0..=1000.0)
+                                        .logarithmic(true)
+                                        .text("Range"),
+                                )
+                                .on_hover_text("0 is an unlimited range");
+                                ui.add(
+                                    Slider::new(&mut spot.outer_angle, 1.0..=89.0)
+                                        .text("Outer angle")
+                                        .suffix("°"),
+                                );
+                                ui.add(
+                                    Slider::new(&mut spot.inner_angle, 0.0..=spot.outer_angle)
+                                        .text("Inner angle")
+                                        .suffix("°"),
+                                );
+
+                                let casts_shadows = ui
+                                    .checkbox(&mut spot.cast_shadows, "Cast shadows")
+                                    .on_hover_text("Only one light casts the shadows, the spot takes them over from the main light");
+                                if casts_shadows.changed() && spot.cast_shadows {
+                                    shadow_caster = Some(i);
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                if ui.button("Move to the camera").clicked() {
+                                    spot.pos = camera.pos();
+                                    spot.dir = camera.dir();
+                                }
+
+                                if ui.button("Remove").clicked() {
+                                    removed = Some(i);
+                                }
+                            });
+                        });
+                }
+
+                if let Some(caster) = shadow_caster {
+                    for (i, spot) in self.spot_lights.iter_mut().enumerate() {
+                        spot.cast_shadows = i == caster;
+                    }
+                }
+
+                if let Some(i) = removed {
+                    self.spot_lights.remove(i);
+                }
             });
+    }
+
+    /// Selection of the HDR background image and its rotation and exposure
+    fn show_background(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Background HDR…").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_title("Open background")
+                    .add_filter("Radiance HDR", &["hdr"])
+                    .pick_file()
+                {
+                    self.background_path = Some(path.to_string_lossy().into_owned());
+                }
+            }
+
+            if self.background_path.is_some() && ui.button("Clear").clicked() {
+                self.background_path = None;
+            }
         });
 
-        if response.response.hovered() {
-            animations.animation_control = AnimationControl::Static;
+        if let Some(path) = &self.background_path {
+            ui.label(path.as_str());
+            ui.add(
+                Slider::new(&mut self.background_rotation, 0.0..=360.0)
+                    .text("Background rotation")
+                    .suffix("°"),
+            );
+            ui.add(
+                Slider::new(&mut self.background_exposure, -8.0..=8.0)
+                    .text("Background exposure")
+                    .suffix(" EV"),
+            );
         }
+    }
 
-        joint.transform.rotation = Quat::from_axis_angle(axis.normalize(), angle.to_radians());
+    /// Lets the user pick a gltf file in a native dialog and adds the model to the scene.
+    ///
+    /// The new model is selected. Cancelling the dialog does nothing.
+    fn open_model(&mut self, scene: &mut Vec<Model>) {
+        let path = match rfd::FileDialog::new()
+            .set_title("Open model")
+            .add_filter("glTF", &["gltf", "glb"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        let path = path.to_string_lossy();
+        match Model::from_gltf_with_options(&path, self.import_options()) {
+            Ok(mut model) => {
+                model.auto_layout(&scene[self.selected_model].bounds());
+
+                scene.push(model);
+                self.selected_model = scene.len() - 1;
+                self.selected_joint = 0;
+                self.picked = None;
+                self.opened_models.push(path.to_string());
+            }
+            Err(e) => {
+                eprintln!("Couldn't load '{path}': {e:?}");
+                self.notification = Some(format!("Couldn't load '{path}': {e}"));
+            }
+        }
     }
 
-    /// Creates a gui for the side panel
-    fn gui_side_panel(&mut self, scene: &mut [Model], camera: &mut Camera, egui_ctx: &mut CtxRef) {
-        egui::SidePanel::right("Side Panel").show(egui_ctx, |ui| {
-            ui.group(|ui| {
-                ui.add(egui::Label::new(RichText::new("Scenes").heading().strong()));
-                ui.separator();
+    /// The options of loading the models opened at runtime (and the models of the default scene)
+    pub fn import_options(&self) -> ImportOptions {
+        ImportOptions {
+            handedness: self.import_handedness,
+            normalize_scale: self.import_normalize_scale,
+            ..ImportOptions::default()
+        }
+    }
 
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    for (i, model) in scene.iter().enumerate() {
-                        if ui.button(&model.name).clicked() {
-                            self.selected_model = i;
-                        }
-                    }
-                });
-            });
+    /// Creates the conversions applied to the models opened with the dialog
+    fn show_import_options(&mut self, ui: &mut Ui) {
+        let handedness = &mut self.import_handedness;
+        let normalize_scale = &mut self.import_normalize_scale;
 
-            ui.group(|ui| {
-                ui.add(egui::Label::new(
-                    RichText::new("Settings").heading().strong(),
-                ));
+        CollapsingHeader::new("Import options").show(ui, |ui| {
+            egui::ComboBox::from_label("Mirror axis")
+                .selected_text(format!("{:?}", handedness.mirror))
+                .show_ui(ui, |ui| {
+                    for axis in MirrorAxis::ALL {
+                        ui.selectable_value(&mut handedness.mirror, axis, format!("{axis:?}"));
+                    }
+                })
+                .response
+                .on_hover_text("Converts left-handed assets, the triangles keep facing outwards");
 
-                ui.separator();
+            egui::ComboBox::from_label("Up axis")
+                .selected_text(format!("{:?}", handedness.up_axis))
+                .show_ui(ui, |ui| {
+                    for axis in UpAxis::ALL {
+                        ui.selectable_value(&mut handedness.up_axis, axis, format!("{axis:?}"));
+                    }
+                })
+                .response
+                .on_hover_text("Z-up assets are rotated by -90° around X");
 
-                if ui.button("Debug joints").clicked() {
-                    self.draw_skeleton = !self.draw_skeleton;
-                }
+            ui.checkbox(&mut handedness.flip_winding, "Flip winding");
+            ui.checkbox(&mut handedness.flip_normals, "Flip normals")
+                .on_hover_text(
+                    "Only the normals stored in the file, generated normals follow the winding",
+                );
 
-                if ui.button("Draw mesh").clicked() {
-                    self.mesh_visible = !self.mesh_visible;
-                }
+            let label = |normalize_scale: &Option<NormalizeScale>| match normalize_scale {
+                None => "Authored",
+                Some(NormalizeScale::Size(_)) => "Size",
+                Some(NormalizeScale::Height(_)) => "Height",
+            };
+            egui::ComboBox::from_label("Scale")
+                .selected_text(label(normalize_scale))
+                .show_ui(ui, |ui| {
+                    let target = match normalize_scale {
+                        Some(NormalizeScale::Size(target) | NormalizeScale::Height(target)) => {
+                            *target
+                        }
+                        None => 1.,
+                    };
+                    for option in [
+                        None,
+                        Some(NormalizeScale::Size(target)),
+                        Some(NormalizeScale::Height(target)),
+                    ] {
+                        let selected = label(normalize_scale) == label(&option);
+                        if ui.selectable_label(selected, label(&option)).clicked() {
+                            *normalize_scale = option;
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Scales the bounding box of the models to a common size (applied to the default scene at startup)");
 
+            if let Some(NormalizeScale::Size(target) | NormalizeScale::Height(target)) =
+                normalize_scale
+            {
                 ui.add(
-                    Slider::new(&mut camera.move_speed, 0.0..=0.2)
-                        .text("Camera move speed")
-                        .smart_aim(false),
+                    Slider::new(target, 0.1..=10.0)
+                        .logarithmic(true)
+                        .text("Target size"),
                 );
+            }
+        });
+    }
 
-                if ui.button("Reset Camera").clicked() {
-                    camera.set_pos(Vec3::new(0.0, 0.0, 3.0));
-                }
-
-                egui::global_dark_light_mode_switch(ui);
-            });
+    /// Lets the user pick a gltf file in a native dialog and adds its animations to the model
+    fn add_animations(&mut self, model: &mut Model) {
+        let path = match rfd::FileDialog::new()
+            .set_title("Add animations")
+            .add_filter("glTF", &["gltf", "glb"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
 
-            ui.group(|ui| {
-                ui.add(egui::Label::new(
-                    RichText::new("Animations").heading().strong(),
-                ));
+        let path = path.to_string_lossy();
+        match model.add_animations_from_gltf(&path) {
+            Ok(count) => println!("Added {count} animations from '{path}' to '{}'", model.name),
+            Err(e) => {
+                eprintln!("Couldn't add the animations of '{path}': {e:?}");
+                self.notification = Some(format!("Couldn't add the animations of '{path}': {e}"));
+            }
+        }
+    }
 
-                ui.separator();
+    /// Creates the buttons that move the viewer camera to the cameras defined in the gltf file.
+    ///
+    /// Models without cameras only have the free camera.
+    fn show_asset_cameras(model: &Model, camera: &mut Camera, ui: &mut Ui) {
+        if model.cameras.is_empty() {
+            return;
+        }
 
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    self.show_animation_view(scene, ui);
-                });
+        CollapsingHeader::new("Asset cameras")
+            .id_source(("asset_cameras", &model.path))
+            .show(ui, |ui| {
+                for asset_camera in &model.cameras {
+                    if ui.button(format!("Use '{}'", asset_camera.name)).clicked() {
+                        camera.use_asset_camera(asset_camera, model.transform);
+                    }
+                }
             });
-        });
     }
 
-    /// Creates a gui for the animations inside the side panel
-    fn show_animation_view(&mut self, scene: &mut [Model], ui: &mut Ui) {
-        let selected_model = &mut scene[self.selected_model];
-        let animations = &mut selected_model.animations;
-        for (i, animation) in animations.animations.iter_mut().enumerate() {
-            ui.group(|ui| {
-                let response = ui.add(
-                    Slider::new(&mut animation.current_time, 0.0..=animation.end_time)
-                        .text("Animation time")
-                        .smart_aim(false),
+    /// Creates the toggle between the lights defined in the gltf file and the light of the viewer.
+    ///
+    /// Models without lights always use the light of the viewer.
+    fn show_asset_lights(&mut self, model: &Model, ui: &mut Ui) {
+        if model.lights.is_empty() {
+            return;
+        }
+
+        CollapsingHeader::new("Asset lights")
+            .id_source(("asset_lights", &model.path))
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.asset_lights, "Use the asset lights")
+                    .on_hover_text("Replaces the light of the viewer, only the first directional light casts shadows");
+
+                ui.add_enabled(
+                    self.asset_lights,
+                    Slider::new(&mut self.asset_light_intensity, 0.0..=10.0)
+                        .logarithmic(true)
+                        .text("Intensity"),
                 );
 
-                if response.clicked() || response.dragged() || response.changed() {
-                    animations.animation_control = AnimationControl::Controllable {
-                        active_animation: i,
+                for (i, light) in model.lights.iter().enumerate() {
+                    let kind = match light.kind {
+                        LightKind::Directional => "directional",
+                        LightKind::Point => "point",
+                        LightKind::Spot { .. } => "spot",
                     };
-                }
 
-                if let AnimationControl::Loop {
-                    active_animation: _,
-                    start_time: _,
-                } = animations.animation_control
-                {
-                    ui.ctx().request_repaint();
-                }
-
-                if ui.button("Play").clicked() {
-                    match animations.animation_control {
-                        AnimationControl::Static
-                        | AnimationControl::Controllable {
-                            active_animation: _,
-                        } => {
-                            animations.animation_control = AnimationControl::Loop {
-                                active_animation: i,
-                                start_time: Instant::now(),
-                            }
-                        }
-                        AnimationControl::Loop {
-                            active_animation: _,
-                            start_time: _,
-                        } => {
-                            animations.animation_control = AnimationControl::Controllable {
-                                active_animation: i,
-                            }
-                        }
+                    let text = format!("{} ({kind}, {})", light.name, light.intensity);
+                    match i < MAX_LIGHTS {
+                        true => ui.label(text),
+                        false => ui.label(RichText::new(format!("{text} - only {MAX_LIGHTS} lights are used")).weak()),
                     };
                 }
             });
-        }
+    }
+
+    /// Creates the read-only metadata of the gltf asset
+    fn show_asset_info(model: &Model, ui: &mut Ui) {
+        let asset = &model.asset;
+        let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+
+        CollapsingHeader::new("Asset info")
+            .id_source(("asset_info", &model.path))
+            .show(ui, |ui| {
+                egui::Grid::new("asset_info").show(ui, |ui| {
+                    ui.label("Generator:");
+                    ui.label(or_none(&asset.generator));
+                    ui.end_row();
+
+                    ui.label("Version:");
+                    ui.label(match &asset.min_version {
+                        Some(min_version) => format!("{} (min {min_version})", asset.version),
+                        None => asset.version.clone(),
+                    });
+                    ui.end_row();
+
+                    ui.label("Copyright:");
+                    ui.label(or_none(&asset.copyright));
+                    ui.end_row();
+                });
+
+                if let Some(extras) = &asset.extras {
+                    ui.label("Extras:");
+                    ui.monospace(extras);
+                }
+            });
+    }
+
+    /// Creates the joint name mapping and the button for retargeting animations onto the model
+    fn show_retargeting(&mut self, model: &mut Model, ui: &mut Ui) {
+        CollapsingHeader::new("Retarget animations").show(ui, |ui| {
+            ui.label("Renamed joints (source -> this model):");
+
+            let mut remove = None;
+            for (i, (source, target)) in self.retarget_names.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(source).desired_width(90.));
+                    ui.label("->");
+                    ui.add(egui::TextEdit::singleline(target).desired_width(90.));
+
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = remove {
+                self.retarget_names.remove(i);
+            }
+
+            if ui.button("Add mapping").clicked() {
+                self.retarget_names.push((String::new(), String::new()));
+            }
+
+            if ui
+                .button("Retarget from file…")
+                .on_hover_text("Approximate - works for rigs with similarly oriented joints")
+                .clicked()
+            {
+                self.retarget_animations(model);
+            }
+        });
+    }
+
+    /// Creates the gui for the look-at constraint of the model (head / eye tracking)
+    fn show_look_at(model: &mut Model, ui: &mut Ui) {
+        // Nodes sharing a skin have the same joints
+        let mut joint_names: Vec<String> = Vec::new();
+        for (node, _) in model.nodes() {
+            for joint in node.joints.iter().flat_map(|joints| &joints.joints) {
+                if !joint_names.contains(&joint.name) {
+                    joint_names.push(joint.name.clone());
+                }
+            }
+        }
+
+        CollapsingHeader::new("Look-at constraint").show(ui, |ui| {
+            if joint_names.is_empty() {
+                ui.label("The model has no skin");
+                return;
+            }
+
+            let mut enabled = model.look_at.is_some();
+            if ui.checkbox(&mut enabled, "Enabled").changed() {
+                model.look_at = match enabled {
+                    // Heads are usually near the end of the hierarchy, the last joint is a guess
+                    true => {
+                        let head = joint_names
+                            .iter()
+                            .find(|name| name.to_lowercase().contains("head"))
+                            .unwrap_or(&joint_names[joint_names.len() - 1]);
+                        Some(LookAt::new(head.clone()))
+                    }
+                    false => None,
+                };
+            }
+
+            let look_at = match &mut model.look_at {
+                Some(look_at) => look_at,
+                None => return,
+            };
+
+            egui::ComboBox::from_label("Joint")
+                .selected_text(&look_at.joint)
+                .show_ui(ui, |ui| {
+                    for name in &joint_names {
+                        ui.selectable_value(&mut look_at.joint, name.clone(), name);
+                    }
+                });
+
+            ui.checkbox(&mut look_at.track_camera, "Track camera");
+            ui.add_enabled_ui(!look_at.track_camera, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Target");
+                    ui.add(egui::DragValue::new(&mut look_at.target.x).speed(0.05));
+                    ui.add(egui::DragValue::new(&mut look_at.target.y).speed(0.05));
+                    ui.add(egui::DragValue::new(&mut look_at.target.z).speed(0.05));
+                });
+            });
+
+            ui.add(Slider::new(&mut look_at.weight, 0.0..=1.0).text("Weight"));
+
+            for (axis, label) in [
+                (&mut look_at.aim_axis, "Aim axis"),
+                (&mut look_at.up_axis, "Up axis"),
+            ] {
+                egui::ComboBox::from_label(label)
+                    .selected_text(format!("{axis:?}"))
+                    .show_ui(ui, |ui| {
+                        for a in JointAxis::ALL {
+                            ui.selectable_value(axis, a, format!("{a:?}"));
+                        }
+                    });
+            }
+
+            let mut clamp = look_at.max_angle.is_some();
+            let mut max_angle = look_at.max_angle.unwrap_or(60f32.to_radians()).to_degrees();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut clamp, "Limit to cone");
+                ui.add_enabled(clamp, Slider::new(&mut max_angle, 1.0..=180.0).text("°"));
+            })
+            .response
+            .on_hover_text("Maximum angle from the animated direction");
+            look_at.max_angle = clamp.then(|| max_angle.to_radians());
+        });
+    }
+
+    /// Lets the user pick a gltf file in a native dialog and retargets its animations onto the model
+    fn retarget_animations(&mut self, model: &mut Model) {
+        let path = match rfd::FileDialog::new()
+            .set_title("Retarget animations")
+            .add_filter("glTF", &["gltf", "glb"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        let name_overrides = self
+            .retarget_names
+            .iter()
+            .filter(|(source, target)| !source.is_empty() && !target.is_empty())
+            .cloned()
+            .collect();
+
+        let path = path.to_string_lossy();
+        match model.retarget_animations_from_gltf(&path, &name_overrides) {
+            Ok(count) => println!(
+                "Retargeted {count} animations from '{path}' to '{}'",
+                model.name
+            ),
+            Err(e) => {
+                eprintln!("Couldn't retarget the animations of '{path}': {e:?}");
+                self.notification =
+                    Some(format!("Couldn't retarget the animations of '{path}': {e}"));
+            }
+        }
+    }
+
+    /// Lets the user pick a JSON file in a native dialog and saves the state of the scene into it
+    fn save_scene(&mut self, scene: &[Model], camera: &Camera) {
+        let path = match rfd::FileDialog::new()
+            .set_title("Save scene")
+            .add_filter("JSON", &["json"])
+            .set_file_name("scene.json")
+            .save_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Err(e) = SceneState::capture(scene, camera, self).save(&path) {
+            eprintln!("{e:?}");
+            self.notification = Some(format!("{e:#}"));
+        }
+    }
+
+    /// Lets the user pick a JSON file in a native dialog and replaces the scene with the saved state
+    fn load_scene(&mut self, scene: &mut Vec<Model>, camera: &mut Camera) {
+        let path = match rfd::FileDialog::new()
+            .set_title("Load scene")
+            .add_filter("JSON", &["json"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = SceneState::load(&path).and_then(|state| state.apply(scene, camera, self));
+        if let Err(e) = result {
+            eprintln!("{e:?}");
+            self.notification = Some(format!("{e:#}"));
+        }
+    }
+
+    /// Creates the orientation gizmo in the bottom-left corner.
+    ///
+    /// Only the rotation of the camera is used. Clicking an axis snaps the camera onto that axis.
+    fn gui_axis_gizmo(&mut self, camera: &mut Camera, egui_ctx: &mut CtxRef) {
+        const SIZE: f32 = 90.;
+        const HANDLE_RADIUS: f32 = 8.;
+
+        let view_rotation = Mat3::from_mat4(camera.view_mat());
+
+        egui::Area::new("Axis Gizmo")
+            .anchor(Align2::LEFT_BOTTOM, [10., -10.])
+            .show(egui_ctx, |ui| {
+                let (response, painter) =
+                    ui.allocate_painter(egui::Vec2::splat(SIZE), Sense::click());
+
+                let center = response.rect.center();
+                let axis_len = SIZE / 2. - HANDLE_RADIUS;
+
+                let mut axes = [
+                    (Vec3::X, "X", Color32::from_rgb(230, 60, 60)),
+                    (Vec3::Y, "Y", Color32::from_rgb(80, 200, 60)),
+                    (Vec3::Z, "Z", Color32::from_rgb(60, 120, 230)),
+                ]
+                .map(|(axis, label, color)| (axis, view_rotation * axis, label, color));
+
+                // Axes pointing away from the camera are drawn first, so the closer ones overlap them
+                axes.sort_by(|a, b| a.1.z.total_cmp(&b.1.z));
+
+                let mut clicked_axis = None;
+
+                for (axis, view_axis, label, color) in axes {
+                    // egui has the y axis pointing down
+                    let end = center + egui::vec2(view_axis.x, -view_axis.y) * axis_len;
+
+                    painter.line_segment([center, end], Stroke::new(2., color));
+                    painter.circle_filled(end, HANDLE_RADIUS, color);
+                    painter.text(
+                        end,
+                        Align2::CENTER_CENTER,
+                        label,
+                        TextStyle::Small,
+                        Color32::BLACK,
+                    );
+
+                    if let Some(pointer_pos) = response.interact_pointer_pos() {
+                        if response.clicked() && pointer_pos.distance(end) <= HANDLE_RADIUS {
+                            clicked_axis = Some(axis);
+                        }
+                    }
+                }
+
+                if let Some(axis) = clicked_axis {
+                    camera.snap_to_axis(axis);
+                }
+            });
+    }
+
+    /// Create the subwindow containing the model hierarchy
+    fn gui_model_hierarchy_window(
+        &mut self,
+        scene: &[Model],
+        camera: &mut Camera,
+        egui_ctx: &mut CtxRef,
+    ) {
+        let model = &scene[self.selected_model];
+        let screen_rect = egui_ctx.input().screen_rect();
+        let aspect_ratio = screen_rect.width() / screen_rect.height();
+
+        egui::Window::new("Model Hierarchy")
+            .scroll2([false, true])
+            .resizable(true)
+            .show(egui_ctx, |ui| {
+                self.gui_node_search(model, camera, aspect_ratio, ui);
+                ui.separator();
+
+                let search = self.node_search.to_lowercase();
+                self.gui_node(&model.root, &search, ui);
+            });
+    }
+
+    /// Creates the search box of the nodes and joints.
+    ///
+    /// Clicking a result focuses the camera on the node (and selects it if it's a joint).
+    fn gui_node_search(
+        &mut self,
+        model: &Model,
+        camera: &mut Camera,
+        aspect_ratio: f32,
+        ui: &mut Ui,
+    ) {
+        const MAX_RESULTS: usize = 20;
+
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            ui.text_edit_singleline(&mut self.node_search);
+        });
+
+        let search = self.node_search.to_lowercase();
+        if search.is_empty() {
+            return;
+        }
+
+        let joints = model
+            .nodes()
+            .find_map(|(node, _)| node.joints.as_ref())
+            .map(|joints| joints.joints.as_slice())
+            .unwrap_or(&[]);
+
+        let results: Vec<&Node> = model
+            .nodes()
+            .map(|(node, _)| node)
+            .filter(|node| Self::matches_search(node, &search))
+            .collect();
+
+        for node in results.iter().take(MAX_RESULTS) {
+            let joint_index = joints.iter().position(|j| j.node_index == node.index);
+
+            let label = match joint_index {
+                Some(_) => format!("{} (joint)", node.name),
+                None => node.name.clone(),
+            };
+
+            if ui.button(label).clicked() {
+                if let Some(joint_index) = joint_index {
+                    self.selected_joint = joint_index;
+                }
+
+                if let Some(mut aabb) = model.node_world_bounds(node.index) {
+                    // Nodes without meshes would be focused from too close
+                    let model_bounds = model.bounds();
+                    let min_size = match model_bounds.is_empty() {
+                        true => 1.,
+                        false => model_bounds.size().length() * 0.1,
+                    };
+                    let center = aabb.center();
+                    aabb.extend(center - Vec3::splat(min_size / 2.));
+                    aabb.extend(center + Vec3::splat(min_size / 2.));
+
+                    camera.focus_bounds(&aabb, aspect_ratio);
+                    camera.fit_planes_to_bounds(&model_bounds);
+                }
+            }
+        }
+
+        if results.len() > MAX_RESULTS {
+            ui.label(format!("... and {} more", results.len() - MAX_RESULTS));
+        } else if results.is_empty() {
+            ui.label("No matching nodes");
+        }
+    }
+
+    /// If the name of the node contains the (lowercase) search text
+    fn matches_search(node: &Node, search: &str) -> bool {
+        !search.is_empty() && node.name.to_lowercase().contains(search)
+    }
+
+    /// Recusrive - creates the node hierarchy inside the model hierarchy window.
+    ///
+    /// Nodes matching the search and the picked node are highlighted and the nodes leading to them are opened.
+    fn gui_node(&mut self, node: &Node, search: &str, ui: &mut Ui) {
+        let default_open = node.children.len() == 1;
+
+        let picked_node = self.picked.map(|picked| picked.node);
+
+        let mut name = RichText::new(&node.name);
+        if Self::matches_search(node, search) {
+            name = name.color(Color32::YELLOW).strong();
+        } else if picked_node == Some(node.index) {
+            name = name.color(Color32::LIGHT_BLUE).strong();
+        }
+
+        ui.horizontal(|ui| {
+            if !&node.children.is_empty() {
+                let contains_match = node.iter().skip(1).any(|(child, _)| {
+                    Self::matches_search(child, search) || picked_node == Some(child.index)
+                });
+
+                CollapsingHeader::new(name)
+                    .id_source(node.index)
+                    .default_open(default_open)
+                    .open(contains_match.then_some(true))
+                    .selectable(true)
+                    .show(ui, |ui| {
+                        for child_node in &node.children {
+                            self.gui_node(child_node, search, ui);
+                        }
+                    });
+            } else {
+                ui.label(name);
+            }
+
+            if let Some(mesh) = &node.mesh {
+                ui.separator();
+
+                let mesh_name = mesh.name.as_deref().unwrap_or("N/A");
+                let counts: Vec<String> = mesh
+                    .primitives
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| {
+                        format!(
+                            "Primitive {i}: {} triangles, {} vertices",
+                            p.triangle_count, p.vertex_count
+                        )
+                    })
+                    .collect();
+
+                ui.add(egui::Label::new(mesh_name))
+                    .on_hover_text(counts.join("\n"));
+
+                ui.end_row()
+            }
+        });
+    }
+
+    fn gui_joints_window(&mut self, model: &mut Model, egui_ctx: &mut CtxRef) {
+        self.gui_joints_window_helper(&mut model.root, &mut model.animations, egui_ctx);
+    }
+
+    /// Recursive - creates the joints window and it's nodes
+    fn gui_joints_window_helper(
+        &mut self,
+        node: &mut Node,
+        animations: &mut Animations,
+        egui_ctx: &mut CtxRef,
+    ) {
+        if let Some(joints) = &mut node.joints {
+            egui::Window::new("Joints").show(egui_ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, joint) in joints.joints.iter_mut().enumerate() {
+                        let joint_name = joint.name.clone();
+
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.selected_joint, i, "")
+                                .on_hover_text("Highlight this joint in the debug views");
+
+                            // FIXME: for some reason there is an ID collision when rendering the droid model... maybe a bug in egui ?
+                            CollapsingHeader::new(joint_name).show(ui, |ui| {
+                                Self::show_joint_transforms(joint, animations, ui);
+                            });
+
+                            if joint.pose_override.is_some() {
+                                ui.label(
+                                    RichText::new("override")
+                                        .color(Color32::from_rgb(240, 170, 40)),
+                                )
+                                .on_hover_text(
+                                    "The transform is set from code, the animations are ignored",
+                                );
+
+                                if ui.small_button("Clear").clicked() {
+                                    joint.pose_override = None;
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+        } else {
+            // I assume there is only 1 skeleton in the models we are going to work with
+            for child_node in &mut node.children {
+                self.gui_joints_window_helper(child_node, animations, egui_ctx);
+            }
+        }
+    }
+
+    /// Creates the gui for transforms of a specific joint
+    fn show_joint_transforms(joint: &mut Joint, animations: &mut Animations, ui: &mut Ui) {
+        let trans = &mut joint.transform.translation;
+        let (axis, angle) = joint.transform.rotation.to_axis_angle();
+        let mut angle = angle.to_degrees();
+
+        let response = ui.group(|ui| {
+            ui.label("Translation");
+            ui.horizontal(|ui| {
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut trans.x).speed(0.03));
+                ui.label("y");
+                ui.add(egui::DragValue::new(&mut trans.y).speed(0.03));
+                ui.label("z");
+                ui.add(egui::DragValue::new(&mut trans.z).speed(0.03));
+            });
+
+            ui.label("Rotation");
+            ui.horizontal(|ui| {
+                ui.label("angle");
+                ui.add(
+                    egui::DragValue::new(&mut angle)
+                        .speed(1.0)
+                        .clamp_range((0.1)..=(359.9)),
+                );
+            });
+        });
+
+        if response.response.hovered() {
+            animations.animation_control = AnimationControl::Static;
+        }
+
+        joint.transform.rotation = Quat::from_axis_angle(axis.normalize(), angle.to_radians());
+    }
+
+    /// Creates a gui for the side panel
+    fn gui_side_panel(
+        &mut self,
+        scene: &mut Vec<Model>,
+        camera: &mut Camera,
+        egui_ctx: &mut CtxRef,
+    ) {
+        egui::SidePanel::right("Side Panel").show(egui_ctx, |ui| {
+            ui.group(|ui| {
+                ui.add(egui::Label::new(RichText::new("Scenes").heading().strong()));
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, model) in scene.iter().enumerate() {
+                        let button = ui.button(&model.name).on_hover_text(format!(
+                            "{} triangles, {} vertices",
+                            model.triangle_count, model.vertex_count
+                        ));
+
+                        if button.clicked() {
+                            self.selected_model = i;
+                            self.picked = None;
+                        }
+                    }
+                });
+
+                if ui.button("Open model…").clicked() {
+                    self.open_model(scene);
+                }
+
+                self.show_import_options(ui);
+
+                if ui.button("Add animations…").clicked() {
+                    self.add_animations(&mut scene[self.selected_model]);
+                }
+
+                self.show_retargeting(&mut scene[self.selected_model], ui);
+                Self::show_look_at(&mut scene[self.selected_model], ui);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save scene…").clicked() {
+                        self.save_scene(scene, camera);
+                    }
+
+                    if ui.button("Load scene…").clicked() {
+                        self.load_scene(scene, camera);
+                    }
+                });
+
+                ui.separator();
+                let selected_model = &mut scene[self.selected_model];
+
+                if let Some(picked) = self.picked {
+                    let node = selected_model
+                        .nodes()
+                        .find(|(node, _)| node.index == picked.node);
+
+                    if let Some((node, _)) = node {
+                        let primitive = node
+                            .mesh
+                            .as_ref()
+                            .and_then(|mesh| mesh.primitives.get(picked.primitive));
+
+                        ui.label(format!(
+                            "Picked: '{}' (primitive {})",
+                            node.name, picked.primitive
+                        ));
+
+                        if let Some(primitive) = primitive {
+                            ui.label(format!(
+                                "{} triangles, {} vertices",
+                                primitive.triangle_count, primitive.vertex_count
+                            ));
+                        }
+                    }
+                }
+
+                ui.checkbox(
+                    &mut selected_model.wireframe,
+                    format!("Wireframe of '{}'", selected_model.name),
+                );
+                ui.checkbox(
+                    &mut selected_model.flip_v,
+                    format!("Flip V texture coordinate of '{}'", selected_model.name),
+                )
+                .on_hover_text("For textures that appear upside-down");
+
+                let mut normal_mode = selected_model.normal_mode;
+                egui::ComboBox::from_label("Normals")
+                    .selected_text(format!("{normal_mode:?}"))
+                    .show_ui(ui, |ui| {
+                        for mode in NormalMode::ALL {
+                            ui.selectable_value(&mut normal_mode, mode, format!("{mode:?}"));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Primitives without normals in the file use smooth normals");
+
+                if normal_mode != selected_model.normal_mode {
+                    selected_model.set_normal_mode(normal_mode);
+                }
+
+                let mut up_axis = selected_model.handedness.up_axis;
+                egui::ComboBox::from_label("Up axis")
+                    .selected_text(format!("{up_axis:?}"))
+                    .show_ui(ui, |ui| {
+                        for axis in UpAxis::ALL {
+                            ui.selectable_value(&mut up_axis, axis, format!("{axis:?}"));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Stands up the models exported from Z-up tools");
+
+                if up_axis != selected_model.handedness.up_axis {
+                    selected_model.set_up_axis(up_axis);
+                }
+
+                Self::show_asset_info(selected_model, ui);
+                Self::show_asset_cameras(selected_model, camera, ui);
+                self.show_asset_lights(selected_model, ui);
+            });
+
+            ui.group(|ui| {
+                ui.add(egui::Label::new(
+                    RichText::new("Settings").heading().strong(),
+                ));
+
+                ui.separator();
+
+                if ui.button("Debug joints").clicked() {
+                    self.draw_skeleton = !self.draw_skeleton;
+                }
+
+                if ui.button("Draw mesh").clicked() {
+                    self.mesh_visible = !self.mesh_visible;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.wireframe, "Wireframe (all models)");
+                    ui.color_edit_button_rgba_unmultiplied(&mut self.wireframe_color);
+                });
+
+                ui.add(Slider::new(&mut self.wireframe_width, 1.0..=10.0).text("Wireframe width"))
+                    .on_hover_text("Widths above 1 aren't supported by all drivers");
+
+                ui.add(
+                    Slider::new(&mut self.overlay_line_width, 1.0..=10.0)
+                        .text("Overlay line width"),
+                )
+                .on_hover_text("Width of the bones and the bounding boxes in pixels");
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.draw_model_bounds, "Model bounds");
+                    ui.checkbox(&mut self.draw_primitive_bounds, "Primitive bounds")
+                        .on_hover_text(
+                            "Skinned primitives show the box around their bounding sphere",
+                        );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_rgba_unmultiplied(&mut self.bounds_color);
+                    ui.label("In view");
+                    ui.color_edit_button_rgba_unmultiplied(&mut self.culled_bounds_color);
+                    ui.label("Outside of the view");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.draw_root_trail, "Root trail")
+                        .on_hover_text("Path of the root joint (or the root node) of the model");
+                    ui.color_edit_button_rgba_unmultiplied(&mut self.root_trail_color);
+                });
+
+                ui.add_enabled(
+                    self.draw_root_trail,
+                    Slider::new(&mut self.root_trail_length, 2..=2000)
+                        .text("Trail length (frames)"),
+                );
+
+                ui.checkbox(&mut self.depth_test, "Depth test");
+                ui.checkbox(&mut self.face_culling, "Face culling");
+
+                ui.add_enabled_ui(self.face_culling, |ui| {
+                    egui::ComboBox::from_label("Culled faces")
+                        .selected_text(format!("{:?}", self.culled_face))
+                        .show_ui(ui, |ui| {
+                            for face in CulledFace::ALL {
+                                ui.selectable_value(
+                                    &mut self.culled_face,
+                                    face,
+                                    format!("{face:?}"),
+                                );
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Render settings").clicked() {
+                        self.render_settings_open = !self.render_settings_open;
+                    }
+
+                    if ui.button("System").clicked() {
+                        self.system_info_open = !self.system_info_open;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_rgb(&mut self.ambient_color);
+                    ui.add(
+                        Slider::new(&mut self.ambient_intensity, 0.0..=1.0).text("Ambient light"),
+                    );
+                });
+
+                self.show_spot_lights(camera, ui);
+
+                self.show_background(ui);
+
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_rgb(&mut self.fog_color)
+                        .on_hover_text("Defaults to the background color");
+                    egui::ComboBox::from_label("Fog")
+                        .selected_text(format!("{:?}", self.fog_mode))
+                        .show_ui(ui, |ui| {
+                            for mode in FogMode::ALL {
+                                ui.selectable_value(&mut self.fog_mode, mode, format!("{mode:?}"));
+                            }
+                        });
+                });
+
+                match self.fog_mode {
+                    FogMode::Off => {}
+                    FogMode::Linear => {
+                        ui.add(
+                            Slider::new(&mut self.fog_start, 0.0..=200.0)
+                                .logarithmic(true)
+                                .text("Fog start"),
+                        );
+                        ui.add(
+                            Slider::new(&mut self.fog_end, 0.0..=500.0)
+                                .logarithmic(true)
+                                .text("Fog end"),
+                        );
+                        self.fog_end = self.fog_end.max(self.fog_start);
+                    }
+                    FogMode::Exponential => {
+                        ui.add(
+                            Slider::new(&mut self.fog_density, 0.0..=1.0)
+                                .logarithmic(true)
+                                .text("Fog density"),
+                        );
+                    }
+                }
+
+                ui.checkbox(&mut self.shadows, "Shadows")
+                    .on_hover_text("Cascaded shadow maps of the light, only the lit textured primitives are shadowed");
+                if self.shadows {
+                    ui.add(
+                        Slider::new(&mut self.shadow_cascades, 1..=MAX_CASCADES).text("Cascades"),
+                    );
+                    ui.add(
+                        Slider::new(&mut self.shadow_split_lambda, 0.0..=1.0)
+                            .text("Split lambda"),
+                    )
+                    .on_hover_text("Uniform (0) or logarithmic (1) distances of the cascades");
+                    ui.add(
+                        Slider::new(&mut self.shadow_distance, 1.0..=500.0)
+                            .logarithmic(true)
+                            .text("Shadow distance"),
+                    );
+                    ui.checkbox(&mut self.shadow_cascade_debug, "Color the cascades");
+                }
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.blob_shadow, "Blob shadow")
+                        .on_hover_text("A soft shadow on the ground below the model, sized by its bounds");
+                    ui.add_enabled(
+                        self.blob_shadow,
+                        Slider::new(&mut self.blob_shadow_opacity, 0.0..=1.0).text("Opacity"),
+                    );
+                });
+
+                let max_anisotropy = self.max_anisotropy.unwrap_or(1.).min(16.);
+                ui.add_enabled(
+                    self.max_anisotropy.is_some(),
+                    Slider::new(&mut self.anisotropy, 1.0..=max_anisotropy)
+                        .text("Anisotropic filtering")
+                        .suffix("x"),
+                )
+                .on_disabled_hover_text("Anisotropic filtering isn't supported");
+
+                ui.add(
+                    Slider::new(&mut camera.move_speed, 0.0..=0.2)
+                        .text("Camera move speed")
+                        .smart_aim(false),
+                );
+
+                if ui.button("Reset Camera").clicked() {
+                    camera.set_pos(Vec3::new(0.0, 0.0, 3.0));
+                }
+
+                self.show_auto_quality(ui);
+
+                Self::show_camera_transform(camera, ui);
+                Self::show_camera_planes(scene, camera, ui);
+                self.show_camera_bookmarks(camera, ui);
+
+                ui.checkbox(&mut self.vsync, "VSync");
+
+                // FXAA and the MSAA samples of the window are exclusive
+                let antialiasing = |fxaa: bool, samples: u8| match (fxaa, samples) {
+                    (true, _) => "FXAA".to_string(),
+                    (false, 0) => "Off".to_string(),
+                    (false, samples) => format!("MSAA {samples}x"),
+                };
+                egui::ComboBox::from_label("Anti-aliasing")
+                    .selected_text(antialiasing(self.fxaa, self.msaa_samples))
+                    .show_ui(ui, |ui| {
+                        let options = [(false, 0), (true, 0), (false, 2), (false, 4), (false, 8)];
+                        for (fxaa, samples) in options {
+                            let selected = (self.fxaa, self.msaa_samples) == (fxaa, samples);
+                            if ui
+                                .selectable_label(selected, antialiasing(fxaa, samples))
+                                .clicked()
+                            {
+                                self.fxaa = fxaa;
+                                self.msaa_samples = samples;
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "FXAA filters the final image, it's cheaper than MSAA, which is applied after a restart",
+                    );
+                ui.checkbox(
+                    &mut self.pause_animations_when_inactive,
+                    "Pause animations in the background",
+                )
+                .on_hover_text("Rendering always stops while the window is minimized or unfocused");
+
+                egui::global_dark_light_mode_switch(ui);
+            });
+
+            ui.group(|ui| {
+                ui.add(egui::Label::new(
+                    RichText::new("Animations").heading().strong(),
+                ));
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.show_animation_view(scene, ui);
+                });
+            });
+        });
+    }
+
+    /// Creates the controls of the automatic quality fallback
+    fn show_auto_quality(&mut self, ui: &mut Ui) {
+        let auto_quality = &mut self.auto_quality;
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut auto_quality.enabled, "Auto quality")
+                .on_hover_text("Disables expensive features when the frames exceed the budget");
+            ui.label(format!("Frame: {:.1} ms", auto_quality.frame_ms()))
+                .on_hover_text("VSync limits the frame time to the refresh rate");
+        });
+
+        if !auto_quality.enabled {
+            return;
+        }
+
+        ui.add(
+            Slider::new(&mut auto_quality.target_ms, 4.0..=100.0)
+                .text("Frame budget")
+                .suffix(" ms"),
+        );
+
+        let disabled = auto_quality.disabled();
+        if !disabled.is_empty() {
+            let names: Vec<String> = disabled.iter().map(|f| format!("{f:?}")).collect();
+            ui.label(
+                RichText::new(format!("Auto-disabled: {}", names.join(", ")))
+                    .color(Color32::LIGHT_RED),
+            );
+        }
+    }
+
+    /// Creates the controls for the near and far planes of the camera
+    fn show_camera_planes(scene: &[Model], camera: &mut Camera, ui: &mut Ui) {
+        ui.add(
+            Slider::new(&mut camera.near, 0.001..=10.0)
+                .text("Near plane")
+                .logarithmic(true),
+        );
+        ui.add(
+            Slider::new(&mut camera.far, 1.0..=100000.0)
+                .text("Far plane")
+                .logarithmic(true),
+        );
+        camera.far = camera.far.max(camera.near * 2.);
+
+        if ui.button("Fit planes to scene").clicked() {
+            let mut scene_bounds = Aabb::new_empty();
+            for model in scene {
+                scene_bounds.union(&model.bounds());
+            }
+
+            camera.fit_planes_to_bounds(&scene_bounds);
+        }
+
+        ui.add_enabled(
+            gl::ClipControl::is_loaded(),
+            egui::Checkbox::new(&mut camera.reversed_z, "Reversed-Z depth"),
+        )
+        .on_disabled_hover_text("Requires glClipControl (OpenGL 4.5)");
+
+        ui.checkbox(&mut camera.orthographic, "Orthographic")
+            .on_hover_text("Views of the selected model: 1 / 3 / 7 (front / right / top), Ctrl for the opposite side, 5 toggles the projection");
+        if camera.orthographic {
+            ui.add(
+                Slider::new(&mut camera.ortho_half_height, 0.001..=1000.0)
+                    .text("View height / 2")
+                    .logarithmic(true),
+            );
+        }
+    }
+
+    /// Creates the fields with the position and orientation of the camera.
+    ///
+    /// The fields follow the camera every frame and editing one of them moves the camera.
+    fn show_camera_transform(camera: &mut Camera, ui: &mut Ui) {
+        CollapsingHeader::new("Camera transform").show(ui, |ui| {
+            let mut pos = camera.pos();
+            ui.label("Position");
+            let pos_changed = ui
+                .horizontal(|ui| {
+                    ui.label("x");
+                    let x = ui.add(egui::DragValue::new(&mut pos.x).speed(0.03));
+                    ui.label("y");
+                    let y = ui.add(egui::DragValue::new(&mut pos.y).speed(0.03));
+                    ui.label("z");
+                    let z = ui.add(egui::DragValue::new(&mut pos.z).speed(0.03));
+                    x.changed() || y.changed() || z.changed()
+                })
+                .inner;
+
+            if pos_changed {
+                camera.set_pos(pos);
+            }
+
+            let mut yaw = camera.yaw();
+            let mut pitch = camera.pitch();
+            ui.label("Orientation");
+            let angles_changed = ui
+                .horizontal(|ui| {
+                    ui.label("yaw");
+                    let yaw = ui.add(egui::DragValue::new(&mut yaw).speed(0.5).suffix("°"));
+                    ui.label("pitch");
+                    let pitch = ui.add(
+                        egui::DragValue::new(&mut pitch)
+                            .speed(0.5)
+                            .clamp_range(-89.0..=89.0)
+                            .suffix("°"),
+                    );
+                    yaw.changed() || pitch.changed()
+                })
+                .inner;
+
+            if angles_changed {
+                camera.set_yaw_pitch(yaw, pitch);
+            }
+
+            let mut dir = camera.dir();
+            ui.label("Forward");
+            let dir_changed = ui
+                .horizontal(|ui| {
+                    ui.label("x");
+                    let x = ui.add(egui::DragValue::new(&mut dir.x).speed(0.01));
+                    ui.label("y");
+                    let y = ui.add(egui::DragValue::new(&mut dir.y).speed(0.01));
+                    ui.label("z");
+                    let z = ui.add(egui::DragValue::new(&mut dir.z).speed(0.01));
+                    x.changed() || y.changed() || z.changed()
+                })
+                .inner;
+
+            // The direction is normalized, a zero vector would have no direction
+            if dir_changed && dir.length_squared() > f32::EPSILON {
+                camera.set_dir(dir);
+            }
+        });
+    }
+
+    /// Creates the list of the saved camera views
+    fn show_camera_bookmarks(&mut self, camera: &mut Camera, ui: &mut Ui) {
+        CollapsingHeader::new("Camera bookmarks").show(ui, |ui| {
+            let mut remove = None;
+            for (i, bookmark) in self.camera_bookmarks.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut bookmark.name);
+
+                    if ui.button("Go").clicked() {
+                        camera.set_pos(bookmark.pos);
+                        camera.set_dir(bookmark.dir);
+                    }
+
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = remove {
+                self.camera_bookmarks.remove(i);
+            }
+
+            if ui.button("Save view").clicked() {
+                self.camera_bookmarks.push(CameraBookmark {
+                    name: format!("View {}", self.camera_bookmarks.len() + 1),
+                    pos: camera.pos(),
+                    dir: camera.dir(),
+                });
+            }
+        });
+    }
+
+    /// Creates the controls of the 1D blend tree
+    fn show_blend_tree(animations: &mut Animations, ui: &mut Ui) {
+        let clips = &animations.animations;
+        let blend_tree = &mut animations.blend_tree;
+
+        CollapsingHeader::new("Blend tree (1D)").show(ui, |ui| {
+            if ui
+                .checkbox(&mut blend_tree.enabled, "Drive the model by the blend tree")
+                .changed()
+            {
+                blend_tree.reset();
+            }
+
+            ui.add(Slider::new(&mut blend_tree.parameter, 0.0..=1.0).text("Parameter"));
+
+            let mut remove = None;
+            for (i, point) in blend_tree.points.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source(("blend_point", i))
+                        .selected_text(Self::animation_label(
+                            &clips[point.animation],
+                            point.animation,
+                        ))
+                        .show_ui(ui, |ui| {
+                            for (clip_index, clip) in clips.iter().enumerate() {
+                                let label = Self::animation_label(clip, clip_index);
+                                ui.selectable_value(&mut point.animation, clip_index, label);
+                            }
+                        });
+
+                    ui.add(Slider::new(&mut point.position, 0.0..=1.0).text("at"));
+
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = remove {
+                blend_tree.points.remove(i);
+            }
+
+            if ui.button("Add clip").clicked() {
+                let position = if blend_tree.points.is_empty() { 0. } else { 1. };
+                blend_tree.points.push(BlendPoint {
+                    animation: 0,
+                    position,
+                });
+            }
+        });
+
+        if blend_tree.enabled {
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// The step the animations advance by every frame in seconds, None if they follow the real time
+    pub fn fixed_timestep(&self) -> Option<f32> {
+        self.fixed_timestep.then(|| self.fixed_timestep_ms / 1000.)
+    }
+
+    /// Creates a gui for the animations inside the side panel
+    fn show_animation_view(&mut self, scene: &mut [Model], ui: &mut Ui) {
+        let selected_model = &mut scene[self.selected_model];
+        let animations = &mut selected_model.animations;
+
+        ui.label(format!("Clips: {}", animations.animations.len()));
+
+        if animations.animations.is_empty() {
+            return;
+        }
+
+        ui.checkbox(&mut self.crossfade, "Crossfade when switching clips");
+        if self.crossfade {
+            ui.add(
+                Slider::new(&mut self.crossfade_duration, 0.05..=2.0)
+                    .text("Crossfade duration")
+                    .smart_aim(false),
+            );
+        }
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.fixed_timestep, "Fixed timestep")
+                .on_hover_text(
+                "Advances the animations by the same step every frame, regardless of the real time",
+            );
+            ui.add_enabled(
+                self.fixed_timestep,
+                egui::DragValue::new(&mut self.fixed_timestep_ms)
+                    .speed(0.1)
+                    .clamp_range(0.1..=1000.0)
+                    .suffix(" ms"),
+            );
+        });
+
+        let active_animation = animations.active_animation();
+        let selected_text = match active_animation {
+            Some(i) => Self::animation_label(&animations.animations[i], i),
+            None => "None".to_string(),
+        };
+
+        let mut switch_to = None;
+        let mut clip_scrub = None;
+        egui::ComboBox::from_label("Clip")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for (i, animation) in animations.animations.iter().enumerate() {
+                    let label = Self::animation_label(animation, i);
+                    if ui
+                        .selectable_label(active_animation == Some(i), label)
+                        .clicked()
+                    {
+                        switch_to = Some(i);
+                    }
+                }
+            });
+
+        let interpolation_label = |interpolation: Option<Interpolation>| match interpolation {
+            None => "As authored",
+            Some(Interpolation::Step) => "Step",
+            Some(Interpolation::Linear) => "Linear",
+            Some(Interpolation::CubicSpline) => "Cubic spline",
+        };
+        egui::ComboBox::from_label("Interpolation")
+            .selected_text(interpolation_label(self.interpolation_override))
+            .show_ui(ui, |ui| {
+                for interpolation in [
+                    None,
+                    Some(Interpolation::Step),
+                    Some(Interpolation::Linear),
+                    Some(Interpolation::CubicSpline),
+                ] {
+                    ui.selectable_value(
+                        &mut self.interpolation_override,
+                        interpolation,
+                        interpolation_label(interpolation),
+                    );
+                }
+            })
+            .response
+            .on_hover_text("Forces the interpolation of all channels, linear channels sampled as cubic splines use Catmull-Rom tangents");
+
+        CollapsingHeader::new("All clips").show(ui, |ui| {
+            if let Some(scrub) = clip_overview(ui, &animations.animations, active_animation) {
+                clip_scrub = Some(scrub);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Disable channels:");
+            ui.checkbox(&mut self.disable_translation, "Translation");
+            ui.checkbox(&mut self.disable_rotation, "Rotation");
+            ui.checkbox(&mut self.disable_scale, "Scale");
+        })
+        .response
+        .on_hover_text("Disabled channels are replaced by the rest pose");
+
+        Self::show_blend_tree(animations, ui);
+
+        if let Some(i) = switch_to {
+            let crossfade_duration = self.crossfade.then_some(self.crossfade_duration);
+            animations.switch_animation(i, crossfade_duration);
+        }
+
+        // Scrubbing in the overview activates the clip immediately and pauses it at the pointer
+        if let Some(scrub) = clip_scrub {
+            animations.switch_animation(scrub.animation, None);
+
+            let animation = &mut animations.animations[scrub.animation];
+            animation.current_time = scrub.time.clamp(animation.trim_start, animation.trim_end);
+            animations.animation_control = AnimationControl::Controllable {
+                active_animation: scrub.animation,
+            };
+        }
+
+        if animations.crossfade.is_some() {
+            ui.ctx().request_repaint();
+        }
+
+        let i = match animations.active_animation() {
+            Some(i) => i,
+            None => return,
+        };
+        let animation = &mut animations.animations[i];
+        let mut toggle_playback = false;
+
+        ui.group(|ui| {
+            ui.label(format!(
+                "Time: {:.2} s (playing {:.2} s - {:.2} s)",
+                animation.current_time, animation.trim_start, animation.trim_end
+            ));
+
+            let response = timeline(ui, animation, self.snap_to_keyframes);
+            let mut scrubbed = response.scrubbed;
+
+            ui.horizontal(|ui| {
+                let current_time = animation.current_time;
+
+                if ui.button("< Keyframe").clicked() {
+                    if let Some(time) = animation.previous_keyframe(current_time) {
+                        animation.current_time = time.max(animation.trim_start);
+                        scrubbed = true;
+                    }
+                }
+
+                if ui.button("Keyframe >").clicked() {
+                    if let Some(time) = animation.next_keyframe(current_time) {
+                        animation.current_time = time.min(animation.trim_end);
+                        scrubbed = true;
+                    }
+                }
+
+                ui.checkbox(&mut self.snap_to_keyframes, "Snap to keyframes");
+            });
+
+            if ui.button("Reset trim").clicked() {
+                animation.set_trim(0., animation.end_time);
+            }
+
+            Self::show_markers(animation, ui);
+
+            ui.label("Recent events:");
+            for event in &self.recent_events {
+                ui.label(RichText::new(event).color(Color32::from_rgb(240, 170, 40)));
+            }
+
+            if scrubbed {
+                animations.animation_control = AnimationControl::Controllable {
+                    active_animation: i,
+                };
+            }
+
+            if let AnimationControl::Loop {
+                active_animation: _,
+                start_time: _,
+            } = animations.animation_control
+            {
+                ui.ctx().request_repaint();
+            }
+
+            let mut step = 0.;
+            ui.horizontal(|ui| {
+                if ui.button("< Frame").on_hover_text("Left arrow").clicked() {
+                    step = -self.frame_step_ms / 1000.;
+                }
+
+                if ui.button("Frame >").on_hover_text("Right arrow").clicked() {
+                    step = self.frame_step_ms / 1000.;
+                }
+
+                ui.add(
+                    egui::DragValue::new(&mut self.frame_step_ms)
+                        .speed(0.1)
+                        .clamp_range(0.1..=1000.0)
+                        .suffix(" ms"),
+                )
+                .on_hover_text("Step of the frame by frame playback (16.67 ms is 60 FPS)");
+            });
+
+            // Stepping pauses the animation
+            if step != 0. {
+                animation.step(step);
+                animations.animation_control = AnimationControl::Controllable {
+                    active_animation: i,
+                };
+            }
+
+            toggle_playback = ui.button("Play").clicked();
+        });
+
+        // The paused animation continues from its current time
+        if toggle_playback {
+            if animations.is_playing() {
+                animations.pause();
+            } else {
+                animations.resume();
+            }
+        }
+
+        self.show_recording(scene, ui);
+    }
+
+    /// Creates the button starting the recording of the active clip, or the progress of the recording
+    fn show_recording(&mut self, scene: &[Model], ui: &mut Ui) {
+        if let Some(recording) = &self.recording {
+            let (frame, frame_count) = recording.progress();
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(frame as f32 / frame_count as f32)
+                        .text(format!("Recording frame {frame} / {frame_count}")),
+                );
+
+                if ui.button("Cancel").clicked() {
+                    self.recording = None;
+                }
+            });
+
+            ui.ctx().request_repaint();
+            return;
+        }
+
+        let clicked = ui
+            .button("Record clip")
+            .on_hover_text(
+                "Saves every frame of the clip as a PNG image, the step is the fixed timestep",
+            )
+            .clicked();
+
+        if !clicked {
+            return;
+        }
+
+        let dir = match rfd::FileDialog::new()
+            .set_title("Record clip into")
+            .pick_folder()
+        {
+            Some(dir) => dir,
+            None => return,
+        };
+
+        let model = &scene[self.selected_model];
+        let step = self.fixed_timestep_ms / 1000.;
+        self.recording = ClipRecording::new(dir, self.selected_model, model, step);
+    }
+
+    /// Saves the frame of the recorded clip that was just rendered into the window.
+    ///
+    /// Has to be called after rendering the scene and before the GUI is drawn.
+    /// The recording stops at the end of the clip, on an error or when another model is selected.
+    pub fn record_frame(&mut self, window: &MyWindow) {
+        let recording = match &mut self.recording {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        if recording.model != self.selected_model {
+            self.recording = None;
+            return;
+        }
+
+        match recording.capture(window) {
+            Ok(false) => {}
+            Ok(true) => {
+                let (frame_count, _) = recording.progress();
+                println!(
+                    "Recorded {frame_count} frames into '{}'",
+                    recording.dir().display()
+                );
+                self.recording = None;
+            }
+            Err(e) => {
+                eprintln!("ERROR: {e:?}");
+                self.notification = Some(format!("Recording failed: {e}"));
+                self.recording = None;
+            }
+        }
+    }
+
+    /// Creates the editor of the event markers of the animation
+    fn show_markers(animation: &mut Animation, ui: &mut Ui) {
+        CollapsingHeader::new("Event markers").show(ui, |ui| {
+            let end_time = animation.end_time;
+
+            let mut remove = None;
+            for (i, marker) in animation.markers.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut marker.time)
+                            .speed(0.01)
+                            .clamp_range(0.0..=end_time)
+                            .suffix(" s"),
+                    );
+                    ui.text_edit_singleline(&mut marker.label);
+
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = remove {
+                animation.markers.remove(i);
+            }
+
+            if ui.button("Add marker at playhead").clicked() {
+                animation.markers.push(AnimationMarker {
+                    time: animation.current_time,
+                    label: format!("Event {}", animation.markers.len() + 1),
+                });
+            }
+        });
+    }
+
+    /// Name of the animation together with its duration
+    fn animation_label(animation: &Animation, index: usize) -> String {
+        let name = match &animation.name {
+            Some(name) => name.clone(),
+            None => format!("Animation-{index}"),
+        };
+
+        format!("{name} ({:.2} s)", animation.end_time)
+    }
+}
+
+impl Default for Gui {
+    fn default() -> Self {
+        Self::new()
     }
 }
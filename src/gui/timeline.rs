@@ -0,0 +1,252 @@
+use egui::{pos2, vec2, Align2, Color32, Rect, Response, Sense, Stroke, TextStyle, Ui};
+
+use crate::model::Animation;
+
+/// Height of the whole timeline widget
+const HEIGHT: f32 = 26.;
+/// Width of the trim handles
+const HANDLE_WIDTH: f32 = 7.;
+/// Width of the marker handles
+const MARKER_WIDTH: f32 = 6.;
+/// Color of the markers
+const MARKER_COLOR: Color32 = Color32::from_rgb(240, 170, 40);
+/// Color of the playhead
+const PLAYHEAD_COLOR: Color32 = Color32::from_rgb(230, 60, 60);
+/// Height of a row of the clip overview
+const CLIP_ROW_HEIGHT: f32 = 18.;
+
+/// What the user changed on the timeline
+pub struct TimelineResponse {
+    /// The playhead was moved
+    pub scrubbed: bool,
+}
+
+/// A timeline scrubber of an animation.
+///
+/// Dragging the bar moves the playhead (the current time), the handles at the edges
+/// of the highlighted region move the trim markers.
+/// Keyframes are marked by ticks, the playhead snaps to them if 'snap_to_keyframes' is set.
+/// Event markers are shown above the bar and can be dragged.
+pub fn timeline(
+    ui: &mut Ui,
+    animation: &mut Animation,
+    snap_to_keyframes: bool,
+) -> TimelineResponse {
+    let size = vec2(ui.available_width(), HEIGHT);
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let id = ui.make_persistent_id(("timeline", animation.name.as_deref()));
+
+    let duration = animation.end_time.max(f32::EPSILON);
+    let time_to_x = |time: f32| rect.left() + (time / duration) * rect.width();
+    let x_to_time = |x: f32| ((x - rect.left()) / rect.width()).clamp(0., 1.) * duration;
+
+    let handle_rect = |time: f32| {
+        let x = time_to_x(time);
+        Rect::from_x_y_ranges(
+            (x - HANDLE_WIDTH / 2.)..=(x + HANDLE_WIDTH / 2.),
+            rect.y_range(),
+        )
+    };
+
+    let marker_rect = |time: f32| {
+        let x = time_to_x(time);
+        Rect::from_x_y_ranges(
+            (x - MARKER_WIDTH / 2.)..=(x + MARKER_WIDTH / 2.),
+            rect.top()..=(rect.top() + HEIGHT / 4.),
+        )
+    };
+
+    // The handles have to be interacted with before the bar so that they get the drag priority
+    let marker_handles: Vec<Response> = animation
+        .markers
+        .iter()
+        .enumerate()
+        .map(|(i, marker)| {
+            ui.interact(
+                marker_rect(marker.time),
+                id.with(("marker", i)),
+                Sense::drag(),
+            )
+            .on_hover_text(&marker.label)
+        })
+        .collect();
+
+    let start_handle = ui.interact(
+        handle_rect(animation.trim_start),
+        id.with("trim_start"),
+        Sense::drag(),
+    );
+    let end_handle = ui.interact(
+        handle_rect(animation.trim_end),
+        id.with("trim_end"),
+        Sense::drag(),
+    );
+    let bar = ui.interact(rect, id.with("bar"), Sense::click_and_drag());
+
+    if let Some(time) = dragged_time(&start_handle, x_to_time) {
+        animation.set_trim(time, animation.trim_end);
+    }
+
+    if let Some(time) = dragged_time(&end_handle, x_to_time) {
+        animation.set_trim(animation.trim_start, time);
+    }
+
+    for (marker, handle) in animation.markers.iter_mut().zip(&marker_handles) {
+        if let Some(time) = dragged_time(handle, x_to_time) {
+            marker.time = time;
+        }
+    }
+
+    let mut scrubbed = false;
+    if bar.clicked() || bar.dragged() {
+        if let Some(pointer_pos) = bar.interact_pointer_pos() {
+            let mut time = x_to_time(pointer_pos.x);
+            if snap_to_keyframes {
+                time = animation.nearest_keyframe(time).unwrap_or(time);
+            }
+
+            animation.current_time = time.clamp(animation.trim_start, animation.trim_end);
+            scrubbed = true;
+        }
+    }
+
+    // Drawing
+    let visuals = ui.visuals();
+    let painter = ui.painter();
+
+    let bar_rect = rect.shrink2(vec2(0., HEIGHT / 4.));
+    painter.rect_filled(bar_rect, 2., visuals.extreme_bg_color);
+
+    let trimmed_rect = Rect::from_x_y_ranges(
+        time_to_x(animation.trim_start)..=time_to_x(animation.trim_end),
+        bar_rect.y_range(),
+    );
+    painter.rect_filled(trimmed_rect, 2., visuals.selection.bg_fill);
+
+    let tick_stroke = Stroke::new(1., visuals.widgets.noninteractive.fg_stroke.color);
+    for time in &animation.keyframe_times {
+        let x = time_to_x(*time);
+        painter.line_segment(
+            [pos2(x, bar_rect.bottom()), pos2(x, rect.bottom())],
+            tick_stroke,
+        );
+    }
+
+    for marker in &animation.markers {
+        let x = time_to_x(marker.time);
+        painter.line_segment(
+            [pos2(x, rect.top()), pos2(x, bar_rect.bottom())],
+            Stroke::new(1., MARKER_COLOR),
+        );
+        painter.rect_filled(marker_rect(marker.time), 1., MARKER_COLOR);
+    }
+
+    for handle in [&start_handle, &end_handle] {
+        let style = ui.style().interact(handle);
+        painter.rect_filled(handle.rect, 2., style.fg_stroke.color);
+    }
+
+    let playhead_x = time_to_x(animation.current_time);
+    painter.line_segment(
+        [
+            pos2(playhead_x, rect.top()),
+            pos2(playhead_x, rect.bottom()),
+        ],
+        Stroke::new(2., PLAYHEAD_COLOR),
+    );
+
+    TimelineResponse { scrubbed }
+}
+
+/// A clip of the overview that the user clicked or dragged on
+pub struct ClipScrub {
+    /// Index of the clip
+    pub animation: usize,
+    /// Time under the pointer
+    pub time: f32,
+}
+
+/// All of the clips stacked under each other, with the lengths of the bars proportional to their durations.
+///
+/// The active clip is highlighted and shows its playhead.
+/// Clicking or dragging on a bar returns the clip and the time under the pointer.
+pub fn clip_overview(
+    ui: &mut Ui,
+    animations: &[Animation],
+    active_animation: Option<usize>,
+) -> Option<ClipScrub> {
+    let longest = animations
+        .iter()
+        .map(|animation| animation.end_time)
+        .fold(f32::EPSILON, f32::max);
+
+    let mut scrub = None;
+    for (i, animation) in animations.iter().enumerate() {
+        let size = vec2(ui.available_width(), CLIP_ROW_HEIGHT);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+
+        let bar_rect = Rect::from_min_size(
+            rect.min,
+            vec2(
+                rect.width() * (animation.end_time / longest).max(0.01),
+                rect.height() - 2.,
+            ),
+        );
+        let duration = animation.end_time.max(f32::EPSILON);
+        let time_to_x = |time: f32| bar_rect.left() + (time / duration) * bar_rect.width();
+
+        if response.clicked() || response.dragged() {
+            if let Some(pointer_pos) = response.interact_pointer_pos() {
+                let fraction = (pointer_pos.x - bar_rect.left()) / bar_rect.width();
+                scrub = Some(ClipScrub {
+                    animation: i,
+                    time: fraction.clamp(0., 1.) * animation.end_time,
+                });
+            }
+        }
+
+        // Drawing
+        let is_active = active_animation == Some(i);
+        let visuals = ui.visuals();
+        let painter = ui.painter().sub_region(rect);
+
+        let fill = match is_active {
+            true => visuals.selection.bg_fill,
+            false => visuals.widgets.inactive.bg_fill,
+        };
+        painter.rect_filled(bar_rect, 2., fill);
+
+        let name = match &animation.name {
+            Some(name) => name.clone(),
+            None => format!("Animation-{i}"),
+        };
+        painter.text(
+            pos2(bar_rect.left() + 4., bar_rect.center().y),
+            Align2::LEFT_CENTER,
+            format!("{name} ({:.2} s)", animation.end_time),
+            TextStyle::Small,
+            visuals.text_color(),
+        );
+
+        if is_active {
+            let x = time_to_x(animation.current_time);
+            painter.line_segment(
+                [pos2(x, bar_rect.top()), pos2(x, bar_rect.bottom())],
+                Stroke::new(2., PLAYHEAD_COLOR),
+            );
+        }
+    }
+
+    scrub
+}
+
+/// Time under the pointer if the handle is being dragged
+fn dragged_time(handle: &Response, x_to_time: impl Fn(f32) -> f32) -> Option<f32> {
+    if !handle.dragged() {
+        return None;
+    }
+
+    handle
+        .interact_pointer_pos()
+        .map(|pointer_pos| x_to_time(pointer_pos.x))
+}
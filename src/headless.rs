@@ -0,0 +1,229 @@
+use std::{fs, path::Path};
+
+use eyre::{eyre, Context, Result};
+use glam::Vec3;
+
+use crate::{
+    camera::Camera,
+    gui::Gui,
+    model::Model,
+    ogl,
+    ogl::framebuffer::Framebuffer,
+    renderer::{RenderTarget, Renderer},
+    window::MyWindow,
+};
+
+/// Options for rendering models into images
+pub struct HeadlessOptions {
+    pub job: HeadlessJob,
+    /// Width of the images
+    pub width: u32,
+    /// Height of the images
+    pub height: u32,
+}
+
+/// What should be rendered
+pub enum HeadlessJob {
+    /// Render a single model into the output image
+    Render {
+        /// Path to the gltf file
+        model_path: String,
+        /// Path of the output PNG image
+        out_path: String,
+    },
+    /// Render a thumbnail for every gltf / glb file in the directory
+    Thumbnails {
+        /// Directory containing the models, the thumbnails are saved beside them
+        dir: String,
+    },
+}
+
+impl HeadlessOptions {
+    /// Parses the `--render path.gltf [--out thumb.png] [--size WxH]`
+    /// or the `--thumbnails directory [--size WxH]` arguments.
+    ///
+    /// Returns None if neither `--render` nor `--thumbnails` is specified.
+    /// The default output path is the model path with a `.png` extension, the default size is 512x512.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>> {
+        let mut model_path = None;
+        let mut thumbnails_dir = None;
+        let mut out_path = None;
+        let mut size = (512, 512);
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| eyre!("Missing a value for the '{arg}' argument"))
+            };
+
+            match arg.as_str() {
+                "--render" => model_path = Some(value()?.clone()),
+                "--thumbnails" => thumbnails_dir = Some(value()?.clone()),
+                "--out" => out_path = Some(value()?.clone()),
+                "--size" => size = parse_size(value()?)?,
+                _ => return Err(eyre!("Unknown argument: '{arg}'")),
+            }
+        }
+
+        let job = match (model_path, thumbnails_dir) {
+            (Some(_), Some(_)) => {
+                return Err(eyre!(
+                    "'--render' and '--thumbnails' can't be used together"
+                ))
+            }
+            (Some(model_path), None) => {
+                let out_path = out_path.unwrap_or_else(|| thumbnail_path(Path::new(&model_path)));
+                HeadlessJob::Render {
+                    model_path,
+                    out_path,
+                }
+            }
+            (None, Some(dir)) => {
+                if out_path.is_some() {
+                    return Err(eyre!("'--out' can't be used with '--thumbnails'"));
+                }
+                HeadlessJob::Thumbnails { dir }
+            }
+            (None, None) => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            job,
+            width: size.0,
+            height: size.1,
+        }))
+    }
+}
+
+/// Parses the image size in the 'WxH' format
+fn parse_size(size: &str) -> Result<(u32, u32)> {
+    let parse = || -> Option<(u32, u32)> {
+        let (width, height) = size.split_once('x')?;
+        let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+        (width > 0 && height > 0).then_some((width, height))
+    };
+
+    parse().ok_or_else(|| eyre!("Invalid size: '{size}', the expected format is 'WxH'"))
+}
+
+/// The model path with a `.png` extension
+fn thumbnail_path(model_path: &Path) -> String {
+    model_path
+        .with_extension("png")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Executes the job without user interaction
+pub fn run(options: &HeadlessOptions) -> Result<()> {
+    let mut headless = Headless::new(options.width, options.height)?;
+
+    match &options.job {
+        HeadlessJob::Render {
+            model_path,
+            out_path,
+        } => headless.render_to_file(model_path, out_path),
+        HeadlessJob::Thumbnails { dir } => headless.render_thumbnails(dir),
+    }
+}
+
+/// State shared by all of the rendered images
+struct Headless {
+    /// Owns the OpenGL context
+    _window: MyWindow,
+    renderer: Renderer,
+    gui: Gui,
+    framebuffer: Framebuffer,
+}
+
+impl Headless {
+    /// Creates a hidden window and an offscreen framebuffer of the image size
+    fn new(width: u32, height: u32) -> Result<Self> {
+        let window = MyWindow::new_hidden("Leoric", width, height)?;
+
+        ogl::init_debug();
+
+        Ok(Self {
+            _window: window,
+            renderer: Renderer::new()?,
+            gui: Gui::new(),
+            framebuffer: Framebuffer::new(width, height)?,
+        })
+    }
+
+    /// Renders the model framed by the camera and saves it as a PNG image
+    fn render_to_file(&mut self, model_path: &str, out_path: &str) -> Result<()> {
+        let (width, height) = (self.framebuffer.width, self.framebuffer.height);
+
+        let mut scene = vec![Model::from_gltf(model_path)?];
+
+        let mut camera = Camera::new(Vec3::ZERO, 0.05, 0.05, width, height);
+        camera.frame_bounds(&scene[0].bounds(), width as f32 / height as f32);
+
+        let target = RenderTarget::framebuffer(&self.framebuffer);
+        self.renderer
+            .render_to(&mut scene, &mut camera, &self.gui, target);
+
+        let pixels = self.framebuffer.read_pixels();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        save_png(out_path, width, height, &pixels)
+    }
+
+    /// Renders `<name>.png` beside every gltf / glb file in the directory.
+    ///
+    /// Failed models are reported and skipped, an error is returned at the end if any of them failed.
+    fn render_thumbnails(&mut self, dir: &str) -> Result<()> {
+        let mut model_paths = Vec::new();
+        for entry in fs::read_dir(dir).wrap_err_with(|| format!("Couldn't read '{dir}'"))? {
+            let path = entry?.path();
+
+            let is_model = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.eq_ignore_ascii_case("gltf") || ext.eq_ignore_ascii_case("glb"))
+                .unwrap_or(false);
+
+            if is_model && path.is_file() {
+                model_paths.push(path);
+            }
+        }
+
+        model_paths.sort();
+
+        let mut failed = 0;
+        for model_path in &model_paths {
+            let out_path = thumbnail_path(model_path);
+            let model_path = model_path.to_string_lossy();
+
+            match self.render_to_file(&model_path, &out_path) {
+                Ok(_) => println!("Rendered '{out_path}'"),
+                Err(e) => {
+                    eprintln!("ERROR: couldn't render '{model_path}': {e:?}");
+                    failed += 1;
+                }
+            }
+        }
+
+        println!(
+            "Rendered {} of {} thumbnails in '{dir}'",
+            model_paths.len() - failed,
+            model_paths.len()
+        );
+
+        if failed > 0 {
+            return Err(eyre!("{failed} thumbnail(s) couldn't be rendered"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Saves RGBA8 pixels (starting with the top row) as a PNG image
+pub fn save_png(path: &str, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    image::save_buffer(path, pixels, width, height, image::ColorType::Rgba8)
+        .wrap_err_with(|| format!("Couldn't save the image '{path}'"))
+}
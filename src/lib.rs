@@ -0,0 +1,86 @@
+//! PGRF2 project - skeletal animation
+//!
+//! Loading of gltf 2.0 models and rendering them with skinning, usable from other applications.
+//! The `leoric` binary (the viewer) is built on top of this library.
+//!
+//! # Stability
+//!
+//! The types re-exported at the root of the crate are the stable API:
+//! - [`Model`], [`Node`], [`ImportOptions`] and [`LoadStage`] - loading the models and traversing their hierarchy,
+//!   the failures of the loading are described by [`LoadError`]
+//! - [`Transform`] - the local transforms of the joints, which can be overridden with [`Model::set_joint_local`]
+//! - [`Animations`], [`Animation`], [`AnimationControl`] and [`BlendTree1D`] - playing the animations
+//!   ([`Animations::play`], [`Animations::pause`], [`Animations::stop`]) and reading their playhead
+//!   ([`Animations::current_time`], [`Animations::duration`]) to synchronize other systems
+//! - [`Camera`] - the view and projection transformations
+//! - [`Renderer`] and [`RenderTarget`] - drawing the models into the window or an offscreen framebuffer
+//! - [`Gui`] - only its public fields, which hold the render settings passed to the [`Renderer`]
+//!
+//! The renderer requires a current OpenGL 4.3 context with the functions loaded by the `gl` crate
+//! (see [`window::MyWindow`] for an example) and the `shaders` directory in the working directory.
+//!
+//! Everything else (the modules of the viewer: the GUI widgets, the window, the settings file,
+//! the scene files and the headless rendering) can change without notice.
+//!
+//! ```no_run
+//! use leoric::{Camera, Gui, Model, RenderTarget, Renderer};
+//!
+//! # fn frame(width: u32, height: u32) -> eyre::Result<()> {
+//! // An OpenGL context has to be current on this thread
+//! let mut models = vec![Model::from_gltf("resources/squash_stretch/SquashStretch.gltf")?];
+//! let mut camera = Camera::new(glam::Vec3::new(0., 1., 3.), 0.05, 0.05, width, height);
+//! let mut renderer = Renderer::new()?;
+//! let settings = Gui::new();
+//!
+//! let target = RenderTarget {
+//!     framebuffer: 0,
+//!     width,
+//!     height,
+//! };
+//! renderer.render_to(&mut models, &mut camera, &settings, target);
+//! # Ok(())
+//! # }
+//! ```
+
+/// Disabling expensive features when the frames take too long.
+pub mod auto_quality;
+
+/// A module for working with a basic free camera.
+pub mod camera;
+
+/// Application settings persisted between runs.
+pub mod config;
+
+/// All of the code for drawing the GUI using egui.
+pub mod gui;
+
+/// Rendering models into image files without user interaction.
+pub mod headless;
+
+/// Represents a single gltf 2.0 model (used models only have 1 scene).
+pub mod model;
+
+/// Recording the animations into image sequences.
+pub mod recording;
+
+/// Handles rendering the whole scene.
+pub mod renderer;
+
+/// Saving and loading snapshots of the whole scene.
+pub mod scene_state;
+
+/// Abstractions for working with OpenGL.
+pub mod ogl;
+
+/// Handles window creation and egui boilerplate.
+pub mod window;
+
+pub use self::{
+    camera::Camera,
+    gui::Gui,
+    model::{
+        Animation, AnimationControl, Animations, BlendTree1D, ImportOptions, LoadError, LoadStage,
+        Model, Node, NormalizeScale, Transform,
+    },
+    renderer::{RenderTarget, Renderer},
+};
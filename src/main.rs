@@ -1,44 +1,59 @@
 //! PGRF2 project - skeletal animation
 //!
-//! `main` function is the entry-point
-use std::{thread, time::Duration};
+//! `main` function is the entry-point, the viewer is built on top of the `leoric` library
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
 
-use camera::Camera;
 use eyre::Result;
 use glam::{Mat4, Vec3};
-use gui::Gui;
-use model::Model;
-use renderer::Renderer;
+use leoric::{
+    config::AppConfig,
+    headless::{self, HeadlessOptions},
+    ogl,
+    window::MyWindow,
+    Camera, Gui, ImportOptions, Model, Renderer,
+};
 use sdl2::{keyboard::Scancode, EventPump};
 
-use window::MyWindow;
+/// How long to wait for events at once while the window is minimized or unfocused (in milliseconds)
+const INACTIVE_WAIT_MS: u32 = 100;
 
-/// A module for working with a basic free camera.
-mod camera;
+/// Creates the window, configures OpenGL, sets up the scene and begins the render loop.
+///
+/// If started with `--render path.gltf` or `--thumbnails directory`, models are rendered into images instead.
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(options) = HeadlessOptions::from_args(&args)? {
+        return headless::run(&options);
+    }
 
-/// All of the code for drawing the GUI using egui.
-mod gui;
+    let mut config = AppConfig::load();
 
-/// Represents a single gltf 2.0 model (used models only have 1 scene).
-mod model;
+    let mut window = MyWindow::new(
+        "PGRF2 Projekt - Skeletální Animace - Tomáš Král",
+        config.window,
+        config.msaa_samples,
+    )?;
 
-/// Handles rendering the whole scene.
-mod renderer;
+    ogl::init_debug();
 
-/// Abstractions for working with OpenGL.
-mod ogl;
+    let dark_mode = config.gui.dark_mode;
+    window.egui_ctx.set_visuals(match dark_mode {
+        true => egui::Visuals::dark(),
+        false => egui::Visuals::light(),
+    });
 
-/// Handles window creation and egui boilerplate.
-mod window;
+    let mut gui = Gui::with_config(&config);
+    let mut scene = setup_scene(gui.import_options())?;
+    add_opened_models(&mut scene, &mut gui);
 
-/// Creates the window, configures OpenGL, sets up the scene and begins the render loop.
-fn main() -> Result<()> {
-    let mut window = MyWindow::new("PGRF2 Projekt - Skeletální Animace - Tomáš Král")?;
+    let mut vsync = gui.vsync;
+    window.set_vsync(vsync);
 
-    ogl::init_debug();
+    let mut left_was_pressed = false;
 
-    let mut scene = setup_scene()?;
-    let mut gui = Gui::new();
     let mut renderer = Renderer::new()?;
     let mut camera = Camera::new(
         Vec3::new(0.2, 3., 7.5),
@@ -49,32 +64,83 @@ fn main() -> Result<()> {
     );
 
     'render_loop: loop {
+        if window.is_inactive() {
+            let inactive_start = Instant::now();
+
+            while window.is_inactive() {
+                if window.wait_for_events(INACTIVE_WAIT_MS) {
+                    break 'render_loop;
+                }
+            }
+
+            if gui.pause_animations_when_inactive {
+                let inactive = inactive_start.elapsed();
+                for model in &mut scene {
+                    model.animations.delay(inactive);
+                }
+            }
+        }
+
+        let frame_start = Instant::now();
+
         handle_inputs(&mut window.event_pump, &mut camera);
 
+        if let Some(recording) = &gui.recording {
+            recording.pose(&mut scene[recording.model]);
+        } else if let Some(step) = gui.fixed_timestep() {
+            for model in &mut scene {
+                model.animations.advance_fixed(step);
+            }
+        }
+
         window.begin_frame();
 
         renderer.render(&mut scene, &mut camera, &window, &gui);
-        gui.create_gui(&mut scene, &mut camera, &mut window.egui_ctx);
+        gui.record_frame(&window);
+        gui.create_gui(&mut scene, &mut camera, &renderer, &mut window.egui_ctx);
+
+        // After the GUI is created, so that clicks on the side panel are recognized
+        if let Some((x, y)) = clicked_pos(&window, &mut left_was_pressed) {
+            gui.picked = renderer.pick(&mut scene, &mut camera, &window, &gui, x, y);
+        }
+
+        if gui.vsync != vsync {
+            vsync = gui.vsync;
+            window.set_vsync(vsync);
+        }
 
         let should_quit = window.end_frame();
         if should_quit {
             break 'render_loop;
         }
 
+        // The sleep isn't part of the frame time, saving the recorded frames shouldn't lower the quality
+        if gui.recording.is_none() {
+            gui.auto_quality.update(frame_start.elapsed());
+        }
+
         thread::sleep(Duration::from_millis(3));
     }
 
+    config.window = Some(window.config());
+    config.gui.dark_mode = window.egui_ctx.style().visuals.dark_mode;
+    gui.save_config(&mut config);
+
+    if let Err(e) = config.save() {
+        eprintln!("WARN: {e:?}");
+    }
+
     Ok(())
 }
 
 /// Adds models to the scene
-fn setup_scene() -> Result<Vec<Model>> {
+fn setup_scene(options: ImportOptions) -> Result<Vec<Model>> {
     let mut scene = Vec::new();
 
     let mut add = |path: &str| -> Result<()> {
         let start = std::time::Instant::now();
 
-        let model = Model::from_gltf(path)?;
+        let model = Model::from_gltf_with_options(path, options.clone())?;
 
         let time = std::time::Instant::now().duration_since(start);
         println!("Loading '{path}' took '{time:?}'");
@@ -88,12 +154,50 @@ fn setup_scene() -> Result<Vec<Model>> {
     add("resources/pakistan_girl_-_animated/Girl.gltf")?;
     add("resources/toon_cat_free/Cat.gltf")?;
 
-    let cat = scene.last_mut().unwrap();
-    cat.transform = Mat4::from_scale(Vec3::splat(0.015));
+    // The cat is authored much larger than the other models
+    if options.normalize_scale.is_none() {
+        let cat = scene.last_mut().unwrap();
+        cat.transform = Mat4::from_scale(Vec3::splat(0.015));
+    }
 
     Ok(scene)
 }
 
+/// Loads the models that were opened at runtime in the previous run.
+///
+/// Models that can't be loaded anymore are skipped and forgotten.
+/// The models are converted with the current import options.
+fn add_opened_models(scene: &mut Vec<Model>, gui: &mut Gui) {
+    let options = gui.import_options();
+
+    gui.opened_models.retain(
+        |path| match Model::from_gltf_with_options(path, options.clone()) {
+            Ok(mut model) => {
+                model.auto_layout(&scene[0].bounds());
+                scene.push(model);
+                true
+            }
+            Err(e) => {
+                eprintln!("WARN: couldn't load the previously opened model '{path}': {e:?}");
+                false
+            }
+        },
+    );
+}
+
+/// Returns the position of the mouse if the left button was just pressed outside of the GUI
+fn clicked_pos(window: &MyWindow, left_was_pressed: &mut bool) -> Option<(u32, u32)> {
+    let mouse_state = window.event_pump.mouse_state();
+    let just_pressed = mouse_state.left() && !*left_was_pressed;
+    *left_was_pressed = mouse_state.left();
+
+    if !just_pressed || window.egui_ctx.is_pointer_over_area() {
+        return None;
+    }
+
+    Some((mouse_state.x().max(0) as u32, mouse_state.y().max(0) as u32))
+}
+
 /// Modifies camera state based on the mouse / keyboard inputs
 fn handle_inputs(event_pump: &mut EventPump, camera: &mut Camera) {
     let k = event_pump.keyboard_state();
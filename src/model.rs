@@ -1,78 +1,572 @@
-use std::path::Path;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Instant,
+};
 
 use eyre::{eyre, Result};
 use glam::{Mat4, Quat, Vec3};
 use gltf::scene::Transform as GTransform;
+use serde::{Deserialize, Serialize};
+
+use self::material_animation::PointerChannel;
 
+mod aabb;
 mod animation;
+mod asset_camera;
+mod asset_light;
+mod blend_tree;
+mod handedness;
 mod joints;
+mod load_error;
+mod look_at;
+mod material_animation;
 mod mesh;
+mod raycast;
+mod retarget;
 mod transform;
 
 pub use self::{
-    animation::{Animation, AnimationControl, AnimationTransform, AnimationTransforms, Animations},
+    aabb::Aabb,
+    animation::{
+        Animation, AnimationControl, AnimationMarker, AnimationTransform, AnimationTransforms,
+        Animations, NodeAnimationTransform,
+    },
+    asset_camera::{AssetCamera, CameraProjection},
+    asset_light::{AssetLight, LightKind},
+    blend_tree::{BlendPoint, BlendTree1D},
+    handedness::{Handedness, MirrorAxis, UpAxis},
     joints::{Joint, Joints},
-    mesh::{Mesh, Primitive, PrimitiveTexture},
-    transform::Transform,
+    load_error::LoadError,
+    look_at::{JointAxis, LookAt},
+    material_animation::{MaterialChannel, MaterialProperty},
+    mesh::{
+        ColorSpace, ImageTextures, Mesh, NormalMode, OrmChannels, PbrMaterial, PendingTexture,
+        PrimSkin, Primitive, PrimitiveTexture, TriangleHit,
+    },
+    raycast::{ray_aabb, ray_triangle, raycast_models, Hit},
+    transform::{Transform, MIN_DETERMINANT},
 };
 
+/// Options for loading a model
+#[derive(Clone)]
+pub struct ImportOptions {
+    /// How to handle non-finite values in the vertex data and animations
+    pub validation: Validation,
+    /// Conversions of left-handed / mirrored assets
+    pub handedness: Handedness,
+    /// Name of the node whose subtree is loaded instead of the whole scene (see `Model::from_gltf_node`)
+    pub root_node: Option<String>,
+    /// If the CPU copies of the vertex data are kept after the upload.
+    ///
+    /// They are needed for raycasting and for switching the normal mode, dropping them saves memory.
+    pub keep_vertex_data: bool,
+    /// Scales the model to a common size (stored in `Model::transform`), None keeps the authored scale
+    pub normalize_scale: Option<NormalizeScale>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            validation: Validation::Warn,
+            handedness: Handedness::default(),
+            root_node: None,
+            keep_vertex_data: true,
+            normalize_scale: None,
+        }
+    }
+}
+
+/// The size the bounding box of a model is scaled to (see `Model::normalize_scale`)
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum NormalizeScale {
+    /// The longest side of the bounding box
+    Size(f32),
+    /// The height of the bounding box (along Y)
+    Height(f32),
+}
+
+/// What to do with non-finite (NaN / Inf) values in the vertex data and animations.
+///
+/// Checking the data requires a scan over all of the vertices.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /// Don't check the data
+    Off,
+    /// Log a warning naming the offending primitive / channel
+    Warn,
+    /// Log a warning and skip the offending primitives / channels
+    Skip,
+}
+
+/// Stages of loading a model, reported to the progress callback of `Model::from_gltf_with_progress`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoadStage {
+    /// Reading and parsing the gltf / glb file
+    Parsing,
+    /// Loading the buffers (external files, data URIs or the binary chunk)
+    Buffers,
+    /// Decoding the images of the textures
+    Images,
+    /// Creating the meshes and uploading their vertex data to the GPU
+    Meshes,
+    /// Reading the animations
+    Animations,
+}
+
+/// Callback receiving the stage of the loading and the completed fraction (0 to 1) of the stage
+pub type LoadProgress<'a> = &'a mut dyn FnMut(LoadStage, f32);
+
+/// The fraction of the stage completed after `done` of `total` items, stages without items are complete
+fn stage_fraction(done: usize, total: usize) -> f32 {
+    match total {
+        0 => 1.,
+        _ => done as f32 / total as f32,
+    }
+}
+
 /// Image and vertex data of the asset.
-pub struct DataBundle {
+///
+/// The bundle only lives while a single model is being loaded, nothing is cached between loads.
+pub struct DataBundle<'a> {
     /// Vertex data
     buffers: Vec<gltf::buffer::Data>,
     /// Texture data
     images: Vec<gltf::image::Data>,
-    /// To keep track if which textures were already sent to the GPU
-    pub gl_textures: Vec<Option<PrimitiveTexture>>,
+    /// To keep track if which textures were already created (for each image and color space)
+    pub gl_textures: Vec<ImageTextures>,
+    /// Options the model is loaded with
+    pub options: ImportOptions,
+    /// KHR_animation_pointer channels removed from the JSON
+    pub pointer_channels: Vec<PointerChannel>,
+    /// Reports the progress of the loading
+    progress: LoadProgress<'a>,
+    /// Number of meshes instantiated by the nodes of the scene
+    mesh_count: usize,
+    /// Number of meshes created so far
+    loaded_meshes: usize,
+    /// The nodes whose animation channels are loaded (None loads all of them)
+    pub loaded_nodes: Option<HashSet<usize>>,
 }
 
-impl DataBundle {
-    fn new(buffers: Vec<gltf::buffer::Data>, images: Vec<gltf::image::Data>) -> Self {
+impl<'a> DataBundle<'a> {
+    fn new(
+        buffers: Vec<gltf::buffer::Data>,
+        images: Vec<gltf::image::Data>,
+        options: ImportOptions,
+        pointer_channels: Vec<PointerChannel>,
+        progress: LoadProgress<'a>,
+    ) -> Self {
         Self {
             buffers,
-            gl_textures: vec![Option::None; images.len()],
+            gl_textures: vec![ImageTextures::default(); images.len()],
             images,
+            options,
+            pointer_channels,
+            progress,
+            mesh_count: 0,
+            loaded_meshes: 0,
+            loaded_nodes: None,
+        }
+    }
+
+    /// Reports that another mesh was created
+    fn mesh_loaded(&mut self) {
+        self.loaded_meshes += 1;
+        (self.progress)(
+            LoadStage::Meshes,
+            stage_fraction(self.loaded_meshes, self.mesh_count),
+        );
+    }
+
+    /// Reports the progress of a stage
+    fn report(&mut self, stage: LoadStage, fraction: f32) {
+        (self.progress)(stage, fraction);
+    }
+
+    /// Loads the gltf document together with its buffers and images.
+    ///
+    /// External resources are resolved relative to the directory of the gltf file, not the working directory.
+    pub(crate) fn import(
+        path: &Path,
+        options: ImportOptions,
+        progress: LoadProgress<'a>,
+    ) -> Result<(gltf::Document, Self), LoadError> {
+        let base = path.parent().unwrap_or_else(|| Path::new("./"));
+
+        progress(LoadStage::Parsing, 0.);
+        let (gltf::Gltf { document, mut blob }, pointer_channels) = Self::open(path)?;
+        progress(LoadStage::Parsing, 1.);
+
+        let buffer_count = document.buffers().len();
+        let mut buffers = Vec::new();
+        for buffer in document.buffers() {
+            progress(
+                LoadStage::Buffers,
+                stage_fraction(buffer.index(), buffer_count),
+            );
+
+            let resource = match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => Self::describe_uri(base, uri),
+                gltf::buffer::Source::Bin => "the binary chunk".to_string(),
+            };
+
+            let data = match gltf::buffer::Data::from_source_and_blob(
+                buffer.source(),
+                Some(base),
+                &mut blob,
+            ) {
+                Ok(data) => data,
+                Err(e) => {
+                    return Err(LoadError::MissingBuffer {
+                        index: buffer.index(),
+                        resource,
+                        reason: e.to_string(),
+                    })
+                }
+            };
+
+            if data.len() < buffer.length() {
+                return Err(LoadError::MissingBuffer {
+                    index: buffer.index(),
+                    reason: format!(
+                        "it has {} bytes, expected at least {} bytes",
+                        data.len(),
+                        buffer.length()
+                    ),
+                    resource,
+                });
+            }
+
+            buffers.push(data);
+        }
+        progress(LoadStage::Buffers, 1.);
+
+        let images = Self::decode_images(&document, base, &buffers, progress);
+        progress(LoadStage::Images, 1.);
+
+        Ok((
+            document,
+            Self::new(buffers, images, options, pointer_channels, progress),
+        ))
+    }
+
+    /// Decodes the images of the document on multiple threads, in the order of the document.
+    ///
+    /// Every worker takes the next image that isn't decoded yet, the results are collected
+    /// (and the progress is reported) on the calling thread as the images finish.
+    fn decode_images(
+        document: &gltf::Document,
+        base: &Path,
+        buffers: &[gltf::buffer::Data],
+        progress: LoadProgress,
+    ) -> Vec<gltf::image::Data> {
+        let sources: Vec<gltf::Image> = document.images().collect();
+        let image_count = sources.len();
+        progress(LoadStage::Images, 0.);
+
+        let threads = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(image_count);
+        let next_image = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        let mut images: Vec<Option<gltf::image::Data>> = (0..image_count).map(|_| None).collect();
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                let (sources, next_image, sender) = (&sources, &next_image, sender.clone());
+
+                scope.spawn(move || loop {
+                    let index = next_image.fetch_add(1, Ordering::Relaxed);
+                    let image = match sources.get(index) {
+                        Some(image) => image,
+                        None => break,
+                    };
+
+                    let data = gltf::image::Data::from_source(image.source(), Some(base), buffers);
+                    if sender.send((index, data)).is_err() {
+                        break;
+                    }
+                });
+            }
+            // The receiver stops when all of the workers are finished
+            drop(sender);
+
+            for (decoded, (index, data)) in receiver.iter().enumerate() {
+                let resource = match sources[index].source() {
+                    gltf::image::Source::Uri { uri, .. } => Self::describe_uri(base, uri),
+                    gltf::image::Source::View { view, .. } => {
+                        format!("buffer view {}", view.index())
+                    }
+                };
+
+                // A corrupt image shouldn't prevent loading the rest of the model
+                let data = match data {
+                    Ok(data) => data,
+                    Err(e) => {
+                        eprintln!(
+                            "WARN: couldn't load image {index} ({resource}), using a placeholder: {e}"
+                        );
+                        Self::placeholder_image()
+                    }
+                };
+
+                images[index] = Some(data);
+                progress(LoadStage::Images, stage_fraction(decoded + 1, image_count));
+            }
+        });
+
+        // Every index is taken by exactly one worker
+        images
+            .into_iter()
+            .map(|image| image.unwrap_or_else(Self::placeholder_image))
+            .collect()
+    }
+
+    /// Reads and parses the gltf / glb file
+    fn open(path: &Path) -> Result<(gltf::Gltf, Vec<PointerChannel>), LoadError> {
+        let data = fs::read(path).map_err(|source| LoadError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::parse(&data).map_err(|e| LoadError::from_gltf(path.to_path_buf(), e))
+    }
+
+    /// Parses the contents of the gltf / glb file.
+    ///
+    /// Files using KHR_animation_pointer have the pointer channels removed before the document is validated.
+    fn parse(data: &[u8]) -> gltf::Result<(gltf::Gltf, Vec<PointerChannel>)> {
+        // The extension has to be listed in 'extensionsUsed', so other files take the fast path
+        if !data
+            .windows(b"KHR_animation_pointer".len())
+            .any(|w| w == b"KHR_animation_pointer")
+        {
+            return Ok((gltf::Gltf::from_slice(data)?, Vec::new()));
+        }
+
+        let (json, blob) = if data.starts_with(b"glTF") {
+            let glb = gltf::binary::Glb::from_slice(data)?;
+            (glb.json, glb.bin.map(|bin| bin.into_owned()))
+        } else {
+            (data.into(), None)
+        };
+
+        let mut json: gltf::json::Value = gltf::json::deserialize::from_slice(&json)?;
+        let pointer_channels = material_animation::strip_pointer_channels(&mut json);
+
+        let root = gltf::json::deserialize::from_value(json)?;
+        let document = gltf::Document::from_json(root)?;
+
+        Ok((gltf::Gltf { document, blob }, pointer_channels))
+    }
+
+    /// The images of the created textures, to be uploaded later
+    fn into_pending_textures(self) -> Vec<PendingTexture> {
+        self.gl_textures
+            .into_iter()
+            .zip(self.images)
+            .filter(|(textures, _)| textures.linear.is_some() || textures.srgb.is_some())
+            .map(|(textures, image)| PendingTexture { textures, image })
+            .collect()
+    }
+
+    /// A magenta 1x1 image substituted for the images that fail to load
+    fn placeholder_image() -> gltf::image::Data {
+        gltf::image::Data {
+            pixels: vec![255, 0, 255, 255],
+            format: gltf::image::Format::R8G8B8A8,
+            width: 1,
+            height: 1,
+        }
+    }
+
+    /// Describes the resource an URI points to for error messages.
+    ///
+    /// Relative URIs are percent-decoded like the gltf crate does when it resolves them (e.g. 'my%20texture.png').
+    fn describe_uri(base: &Path, uri: &str) -> String {
+        if uri.starts_with("data:") {
+            "embedded data URI".to_string()
+        } else {
+            let path = urlencoding::decode(uri).unwrap_or_else(|_| uri.into());
+            format!("'{}'", base.join(&*path).display())
         }
     }
 }
 
 /// This represents a gltf model and contains necessary data for rendering.
 pub struct Model {
-    /// An artifical root node
+    /// An artifical root node, the parent of the root nodes of the gltf scene.
+    ///
+    /// Its transform is always the identity, the placement of the whole model is `transform`.
     pub root: Node,
     /// Name of the model
     pub name: String,
+    /// Path of the gltf file the model was loaded from
+    pub path: String,
     /// Animation data
     pub animations: Animations,
-    /// Model transforms of the whole object
+    /// Model transforms of the whole object, the outermost transform of the hierarchy.
+    ///
+    /// The world transform of a node is `transform * root.transform * ... * node.transform`
+    /// and the root joints of the skins are parented to the same chain, so setting it moves
+    /// all of the primitives (static and skinned). See `apply_parent_transform` for composing
+    /// the model into a larger scene.
     pub transform: Mat4,
+    /// If the edges of this model should be drawn (in addition to the global wireframe toggle)
+    pub wireframe: bool,
+    /// If the V texture coordinate should be inverted (for assets exported with the opposite convention)
+    pub flip_v: bool,
+    /// The normal mode last set for all primitives of the model
+    pub normal_mode: NormalMode,
+    /// The conversions applied when the model was loaded (reloading requires the same ones)
+    pub handedness: Handedness,
+    /// Name of the node whose subtree was loaded, None if the whole scene was loaded
+    pub root_node: Option<String>,
+    /// Information from the 'asset' block of the gltf file
+    pub asset: AssetInfo,
+    /// Cameras defined by the nodes of the gltf file
+    pub cameras: Vec<AssetCamera>,
+    /// Lights defined by the nodes of the gltf file (KHR_lights_punctual)
+    pub lights: Vec<AssetLight>,
+    /// Number of the vertices of all primitives
+    pub vertex_count: usize,
+    /// Number of the triangles of all primitives
+    pub triangle_count: usize,
+    /// Optional constraint rotating a joint towards a target (head / eye tracking)
+    pub look_at: Option<LookAt>,
+    /// Textures whose images are uploaded when the model is rendered for the first time.
+    ///
+    /// The renderer only uploads the textures of the selected model, the texture names of a model
+    /// that is never selected stay allocated without any image data.
+    pending_textures: Vec<PendingTexture>,
+}
+
+/// The metadata of the gltf asset
+pub struct AssetInfo {
+    /// The tool that exported the file
+    pub generator: Option<String>,
+    /// The glTF version of the asset
+    pub version: String,
+    /// The minimum glTF version the asset targets
+    pub min_version: Option<String>,
+    pub copyright: Option<String>,
+    /// Application specific data (raw JSON)
+    pub extras: Option<String>,
+}
+
+impl AssetInfo {
+    /// Reads the 'asset' block of the gltf document
+    fn from_gltf(gltf: &gltf::Document) -> Self {
+        let asset = &gltf.as_json().asset;
+
+        Self {
+            generator: asset.generator.clone(),
+            version: asset.version.clone(),
+            min_version: asset.min_version.clone(),
+            copyright: asset.copyright.clone(),
+            extras: asset.extras.as_ref().map(|extras| extras.get().to_string()),
+        }
+    }
 }
 
 impl Model {
     /// Load the model from a path to a gltf file
-    pub fn from_gltf(path: &str) -> Result<Model> {
-        let (gltf, buffers, images) = gltf::import(path)?;
-        let name = Path::new(path)
+    pub fn from_gltf(path: &str) -> Result<Model, LoadError> {
+        Self::from_gltf_with_options(path, ImportOptions::default())
+    }
+
+    /// Load only the node with the name and its descendants (in depth-first order, the first node with the name).
+    ///
+    /// The transforms of the ancestors of the node are dropped, the node is placed at the origin of the model.
+    /// Only the animation channels of the subtree (and of the joints of its skins) are kept,
+    /// animations without any of them are skipped. The meshes and textures outside of the subtree
+    /// aren't created, but the whole file is still read.
+    pub fn from_gltf_node(path: &str, node_name: &str) -> Result<Model, LoadError> {
+        let options = ImportOptions {
+            root_node: Some(node_name.to_string()),
+            ..ImportOptions::default()
+        };
+        Self::from_gltf_with_options(path, options)
+    }
+
+    /// Load the model from a path to a gltf file with specific import options
+    pub fn from_gltf_with_options(path: &str, options: ImportOptions) -> Result<Model, LoadError> {
+        Self::from_gltf_with_progress(path, options, &mut |_, _| {})
+    }
+
+    /// Load the model from a path to a gltf file, the progress of the loading is reported to the callback.
+    ///
+    /// Every stage is reported at least at its start (0) and its end (1), in the order of `LoadStage`.
+    /// The textures are uploaded to the GPU later, when the model is rendered for the first time
+    /// (or by calling `upload_textures`).
+    pub fn from_gltf_with_progress(
+        path: &str,
+        options: ImportOptions,
+        progress: LoadProgress,
+    ) -> Result<Model, LoadError> {
+        let path = Path::new(path);
+        let (gltf, mut bundle) = DataBundle::import(path, options, progress)?;
+        let file_name = path
             .file_name()
             .map(|osstr| osstr.to_string_lossy().to_string())
             .unwrap_or_else(|| "N/A".to_string());
-
-        let mut bundle = DataBundle::new(buffers, images);
+        let name = match &bundle.options.root_node {
+            Some(node_name) => format!("{file_name} ({node_name})"),
+            None => file_name,
+        };
 
         if gltf.scenes().len() != 1 {
-            return Err(eyre!("GLTF file contains more than 1 scene"));
+            return Err(LoadError::MultipleScenes {
+                count: gltf.scenes().len(),
+            });
         }
         let scene = gltf.scenes().next().unwrap();
 
-        let mut id = 1;
+        let root_nodes: Vec<gltf::Node> = match &bundle.options.root_node {
+            Some(node_name) => {
+                let node = scene
+                    .nodes()
+                    .find_map(|node| Self::find_gltf_node(node, node_name))
+                    .ok_or_else(|| LoadError::MissingNode {
+                        name: node_name.clone(),
+                    })?;
+
+                let mut loaded_nodes = HashSet::new();
+                Self::collect_subtree_nodes(&node, &mut loaded_nodes);
+                bundle.loaded_nodes = Some(loaded_nodes);
+
+                vec![node]
+            }
+            None => scene.nodes().collect(),
+        };
+
+        let joint_nodes: HashSet<usize> = gltf
+            .skins()
+            .flat_map(|skin| skin.joints().map(|joint| joint.index()))
+            .collect();
+
+        bundle.mesh_count = root_nodes.iter().map(Self::count_meshes).sum();
+        bundle.report(LoadStage::Meshes, 0.);
+
         let mut nodes = Vec::new();
-        for node in scene.nodes() {
-            let node = Node::from_gltf(&node, &mut bundle, &mut id, &scene)?;
-            id += 1;
+        for node in &root_nodes {
+            let node = Node::from_gltf(node, &mut bundle, &scene, &joint_nodes)
+                .map_err(LoadError::invalid_data)?;
             nodes.push(node);
         }
+        bundle.report(LoadStage::Meshes, 1.);
 
-        let animations = Animation::from_gltf(&gltf, &bundle)?;
+        bundle.report(LoadStage::Animations, 0.);
+        let animations = Animation::from_gltf(&gltf, &bundle).map_err(LoadError::invalid_data)?;
+        bundle.report(LoadStage::Animations, 1.);
 
         let root = Node {
             index: usize::MAX,
@@ -80,31 +574,589 @@ impl Model {
             children: nodes,
             mesh: None,
             transform: Mat4::IDENTITY,
+            rest_transform: Transform::default(),
+            is_joint: false,
             joints: None,
         };
 
-        Ok(Model {
+        let cameras = AssetCamera::collect(&gltf, &root, bundle.options.handedness.mirror);
+        let lights = AssetLight::collect(&gltf, &root, bundle.options.handedness.mirror);
+        let model_normalize_scale = bundle.options.normalize_scale;
+
+        let primitives = || {
+            root.iter()
+                .filter_map(|(node, _)| node.mesh.as_ref())
+                .flat_map(|mesh| &mesh.primitives)
+        };
+        let vertex_count = primitives().map(|p| p.vertex_count).sum();
+        let triangle_count = primitives().map(|p| p.triangle_count).sum();
+
+        let mut model = Model {
             root,
             name,
+            path: path.to_string_lossy().to_string(),
             animations,
-            transform: Mat4::IDENTITY,
-        })
+            transform: bundle.options.handedness.up_axis.correction(),
+            wireframe: false,
+            flip_v: false,
+            normal_mode: NormalMode::File,
+            handedness: bundle.options.handedness,
+            root_node: bundle.options.root_node.clone(),
+            asset: AssetInfo::from_gltf(&gltf),
+            cameras,
+            lights,
+            vertex_count,
+            triangle_count,
+            look_at: None,
+            pending_textures: bundle.into_pending_textures(),
+        };
+
+        if let Some(normalize_scale) = model_normalize_scale {
+            model.normalize_scale(normalize_scale);
+        }
+
+        Ok(model)
+    }
+
+    /// Recursive - finds the node with the name in the subtree of the node (in depth-first order)
+    fn find_gltf_node<'g>(node: gltf::Node<'g>, name: &str) -> Option<gltf::Node<'g>> {
+        if node.name() == Some(name) {
+            return Some(node);
+        }
+
+        node.children()
+            .find_map(|child| Self::find_gltf_node(child, name))
+    }
+
+    /// Recursive - adds the indices of the node, its descendants and the joints of their skins
+    fn collect_subtree_nodes(node: &gltf::Node, nodes: &mut HashSet<usize>) {
+        nodes.insert(node.index());
+
+        if let Some(skin) = node.skin() {
+            nodes.extend(skin.joints().map(|joint| joint.index()));
+        }
+
+        for child in node.children() {
+            Self::collect_subtree_nodes(&child, nodes);
+        }
+    }
+
+    /// Recursive - the number of meshes instantiated by the node and its descendants
+    fn count_meshes(node: &gltf::Node) -> usize {
+        let children: usize = node
+            .children()
+            .map(|child| Self::count_meshes(&child))
+            .sum();
+        children + node.mesh().is_some() as usize
+    }
+
+    /// Loads the animations of another gltf file and appends them to the animations of this model.
+    ///
+    /// The animated nodes are matched to the nodes of this model by their names,
+    /// falling back to their indices (e.g. for unnamed nodes). Returns the number of added animations.
+    pub fn add_animations_from_gltf(&mut self, path: &str) -> Result<usize> {
+        let (gltf, mut animations) = Self::load_foreign_animations(path, self.handedness)?;
+
+        let animated_nodes: HashSet<usize> = animations
+            .iter()
+            .flat_map(|animation| animation.channels.iter().map(|channel| channel.node))
+            .collect();
+
+        let mut node_map = HashMap::new();
+        let mut unmatched = Vec::new();
+
+        for node in gltf.nodes().filter(|n| animated_nodes.contains(&n.index())) {
+            let matched = node
+                .name()
+                .and_then(|name| self.find_by_name(name))
+                .or_else(|| self.find_by_index(node.index()));
+
+            match matched {
+                Some(matched) => {
+                    node_map.insert(node.index(), matched.index);
+                }
+                None => unmatched.push(match node.name() {
+                    Some(name) => format!("'{name}'"),
+                    None => format!("node {}", node.index()),
+                }),
+            }
+        }
+
+        if !unmatched.is_empty() {
+            return Err(eyre!(
+                "The skeleton of '{path}' doesn't match '{}', these nodes weren't found: {}",
+                self.name,
+                unmatched.join(", ")
+            ));
+        }
+
+        for animation in &mut animations {
+            for channel in &mut animation.channels {
+                channel.node = node_map[&channel.node];
+            }
+        }
+
+        let count = animations.len();
+        self.animations.animations.extend(animations);
+
+        Ok(count)
+    }
+
+    /// Loads the animations of a model with a different skeleton and retargets them onto this model.
+    ///
+    /// Joints are matched by their names, 'name_overrides' maps the source joint names to the names
+    /// of the joints of this model for bones that are named differently. Channels of unmatched joints
+    /// are skipped. See `retarget_channel` for the limitations. Returns the number of added animations.
+    pub fn retarget_animations_from_gltf(
+        &mut self,
+        path: &str,
+        name_overrides: &HashMap<String, String>,
+    ) -> Result<usize> {
+        let (gltf, mut animations) = Self::load_foreign_animations(path, self.handedness)?;
+
+        // Source node index -> (target node index, source rest transform, target rest transform)
+        let mut node_map = HashMap::new();
+        for node in gltf.nodes() {
+            let source_name = match node.name() {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let target_name = name_overrides
+                .get(source_name)
+                .map(String::as_str)
+                .unwrap_or(source_name);
+
+            if let Some(target) = self.find_by_name(target_name) {
+                let source_rest = self
+                    .handedness
+                    .mirror
+                    .transform(Transform::from_gltf(&node));
+                node_map.insert(
+                    node.index(),
+                    (target.index, source_rest, target.rest_transform),
+                );
+            }
+        }
+
+        let mut unmatched = HashSet::new();
+        let mut retargeted_channels = 0;
+
+        for animation in &mut animations {
+            let channels = std::mem::take(&mut animation.channels);
+
+            for channel in &channels {
+                let (target_node, source_rest, target_rest) = match node_map.get(&channel.node) {
+                    Some(mapping) => mapping,
+                    None => {
+                        unmatched.insert(channel.node);
+                        continue;
+                    }
+                };
+
+                if let Some(channel) =
+                    retarget::retarget_channel(channel, *target_node, source_rest, target_rest)
+                {
+                    animation.channels.push(channel);
+                    retargeted_channels += 1;
+                }
+            }
+        }
+
+        if retargeted_channels == 0 {
+            return Err(eyre!(
+                "None of the animated joints of '{path}' match the joints of '{}'",
+                self.name
+            ));
+        }
+
+        if !unmatched.is_empty() {
+            let names: Vec<String> = gltf
+                .nodes()
+                .filter(|node| unmatched.contains(&node.index()))
+                .map(|node| match node.name() {
+                    Some(name) => format!("'{name}'"),
+                    None => format!("node {}", node.index()),
+                })
+                .collect();
+
+            eprintln!(
+                "WARN: skipped the channels of unmatched joints while retargeting '{path}': {}",
+                names.join(", ")
+            );
+        }
+
+        let count = animations.len();
+        self.animations.animations.extend(animations);
+
+        Ok(count)
+    }
+
+    /// Loads the animations of another gltf file.
+    ///
+    /// The material channels are removed, because the materials of the other file are unrelated to this model.
+    /// The animations are converted with the same handedness options as the model.
+    fn load_foreign_animations(
+        path: &str,
+        handedness: Handedness,
+    ) -> Result<(gltf::Document, Vec<Animation>)> {
+        let options = ImportOptions {
+            handedness,
+            ..ImportOptions::default()
+        };
+        let mut no_progress = |_, _| {};
+        let (gltf, bundle) = DataBundle::import(Path::new(path), options, &mut no_progress)?;
+        let mut animations = Animation::from_gltf(&gltf, &bundle)?.animations;
+
+        for animation in &mut animations {
+            if !animation.material_channels.is_empty() {
+                eprintln!(
+                    "WARN: ignoring the material channels of animation '{}' from '{path}'",
+                    animation.name.as_deref().unwrap_or("N/A")
+                );
+                animation.material_channels.clear();
+            }
+        }
+
+        Ok((gltf, animations))
+    }
+}
+
+impl Model {
+    /// Bounding box of the model in world space (in the bind pose)
+    pub fn bounds(&self) -> Aabb {
+        let mut aabb = Aabb::new_empty();
+        Self::node_bounds(&self.root, self.transform, &mut aabb);
+        aabb
+    }
+
+    /// Scales and moves the model so that it has the same height as the 'reference' bounds
+    /// and stands at the same spot.
+    ///
+    /// Used for models added at runtime, which can use arbitrary units.
+    pub fn auto_layout(&mut self, reference: &Aabb) {
+        let bounds = self.bounds();
+        if bounds.is_empty() || reference.is_empty() {
+            return;
+        }
+
+        let height = bounds.size().y.max(bounds.size().length() * 0.01);
+        let scale = reference.size().y.max(f32::EPSILON) / height;
+
+        let center = bounds.center();
+        let bottom = Vec3::new(center.x, bounds.min.y, center.z);
+        let target = Vec3::new(reference.center().x, reference.min.y, reference.center().z);
+
+        self.apply_parent_transform(
+            Mat4::from_translation(target)
+                * Mat4::from_scale(Vec3::splat(scale))
+                * Mat4::from_translation(-bottom),
+        );
+    }
+
+    /// Scales the model around the origin, so that its bounding box (in the bind pose) has the size.
+    ///
+    /// Models without any primitives (or flat in the height) aren't scaled.
+    pub fn normalize_scale(&mut self, normalize_scale: NormalizeScale) {
+        let size = self.bounds().size();
+        let (current, target) = match normalize_scale {
+            NormalizeScale::Size(target) => (size.max_element(), target),
+            NormalizeScale::Height(target) => (size.y, target),
+        };
+
+        // An empty bounding box has a negative size
+        if current <= f32::EPSILON || !current.is_finite() {
+            return;
+        }
+
+        self.apply_parent_transform(Mat4::from_scale(Vec3::splat(target / current)));
+    }
+
+    /// Changes the up axis of the loaded model, the correction is the innermost part of `transform`
+    pub fn set_up_axis(&mut self, up_axis: UpAxis) {
+        let old = self.handedness.up_axis.correction();
+        self.transform = self.transform * old.inverse() * up_axis.correction();
+        self.handedness.up_axis = up_axis;
+    }
+
+    /// Places the model under an external parent transform (the parent is applied after the current transform)
+    pub fn apply_parent_transform(&mut self, parent: Mat4) {
+        self.transform = parent * self.transform;
+    }
+
+    /// World transform of the node (in the rest pose, or in the pose of the last rendered frame
+    /// for nodes animated through the hierarchy)
+    pub fn node_world_transform(&self, index: usize) -> Option<Mat4> {
+        let (node, parent_transform) = Self::find_node(&self.root, index, self.transform)?;
+        Some(parent_transform * node.transform)
+    }
+
+    /// Duration of the active animation in seconds, see `Animations::duration`
+    pub fn animation_duration(&self) -> Option<f32> {
+        self.animations.duration()
+    }
+
+    /// Time of the playhead of the active animation in seconds, see `Animations::current_time`
+    pub fn animation_time(&self) -> Option<f32> {
+        self.animations.current_time()
+    }
+
+    /// Overrides the local transform of the joints with the name (in all skins of the model).
+    ///
+    /// The override is applied after sampling the animations, so the joint keeps the transform
+    /// until the override is cleared. Its children still follow their animations.
+    pub fn set_joint_local(&mut self, name: &str, transform: Transform) -> Result<()> {
+        let mut found = false;
+        self.visit_joints_mut(|joint| {
+            if joint.name == name {
+                joint.pose_override = Some(transform);
+                found = true;
+            }
+        });
+
+        match found {
+            true => Ok(()),
+            false => Err(eyre!("Model '{}' has no joint named '{name}'", self.name)),
+        }
+    }
+
+    /// Removes the override of the joints with the name, they return to the animated transform
+    pub fn clear_joint_local(&mut self, name: &str) {
+        self.visit_joints_mut(|joint| {
+            if joint.name == name {
+                joint.pose_override = None;
+            }
+        });
+    }
+
+    /// Removes the overrides of all joints
+    pub fn clear_joint_overrides(&mut self) {
+        self.visit_joints_mut(|joint| joint.pose_override = None);
+    }
+
+    /// Calls 'f' with every joint of every skin of the model
+    fn visit_joints_mut(&mut self, mut f: impl FnMut(&mut Joint)) {
+        self.visit_nodes_mut(|node, _| {
+            if let Some(joints) = &mut node.joints {
+                joints.joints.iter_mut().for_each(&mut f);
+            }
+        });
+    }
+
+    /// Switches the normals of all primitives (primitives without normals in the file use smooth normals)
+    pub fn set_normal_mode(&mut self, mode: NormalMode) {
+        self.normal_mode = mode;
+
+        self.visit_nodes_mut(|node, _| {
+            if let Some(mesh) = &mut node.mesh {
+                for primitive in &mut mesh.primitives {
+                    primitive.set_normal_mode(mode);
+                }
+            }
+        });
+    }
+
+    /// Iterates over all nodes of the model (excluding the artificial root) in depth-first order.
+    ///
+    /// The top-level nodes have a depth of 0.
+    pub fn nodes(&self) -> NodeIter<'_> {
+        NodeIter {
+            stack: self
+                .root
+                .children
+                .iter()
+                .rev()
+                .map(|node| (node, 0))
+                .collect(),
+        }
+    }
+
+    /// Calls 'f' with every node of the model (excluding the artificial root) and its depth
+    /// in depth-first order
+    pub fn visit_nodes_mut(&mut self, mut f: impl FnMut(&mut Node, usize)) {
+        for node in &mut self.root.children {
+            node.visit_mut(0, &mut f);
+        }
+    }
+
+    /// Finds the first node with the name (in depth-first order)
+    pub fn find_by_name(&self, name: &str) -> Option<&Node> {
+        self.root
+            .children
+            .iter()
+            .find_map(|node| node.find_by_name(name))
+    }
+
+    /// Finds the node by its index in the gltf file
+    pub fn find_by_index(&self, index: usize) -> Option<&Node> {
+        self.root.find_by_index(index)
+    }
+
+    /// Uploads the images of the textures that weren't uploaded yet.
+    ///
+    /// Called by the renderer for the selected model only, other models have to call it themselves
+    /// before their textures are sampled (e.g. when they are drawn by another renderer).
+    /// Binds the textures to the active texture unit.
+    pub fn upload_textures(&mut self) {
+        if self.pending_textures.is_empty() {
+            return;
+        }
+
+        let start = Instant::now();
+        let count = self.pending_textures.len();
+
+        for texture in self.pending_textures.drain(..) {
+            texture.upload();
+        }
+
+        let time = Instant::now().duration_since(start);
+        println!(
+            "Uploading {count} texture images of '{}' took '{time:?}'",
+            self.name
+        );
+    }
+
+    /// Starts looping the animation with the name from the beginning (of its trimmed part).
+    ///
+    /// Returns an error if the model doesn't have an animation with the name.
+    pub fn play_animation(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .animations
+            .animations
+            .iter()
+            .position(|a| a.name.as_deref() == Some(name))
+            .ok_or_else(|| eyre!("The model '{}' has no animation '{name}'", self.name))?;
+
+        self.animations.play(index);
+        Ok(())
+    }
+
+    /// Stops the active animation, the model returns to the rest pose
+    pub fn stop_animation(&mut self) {
+        self.animations.stop();
+    }
+
+    /// The rest transforms of all nodes (including the joints) by the node index
+    pub fn rest_transforms(&self) -> HashMap<usize, Transform> {
+        self.nodes()
+            .map(|(node, _)| (node.index, node.rest_transform))
+            .collect()
+    }
+
+    /// World space bounding box of the node and its descendants (in the bind pose).
+    ///
+    /// If the subtree doesn't contain any meshes, the box only contains the origin of the node.
+    pub fn node_world_bounds(&self, index: usize) -> Option<Aabb> {
+        let (node, parent_transform) = Self::find_node(&self.root, index, self.transform)?;
+
+        let mut aabb = Aabb::new_empty();
+        Self::node_bounds(node, parent_transform, &mut aabb);
+
+        if aabb.is_empty() {
+            let origin = (parent_transform * node.transform).transform_point3(Vec3::ZERO);
+            aabb.extend(origin);
+        }
+
+        Some(aabb)
+    }
+
+    /// Recursive - finds the node by its index, returns it with the world transform of its parent
+    fn find_node(node: &Node, index: usize, outer_transform: Mat4) -> Option<(&Node, Mat4)> {
+        if node.index == index {
+            return Some((node, outer_transform));
+        }
+
+        let transform = outer_transform * node.transform;
+        node.children
+            .iter()
+            .find_map(|child| Self::find_node(child, index, transform))
+    }
+
+    /// The nearest triangle hit by the ray in world space (in the bind pose).
+    ///
+    /// The models loaded without the CPU copies of the vertex data are never hit.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3) -> Option<Hit> {
+        let mut nearest = None;
+        Self::node_raycast(&self.root, self.transform, origin, dir, &mut nearest);
+        nearest
+    }
+
+    fn node_raycast(
+        node: &Node,
+        outer_transform: Mat4,
+        origin: Vec3,
+        dir: Vec3,
+        nearest: &mut Option<Hit>,
+    ) {
+        let transform = outer_transform * node.transform;
+
+        // The meshes of non-invertible nodes aren't drawn
+        if let Some(mesh) = node
+            .mesh
+            .as_ref()
+            .filter(|_| transform.determinant().abs() >= MIN_DETERMINANT)
+        {
+            // The distances along the transformed direction are the same as in world space
+            let inverse = transform.inverse();
+            let local_origin = inverse.transform_point3(origin);
+            let local_dir = inverse.transform_vector3(dir);
+
+            for (i, prim) in mesh.primitives.iter().enumerate() {
+                let max_distance = nearest.map_or(f32::INFINITY, |hit| hit.distance);
+
+                if let Some(hit) = prim.raycast(local_origin, local_dir, max_distance) {
+                    *nearest = Some(Hit {
+                        node: node.index,
+                        primitive: i,
+                        triangle: hit.triangle,
+                        barycentric: hit.barycentric,
+                        distance: hit.distance,
+                        position: origin + dir * hit.distance,
+                    });
+                }
+            }
+        }
+
+        for child in &node.children {
+            Self::node_raycast(child, transform, origin, dir, nearest);
+        }
+    }
+
+    /// Recursive - adds the bounding boxes of the node's primitives to the 'aabb'
+    fn node_bounds(node: &Node, outer_transform: Mat4, aabb: &mut Aabb) {
+        let transform = outer_transform * node.transform;
+
+        if let Some(mesh) = &node.mesh {
+            for prim in &mesh.primitives {
+                aabb.union(&prim.aabb.transformed(transform));
+            }
+        }
+
+        for child in &node.children {
+            Self::node_bounds(child, transform, aabb);
+        }
     }
 }
 
 /// A Node represents a subset of a gltf scene
 /// Nodes form a tree hierarchy
 pub struct Node {
-    /// The same index as in the gltf file
+    /// The same index as in the gltf file, identifies the node across the reloads of the file.
+    ///
+    /// The artificial root of the model has an index of `usize::MAX`.
     pub index: usize,
-    /// Name of the node
+    /// Name of the node, 'Node-{index}' if the gltf file doesn't name it
     pub name: String,
     /// Children nodes
     pub children: Vec<Node>,
     /// Optional mesh data of the node (can contain multiple primitives)
     pub mesh: Option<Mesh>,
-    /// Transform of the node on the hierarchy
+    /// Transform of the node on the hierarchy (updated by animations of the node)
     pub transform: Mat4,
+    /// The decomposed transform from the gltf file, animations override its components
+    pub rest_transform: Transform,
+    /// Joint nodes are animated through the skin, not through the node hierarchy
+    pub is_joint: bool,
     /// Optional skeleton data this node is root of
     pub joints: Option<Joints>,
 }
@@ -114,20 +1166,22 @@ impl Node {
     fn from_gltf(
         node: &gltf::Node,
         bundle: &mut DataBundle,
-        id: &mut u32,
         scene: &gltf::Scene,
+        joint_nodes: &HashSet<usize>,
     ) -> Result<Self> {
         let mut children = Vec::new();
 
-        let name = node.name().unwrap_or(&format!("Node-{id}")).to_string();
+        let name = node
+            .name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("Node-{}", node.index()));
 
         for child_node in node.children() {
-            *id += 1;
-            let node = Node::from_gltf(&child_node, bundle, id, scene)?;
+            let node = Node::from_gltf(&child_node, bundle, scene, joint_nodes)?;
             children.push(node);
         }
 
-        let mesh = match node.mesh() {
+        let mut mesh = match node.mesh() {
             Some(m) => Some(Mesh::from_gltf(&m, bundle)?),
             None => None,
         };
@@ -150,19 +1204,306 @@ impl Node {
             }
         };
 
+        let transform = bundle.options.handedness.mirror.mat4(transform);
+
+        let determinant = transform.determinant();
+        if determinant.abs() < MIN_DETERMINANT {
+            eprintln!(
+                "WARN: node '{name}' has a non-invertible (zero-scaled) transform, its meshes aren't drawn"
+            );
+        }
+
         let joints = if let Some(skin) = node.skin() {
             Some(Joints::from_gltf(bundle, &skin, scene)?)
         } else {
             None
         };
 
+        if let (Some(mesh), Some(joints)) = (&mut mesh, &joints) {
+            mesh.compute_joint_radii(&joints.joints);
+        }
+
+        if let Some(mesh) = &mut mesh {
+            if !bundle.options.keep_vertex_data {
+                mesh.release_vertex_data();
+            }
+        }
+
         Ok(Self {
             index: node.index(),
             children,
             mesh,
             transform,
+            rest_transform: bundle
+                .options
+                .handedness
+                .mirror
+                .transform(Transform::from_gltf(node)),
+            is_joint: joint_nodes.contains(&node.index()),
             name,
             joints,
         })
     }
 }
+
+impl Node {
+    /// Iterates over this node and all of its descendants in depth-first order.
+    ///
+    /// This node has a depth of 0.
+    pub fn iter(&self) -> NodeIter<'_> {
+        NodeIter {
+            stack: vec![(self, 0)],
+        }
+    }
+
+    /// Recursive - calls 'f' with this node and all of its descendants in depth-first order
+    pub fn visit_mut(&mut self, depth: usize, f: &mut impl FnMut(&mut Node, usize)) {
+        f(self, depth);
+
+        for child in &mut self.children {
+            child.visit_mut(depth + 1, f);
+        }
+    }
+
+    /// Finds the first node with the name in this subtree (in depth-first order)
+    pub fn find_by_name(&self, name: &str) -> Option<&Node> {
+        self.iter()
+            .map(|(node, _)| node)
+            .find(|node| node.name == name)
+    }
+
+    /// Finds the node with the gltf index in this subtree
+    pub fn find_by_index(&self, index: usize) -> Option<&Node> {
+        self.iter()
+            .map(|(node, _)| node)
+            .find(|node| node.index == index)
+    }
+}
+
+/// Depth-first iterator over a node hierarchy, yields the nodes with their depth
+pub struct NodeIter<'a> {
+    /// Nodes that haven't been visited yet, the next one is at the top
+    stack: Vec<(&'a Node, usize)>,
+}
+
+impl<'a> Iterator for NodeIter<'a> {
+    type Item = (&'a Node, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, depth) = self.stack.pop()?;
+
+        // Reversed so that the first child is visited first
+        self.stack
+            .extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+
+        Some((node, depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+
+    /// Copies the external resources asset into a new temporary directory, outside of the working directory
+    fn copy_external_resources(name: &str) -> PathBuf {
+        let source = Path::new("resources/external_resources");
+        let dir = env::temp_dir().join(format!("leoric-{name}-{}", std::process::id()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+
+        for file in [
+            "ExternalResources.gltf",
+            "ExternalResources.bin",
+            "images/checker.png",
+        ] {
+            fs::copy(source.join(file), dir.join(file)).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn empty_nodes_only() {
+        let model = Model::from_gltf("resources/empty_nodes/EmptyNodesOnly.gltf")
+            .expect("the empty nodes should be loaded");
+
+        let names: Vec<&str> = model.nodes().map(|(node, _)| node.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Locators", "LocatorA", "LocatorB", "EmptyMeshLocator"]
+        );
+
+        let empty_mesh = model.find_by_name("EmptyMeshLocator").unwrap();
+        assert!(empty_mesh.mesh.as_ref().unwrap().primitives.is_empty());
+        assert!(model.bounds().is_empty());
+
+        // The empty nodes still contribute their transforms to the hierarchy
+        let locator_b = model.node_world_bounds(2).unwrap();
+        assert_eq!(locator_b.center(), Vec3::new(2., 1., 0.));
+    }
+
+    #[test]
+    fn model_transform_is_outermost() {
+        let path = "resources/empty_nodes/EmptyNodesOnly.gltf";
+        let identity = Model::from_gltf(path).unwrap();
+        let mut translated = Model::from_gltf(path).unwrap();
+
+        let offset = Vec3::new(5., 0., -3.);
+        translated.transform = Mat4::from_translation(offset);
+        assert_eq!(translated.root.transform, Mat4::IDENTITY);
+
+        for (node, _) in identity.nodes() {
+            let rest = identity.node_world_transform(node.index).unwrap();
+            let moved = translated.node_world_transform(node.index).unwrap();
+            assert!(moved.abs_diff_eq(Mat4::from_translation(offset) * rest, 1e-6));
+        }
+
+        // The parent is applied after the current transform, a rotation turns the offset too
+        translated.apply_parent_transform(Mat4::from_rotation_y(std::f32::consts::PI));
+        let locator_a = translated.node_world_transform(1).unwrap();
+        assert!(locator_a
+            .transform_point3(Vec3::ZERO)
+            .abs_diff_eq(Vec3::new(-3., 1., 3.), 1e-5));
+    }
+
+    #[test]
+    fn resources_relative_to_the_file() {
+        let dir = copy_external_resources("relative");
+        assert_ne!(env::current_dir().unwrap(), dir);
+
+        let path = dir.join("ExternalResources.gltf");
+        let mut progress = |_, _| {};
+        let result = DataBundle::import(&path, ImportOptions::default(), &mut progress);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (gltf, bundle) = result.expect("the resources should be found next to the file");
+        assert_eq!(bundle.buffers[0].len(), 32);
+        // A missing image would be replaced by a 1x1 placeholder
+        assert_eq!((bundle.images[0].width, bundle.images[0].height), (4, 2));
+
+        let animations = Animation::from_gltf(&gltf, &bundle).unwrap();
+        assert_eq!(animations.animations[0].end_time, 1.);
+    }
+
+    #[test]
+    fn missing_buffer_is_named() {
+        let dir = copy_external_resources("missing");
+        fs::remove_file(dir.join("ExternalResources.bin")).unwrap();
+
+        // The index and the resource of the reported missing buffer
+        let missing_buffer = |path: &Path| {
+            let mut progress = |_, _| {};
+            match DataBundle::import(path, ImportOptions::default(), &mut progress) {
+                Err(LoadError::MissingBuffer {
+                    index, resource, ..
+                }) => Some((index, resource)),
+                _ => None,
+            }
+        };
+
+        let path = dir.join("ExternalResources.gltf");
+        let missing = missing_buffer(&path);
+
+        // The percent-encoded URI is reported as the file the resolver looked for
+        let encoded = fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"ExternalResources.bin\"", "\"my%20buffer.bin\"");
+        fs::write(&path, encoded).unwrap();
+        let missing_encoded = missing_buffer(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        for (missing, file) in [
+            (missing, "ExternalResources.bin"),
+            (missing_encoded, "my buffer.bin"),
+        ] {
+            let (index, resource) = missing.expect("the missing buffer should be reported");
+            assert_eq!(index, 0);
+            assert!(resource.ends_with(&format!("{file}'")), "{resource}");
+        }
+    }
+
+    #[test]
+    fn play_unknown_animation() {
+        let mut model = Model::from_gltf("resources/empty_nodes/EmptyNodesOnly.gltf").unwrap();
+
+        assert!(model.play_animation("Walk").is_err());
+        assert!(matches!(
+            model.animations.animation_control,
+            AnimationControl::Static
+        ));
+    }
+
+    #[test]
+    fn retarget_onto_a_mirrored_import() {
+        let path = "resources/waving_arm/WavingArm.gltf";
+        let options = ImportOptions {
+            handedness: Handedness {
+                mirror: MirrorAxis::X,
+                ..Handedness::default()
+            },
+            ..ImportOptions::default()
+        };
+        let mut model = Model::from_gltf_with_options(path, options).unwrap();
+
+        // Retargeting the clip onto the same skeleton has to reproduce the clip loaded with the model
+        let count = model
+            .retarget_animations_from_gltf(path, &HashMap::new())
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let (loaded, retargeted) = (
+            &model.animations.animations[0],
+            &model.animations.animations[1],
+        );
+        for time in [0., 0.5, 1.] {
+            let loaded = loaded.sample(time);
+            let retargeted = retargeted.sample(time);
+            assert_eq!(loaded.len(), retargeted.len());
+
+            for (loaded, retargeted) in loaded.iter().zip(&retargeted) {
+                assert_eq!(loaded.node, retargeted.node);
+
+                match (&loaded.transform, &retargeted.transform) {
+                    (AnimationTransform::Rotation(a), AnimationTransform::Rotation(b)) => {
+                        assert!(a.dot(*b).abs() > 1. - 1e-5, "{a} {b}");
+                    }
+                    (AnimationTransform::Translation(a), AnimationTransform::Translation(b)) => {
+                        assert!(a.abs_diff_eq(*b, 1e-5), "{a} {b}");
+                    }
+                    _ => panic!("the channels should be of the same kind"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unnamed_nodes_are_stable() {
+        let path = "resources/unnamed_nodes/UnnamedNodes.gltf";
+        let first = Model::from_gltf(path).unwrap();
+        let second = Model::from_gltf(path).unwrap();
+
+        let ids = |model: &Model| -> Vec<(usize, String)> {
+            model
+                .nodes()
+                .map(|(node, _)| (node.index, node.name.clone()))
+                .collect()
+        };
+
+        assert_eq!(
+            ids(&first),
+            [
+                (3, "Node-3".to_string()),
+                (2, "Named".to_string()),
+                (1, "Node-1".to_string()),
+                (0, "Node-0".to_string()),
+            ]
+        );
+        assert_eq!(ids(&first), ids(&second));
+
+        for (node, _) in first.nodes() {
+            let found = second.find_by_index(node.index).unwrap();
+            assert_eq!(found.name, node.name);
+        }
+    }
+}
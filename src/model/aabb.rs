@@ -0,0 +1,87 @@
+use glam::{Mat4, Vec3};
+
+/// An axis-aligned bounding box
+#[derive(Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Creates an empty bounding box (min is larger than max)
+    pub fn new_empty() -> Self {
+        Self {
+            min: Vec3::splat(f32::INFINITY),
+            max: Vec3::splat(f32::NEG_INFINITY),
+        }
+    }
+
+    /// Creates the smallest bounding box containing all of the points
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let mut aabb = Self::new_empty();
+        for point in points {
+            aabb.extend(*point);
+        }
+
+        aabb
+    }
+
+    /// An empty bounding box doesn't contain any points
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// Enlarges the bounding box so that it contains the point
+    pub fn extend(&mut self, point: Vec3) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    /// Enlarges the bounding box so that it contains the other bounding box
+    pub fn union(&mut self, other: &Aabb) {
+        if !other.is_empty() {
+            self.extend(other.min);
+            self.extend(other.max);
+        }
+    }
+
+    /// The point in the middle of the bounding box
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Extents of the bounding box along each axis
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// The 8 corners of the bounding box
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// The bounding box containing this bounding box after it's transformed by the matrix
+    pub fn transformed(&self, transform: Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let mut aabb = Aabb::new_empty();
+        for corner in self.corners() {
+            aabb.extend(transform.transform_point3(corner));
+        }
+
+        aabb
+    }
+}
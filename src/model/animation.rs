@@ -1,18 +1,284 @@
-use std::time::Instant;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
 use eyre::{eyre, Result};
-use glam::{Quat, Vec3};
+use glam::{Quat, Vec3, Vec4};
 use gltf::animation::{
     util::{ReadOutputs, Rotations},
     Interpolation,
 };
 
-use super::DataBundle;
+use super::{BlendTree1D, DataBundle, MaterialChannel, Validation};
 
-/// Contains animation data and also the current state of the animation
+/// Contains animation data and also the current state of the animation.
+///
+/// Every Model owns its Animations, so loading the same file twice creates
+/// two instances that are animated independently.
 pub struct Animations {
     pub animations: Vec<Animation>,
     pub animation_control: AnimationControl,
+    /// Blend from the previously active animation after switching clips
+    pub crossfade: Option<Crossfade>,
+    /// Blending of multiple clips driven by a parameter, overrides the active animation when enabled
+    pub blend_tree: BlendTree1D,
+    /// Events of the markers crossed by the playhead, oldest first.
+    ///
+    /// Consumers should drain the queue, only the last MAX_QUEUED_EVENTS events are kept.
+    pub events: VecDeque<AnimationEvent>,
+    /// The active animation and its time when the markers were last checked
+    last_playhead: Option<(usize, f32)>,
+}
+
+/// Maximum number of events waiting in the queue of the Animations
+const MAX_QUEUED_EVENTS: usize = 64;
+
+impl Animations {
+    /// Index of the animation that is either playing or controlled manually
+    pub fn active_animation(&self) -> Option<usize> {
+        match self.animation_control {
+            AnimationControl::Loop {
+                active_animation, ..
+            }
+            | AnimationControl::Controllable { active_animation } => Some(active_animation),
+            AnimationControl::Static => None,
+        }
+    }
+
+    /// Duration of the played part of the active animation (between the trim markers) in seconds
+    pub fn duration(&self) -> Option<f32> {
+        let animation = &self.animations[self.active_animation()?];
+        Some(animation.trim_end - animation.trim_start)
+    }
+
+    /// Time of the playhead of the active animation in seconds (from the start of the clip, not of the trimmed part).
+    ///
+    /// The time of a looping animation is computed at the moment of the call and wraps around,
+    /// so it can be slightly ahead of the time of the last rendered frame.
+    pub fn current_time(&self) -> Option<f32> {
+        match self.animation_control {
+            AnimationControl::Loop {
+                active_animation,
+                start_time,
+            } => {
+                let since_start = Instant::now().duration_since(start_time).as_secs_f32();
+                Some(self.animations[active_animation].loop_time(since_start))
+            }
+            AnimationControl::Controllable { active_animation } => {
+                Some(self.animations[active_animation].current_time)
+            }
+            AnimationControl::Static => None,
+        }
+    }
+
+    /// If the active animation is playing (looping)
+    pub fn is_playing(&self) -> bool {
+        matches!(self.animation_control, AnimationControl::Loop { .. })
+    }
+
+    /// Starts looping the animation from the beginning (of its trimmed part)
+    pub fn play(&mut self, index: usize) {
+        self.switch_animation(index, None);
+        self.animations[index].current_time = self.animations[index].trim_start;
+        self.animation_control = AnimationControl::Loop {
+            active_animation: index,
+            start_time: Instant::now(),
+        };
+    }
+
+    /// Stops the active animation at its current time, it can be scrubbed or resumed
+    pub fn pause(&mut self) {
+        if let Some(index) = self.active_animation() {
+            if let Some(time) = self.current_time() {
+                self.animations[index].current_time = time;
+            }
+            self.animation_control = AnimationControl::Controllable {
+                active_animation: index,
+            };
+        }
+    }
+
+    /// Continues looping the paused animation from its current time
+    pub fn resume(&mut self) {
+        if let AnimationControl::Controllable { active_animation } = self.animation_control {
+            let animation = &self.animations[active_animation];
+            let since_start = (animation.current_time - animation.trim_start).max(0.);
+
+            self.animation_control = AnimationControl::Loop {
+                active_animation,
+                start_time: Instant::now() - Duration::from_secs_f32(since_start),
+            };
+        }
+    }
+
+    /// Moves the playhead of the active animation by `seconds` (negative steps go back) and pauses it.
+    ///
+    /// Used for stepping through the animation frame by frame.
+    pub fn step(&mut self, seconds: f32) {
+        self.pause();
+
+        if let Some(index) = self.active_animation() {
+            self.animations[index].step(seconds);
+        }
+    }
+
+    /// Advances the looping animation by exactly `seconds`, independently of the real time.
+    ///
+    /// Used for the fixed timestep playback, the renderer then doesn't compute the time from the clock.
+    /// The clock of the loop is kept in sync, so the real time playback continues from the same time.
+    pub fn advance_fixed(&mut self, seconds: f32) {
+        if let AnimationControl::Loop {
+            active_animation,
+            start_time,
+        } = &mut self.animation_control
+        {
+            let animation = &mut self.animations[*active_animation];
+            let since_start = animation.current_time - animation.trim_start + seconds;
+            animation.current_time = animation.loop_time(since_start.max(0.));
+
+            let since_start = (animation.current_time - animation.trim_start).max(0.);
+            *start_time = Instant::now() - Duration::from_secs_f32(since_start);
+        }
+    }
+
+    /// Stops the animations, the model returns to the rest pose
+    pub fn stop(&mut self) {
+        self.animation_control = AnimationControl::Static;
+        self.crossfade = None;
+    }
+
+    /// Pauses the playing animations for the duration (used when the time shouldn't be counted as playback time)
+    pub fn delay(&mut self, duration: Duration) {
+        if let AnimationControl::Loop { start_time, .. } = &mut self.animation_control {
+            *start_time += duration;
+        }
+
+        if let Some(crossfade) = &mut self.crossfade {
+            crossfade.start_time += duration;
+        }
+
+        self.blend_tree.delay(duration);
+    }
+
+    /// Makes a different animation active and resets it to the beginning.
+    ///
+    /// A looping animation keeps looping, otherwise the new animation becomes controllable.
+    /// If `crossfade_duration` is set, the pose is blended from the previous animation over that many seconds.
+    pub fn switch_animation(&mut self, index: usize, crossfade_duration: Option<f32>) {
+        let previous = self.active_animation();
+        if previous == Some(index) {
+            return;
+        }
+
+        self.crossfade = match (previous, crossfade_duration) {
+            (Some(from_animation), Some(duration)) if duration > 0. => Some(Crossfade {
+                from_animation,
+                from_time: self.animations[from_animation].current_time,
+                start_time: Instant::now(),
+                duration,
+            }),
+            _ => None,
+        };
+
+        let animation = &mut self.animations[index];
+        animation.current_time = animation.trim_start;
+
+        self.animation_control = match self.animation_control {
+            AnimationControl::Loop { .. } => AnimationControl::Loop {
+                active_animation: index,
+                start_time: Instant::now(),
+            },
+            _ => AnimationControl::Controllable {
+                active_animation: index,
+            },
+        };
+    }
+
+    /// Queues the events of the markers that the playhead crossed since the last call.
+    ///
+    /// A looping animation only moves forward, so a decreasing time means that it wrapped around.
+    /// Otherwise a decreasing time means that the playhead was moved backwards.
+    /// Switching animations doesn't emit events.
+    pub fn emit_marker_events(&mut self) {
+        let (index, looping) = match self.animation_control {
+            AnimationControl::Loop {
+                active_animation, ..
+            } => (active_animation, true),
+            AnimationControl::Controllable { active_animation } => (active_animation, false),
+            AnimationControl::Static => {
+                self.last_playhead = None;
+                return;
+            }
+        };
+
+        let animation = &self.animations[index];
+        let time = animation.current_time;
+
+        let from = match self.last_playhead.replace((index, time)) {
+            Some((last_index, last_time)) if last_index == index => last_time,
+            _ => return,
+        };
+
+        let crossed = |marker: &AnimationMarker| {
+            let t = marker.time;
+            if time >= from {
+                from < t && t <= time
+            } else if looping {
+                (from < t && t <= animation.trim_end) || (animation.trim_start <= t && t <= time)
+            } else {
+                time <= t && t < from
+            }
+        };
+
+        for marker in animation.markers.iter().filter(|m| crossed(m)) {
+            println!(
+                "Animation event '{}' at {:.2} s ({})",
+                marker.label,
+                marker.time,
+                animation.name.as_deref().unwrap_or("N/A")
+            );
+
+            if self.events.len() == MAX_QUEUED_EVENTS {
+                self.events.pop_front();
+            }
+
+            self.events.push_back(AnimationEvent {
+                animation: index,
+                time: marker.time,
+                label: marker.label.clone(),
+            });
+        }
+    }
+}
+
+/// A labeled point in time of an animation (for example a footstep)
+#[derive(Clone)]
+pub struct AnimationMarker {
+    pub time: f32,
+    pub label: String,
+}
+
+/// Emitted when the playhead crosses a marker
+#[derive(Clone)]
+pub struct AnimationEvent {
+    /// Index of the animation containing the marker
+    pub animation: usize,
+    /// Time of the marker
+    pub time: f32,
+    pub label: String,
+}
+
+/// A blend from the pose of the previously active animation to the pose of the current one
+pub struct Crossfade {
+    /// Index of the previously active animation
+    pub from_animation: usize,
+    /// Time of the previous animation when the crossfade started
+    pub from_time: f32,
+    /// When the crossfade started
+    pub start_time: Instant,
+    /// Duration of the crossfade in seconds
+    pub duration: f32,
 }
 
 /// The type of current animation
@@ -30,29 +296,140 @@ pub enum AnimationControl {
 /// Contains all animation data
 pub struct Animation {
     pub channels: Vec<Channel>,
+    /// Channels animating material properties (KHR_animation_pointer)
+    pub material_channels: Vec<MaterialChannel>,
     /// Current time of the animation
     pub current_time: f32,
     /// The time in seconds of the last keyframe, start time is implicitly 0
     pub end_time: f32,
+    /// Start of the part of the animation that is played (in seconds)
+    pub trim_start: f32,
+    /// End of the part of the animation that is played (in seconds)
+    pub trim_end: f32,
     /// Optional name of the animation
     pub name: Option<String>,
+    /// Sorted union of the keyframe times of all channels
+    pub keyframe_times: Vec<f32>,
+    /// Markers that emit events when the playhead crosses them
+    pub markers: Vec<AnimationMarker>,
 }
 
 impl Animation {
+    /// Interpolates the transforms of all channels at a specific time.
+    ///
+    /// Doesn't change the playback state (the current time) of the animation.
+    /// The pose is returned as one transform per channel instead of `AnimationTransforms`,
+    /// which holds the keyframes of a single channel and can't mix the translations,
+    /// rotations and scales of different nodes.
+    pub fn sample(&self, time: f32) -> Vec<NodeAnimationTransform> {
+        let mut transforms = Vec::new();
+        self.sample_into(time, None, &mut transforms);
+        transforms
+    }
+
+    /// Interpolates the transforms of all channels at a specific time and appends them to 'transforms'.
+    ///
+    /// `interpolation` overrides the interpolation of all channels, None uses the authored ones.
+    pub fn sample_into(
+        &self,
+        time: f32,
+        interpolation: Option<Interpolation>,
+        transforms: &mut Vec<NodeAnimationTransform>,
+    ) {
+        for channel in &self.channels {
+            let interpolation = interpolation.unwrap_or(channel.interpolation_type);
+            if let Some(transform) = channel.sample_with(time, interpolation) {
+                transforms.push(NodeAnimationTransform::new(channel.node, transform));
+            }
+        }
+    }
+
     pub fn new(
         channels: Vec<Channel>,
+        material_channels: Vec<MaterialChannel>,
         current_time: f32,
         end_time: f32,
         name: Option<String>,
     ) -> Self {
+        let mut keyframe_times: Vec<f32> = channels
+            .iter()
+            .flat_map(|c| c.keyframe_times.iter().copied())
+            .chain(
+                material_channels
+                    .iter()
+                    .flat_map(|c| c.keyframe_times.iter().copied()),
+            )
+            .collect();
+        keyframe_times.sort_by(f32::total_cmp);
+        keyframe_times.dedup_by(|a, b| (*a - *b).abs() < 1e-5);
+
         Self {
             channels,
+            material_channels,
             current_time,
             end_time,
+            trim_start: 0.,
+            trim_end: end_time,
             name,
+            keyframe_times,
+            markers: Vec::new(),
         }
     }
 
+    /// The keyframe time closest to 'time'
+    pub fn nearest_keyframe(&self, time: f32) -> Option<f32> {
+        self.keyframe_times
+            .iter()
+            .copied()
+            .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+    }
+
+    /// The first keyframe time after 'time'
+    pub fn next_keyframe(&self, time: f32) -> Option<f32> {
+        self.keyframe_times
+            .iter()
+            .copied()
+            .find(|t| *t > time + 1e-5)
+    }
+
+    /// The last keyframe time before 'time'
+    pub fn previous_keyframe(&self, time: f32) -> Option<f32> {
+        self.keyframe_times
+            .iter()
+            .copied()
+            .rev()
+            .find(|t| *t < time - 1e-5)
+    }
+
+    /// Sets the trim markers, they are clamped to the duration of the animation
+    pub fn set_trim(&mut self, trim_start: f32, trim_end: f32) {
+        self.trim_end = trim_end.clamp(0., self.end_time);
+        self.trim_start = trim_start.clamp(0., self.trim_end);
+        self.current_time = self.current_time.clamp(self.trim_start, self.trim_end);
+    }
+
+    /// Moves the current time by `seconds`, wrapping around the trimmed part of the animation
+    pub fn step(&mut self, seconds: f32) {
+        let duration = self.trim_end - self.trim_start;
+        self.current_time = match duration > 0. {
+            true => {
+                self.trim_start
+                    + (self.current_time - self.trim_start + seconds).rem_euclid(duration)
+            }
+            false => self.trim_start,
+        };
+    }
+
+    /// Time inside of the (trimmed) looping animation 'since_start' seconds after it started
+    pub fn loop_time(&self, since_start: f32) -> f32 {
+        let duration = self.trim_end - self.trim_start;
+        if duration <= 0. {
+            return self.trim_start;
+        }
+
+        self.trim_start + since_start % duration
+    }
+
     /// Creates the animation from a gltf::Document struct and the DataBundle
     pub fn from_gltf(gltf: &gltf::Document, bundle: &DataBundle) -> Result<Animations> {
         let mut animations = Vec::new();
@@ -63,13 +440,20 @@ impl Animation {
             for channel in animation.channels() {
                 let node_index = channel.target().node().index();
 
+                // Only a subtree of the scene is loaded
+                if let Some(loaded_nodes) = &bundle.loaded_nodes {
+                    if !loaded_nodes.contains(&node_index) {
+                        continue;
+                    }
+                }
+
                 let reader = channel.reader(|buf| Some(&bundle.buffers[buf.index()]));
                 let keyframe_times: Vec<f32> = reader
                     .read_inputs()
                     .ok_or(eyre!("Animation channel doesn't contain keyframe times"))?
                     .collect();
 
-                let transforms = match reader
+                let mut transforms = match reader
                     .read_outputs()
                     .ok_or(eyre!("Animation channel doesn't contain transforms"))?
                 {
@@ -82,24 +466,87 @@ impl Animation {
                         AnimationTransforms::Scales(data)
                     }
                     ReadOutputs::Rotations(rotations) => Self::decode_rotations(rotations),
-                    ReadOutputs::MorphTargetWeights(_) => todo!(),
+                    ReadOutputs::MorphTargetWeights(_) => {
+                        eprintln!(
+                            "WARN: skipping channel {} (node {node_index}) of animation '{}': morph target weights aren't supported",
+                            channel.index(),
+                            animation.name().unwrap_or("N/A")
+                        );
+                        continue;
+                    }
                 };
 
+                bundle
+                    .options
+                    .handedness
+                    .mirror
+                    .animation_transforms(&mut transforms);
+
+                if bundle.options.validation != Validation::Off {
+                    let times_finite = keyframe_times.iter().all(|t| t.is_finite());
+
+                    if !times_finite || !transforms.is_finite() {
+                        eprintln!(
+                            "WARN: channel {} (node {node_index}) of animation '{}' contains non-finite values",
+                            channel.index(),
+                            animation.name().unwrap_or("N/A")
+                        );
+
+                        if bundle.options.validation == Validation::Skip {
+                            continue;
+                        }
+                    }
+                }
+
                 let interpolation_type = channel.sampler().interpolation();
 
-                let channel =
+                // The outputs of cubic splines are triplets of the in-tangent, the value and the out-tangent
+                let tangents = match interpolation_type {
+                    Interpolation::CubicSpline => {
+                        match transforms.split_cubic_spline(keyframe_times.len()) {
+                            Some((values, tangents)) => {
+                                transforms = values;
+                                Some(tangents)
+                            }
+                            None => {
+                                eprintln!(
+                                    "WARN: skipping channel {} (node {node_index}) of animation '{}': the cubic spline outputs don't match the keyframe times",
+                                    channel.index(),
+                                    animation.name().unwrap_or("N/A")
+                                );
+                                continue;
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+
+                let mut channel =
                     Channel::new(node_index, keyframe_times, transforms, interpolation_type);
+                channel.tangents = tangents;
                 channels.push(channel);
             }
 
             let name = animation.name().map(|n| n.to_string());
+            let material_channels = MaterialChannel::from_gltf(&animation, bundle);
+
+            if bundle.loaded_nodes.is_some() && channels.is_empty() && material_channels.is_empty()
+            {
+                // The animation only targets nodes outside of the loaded subtree
+                continue;
+            }
 
             // Sorting floating point numbers in Rust is cumbersome, partly because of NaN
             let end_time = channels
                 .iter()
                 .map(|c| *c.keyframe_times.last().unwrap_or(&0.))
+                .chain(
+                    material_channels
+                        .iter()
+                        .map(|c| *c.keyframe_times.last().unwrap_or(&0.)),
+                )
                 .fold(0f32, |a, b| a.max(b));
-            let animation = Animation::new(channels, 0.1, end_time, name);
+            let animation = Animation::new(channels, material_channels, 0.1, end_time, name);
 
             animations.push(animation);
         }
@@ -107,6 +554,10 @@ impl Animation {
         Ok(Animations {
             animations,
             animation_control: AnimationControl::Static,
+            crossfade: None,
+            blend_tree: BlendTree1D::new(),
+            events: VecDeque::new(),
+            last_playhead: None,
         })
     }
 
@@ -146,6 +597,16 @@ pub struct Channel {
     pub transforms: AnimationTransforms,
     /// The type of the interpolation that should be applied between the keyframes
     pub interpolation_type: Interpolation,
+    /// Tangents of the keyframes of the channels authored as cubic splines.
+    ///
+    /// Other channels sampled as cubic splines (see `sample_with`) use Catmull-Rom tangents.
+    pub tangents: Option<ChannelTangents>,
+}
+
+/// The in-tangents and the out-tangents of the keyframes of a cubic spline channel
+pub struct ChannelTangents {
+    pub in_tangents: AnimationTransforms,
+    pub out_tangents: AnimationTransforms,
 }
 
 impl Channel {
@@ -160,17 +621,52 @@ impl Channel {
             keyframe_times,
             transforms,
             interpolation_type,
+            tangents: None,
         }
     }
 
-    /// Get a transform at a specific index of (keyframe_times - transforms)
-    pub fn get_fixed_transform(&self, index: usize) -> AnimationTransform {
-        match self.interpolation_type {
-            Interpolation::Linear => {}
-            Interpolation::Step => todo!("Step interpolation"),
-            Interpolation::CubicSpline => todo!("Cubic spline interpolation"),
+    /// Interpolates the transform of the channel at a specific time.
+    ///
+    /// Times before the first keyframe / after the last keyframe take the first / last transform.
+    /// Returns None if the channel doesn't have any keyframes.
+    pub fn sample(&self, time: f32) -> Option<AnimationTransform> {
+        self.sample_with(time, self.interpolation_type)
+    }
+
+    /// Interpolates the transform of the channel at a specific time with any interpolation
+    /// (regardless of how the channel was authored)
+    pub fn sample_with(
+        &self,
+        time: f32,
+        interpolation: Interpolation,
+    ) -> Option<AnimationTransform> {
+        let keyframe_times = &self.keyframe_times;
+
+        for i in 0..keyframe_times.len() {
+            let start_time = keyframe_times[i];
+
+            // If the time is before the start time of this specific channel, take the first transform.
+            if (i == keyframe_times.len() - 1) || (i == 0 && time < start_time) {
+                return Some(self.get_fixed_transform(i));
+            }
+
+            let end_time = keyframe_times[i + 1];
+
+            if start_time <= time && end_time > time {
+                let coeff = (time - start_time) / (end_time - start_time);
+                return Some(match interpolation {
+                    Interpolation::Step => self.get_fixed_transform(i),
+                    Interpolation::Linear => self.interpolate_transforms(i, coeff),
+                    Interpolation::CubicSpline => self.interpolate_cubic_spline(i, coeff),
+                });
+            }
         }
 
+        None
+    }
+
+    /// Get a transform at a specific index of (keyframe_times - transforms)
+    pub fn get_fixed_transform(&self, index: usize) -> AnimationTransform {
         match &self.transforms {
             AnimationTransforms::Translations(trans) => {
                 AnimationTransform::Translation(trans[index])
@@ -190,12 +686,6 @@ impl Channel {
         start_index: usize, // end index is always start_index + 1
         coeff: f32,
     ) -> AnimationTransform {
-        match self.interpolation_type {
-            Interpolation::Linear => {}
-            Interpolation::Step => todo!("Step interpolation"),
-            Interpolation::CubicSpline => todo!("Cubic spline interpolation"),
-        }
-
         match &self.transforms {
             AnimationTransforms::Translations(trans) => {
                 let start = trans[start_index];
@@ -225,6 +715,70 @@ impl Channel {
             }
         }
     }
+
+    /// Get a transform between (keyframe_times - transforms)[start_index..start_index + 1]
+    /// interpolated by the cubic Hermite spline.
+    /// <https://www.khronos.org/registry/glTF/specs/2.0/glTF-2.0.html#appendix-c-interpolation>
+    pub fn interpolate_cubic_spline(&self, start_index: usize, coeff: f32) -> AnimationTransform {
+        let end_index = start_index + 1;
+        let delta_time = self.keyframe_times[end_index] - self.keyframe_times[start_index];
+
+        let start = self.transforms.vec4(start_index);
+        let mut end = self.transforms.vec4(end_index);
+
+        let (start_out, end_in) = match &self.tangents {
+            Some(tangents) => (
+                tangents.out_tangents.vec4(start_index),
+                tangents.in_tangents.vec4(end_index),
+            ),
+            None => {
+                let start_tangent = self.catmull_rom_tangent(start_index);
+                let end_tangent = self.catmull_rom_tangent(end_index);
+
+                // The generated tangents follow the sign of their rotation, the shorter path is taken
+                if self.is_rotation() && start.dot(end) < 0. {
+                    end = -end;
+                    (start_tangent, -end_tangent)
+                } else {
+                    (start_tangent, end_tangent)
+                }
+            }
+        };
+
+        let t = coeff;
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        let interpolated = (2. * t3 - 3. * t2 + 1.) * start
+            + delta_time * (t3 - 2. * t2 + t) * start_out
+            + (-2. * t3 + 3. * t2) * end
+            + delta_time * (t3 - t2) * end_in;
+
+        self.transforms.transform_from_vec4(interpolated)
+    }
+
+    /// Tangent of the keyframe from its neighbours (one-sided at the ends), per second
+    fn catmull_rom_tangent(&self, index: usize) -> Vec4 {
+        let last = self.keyframe_times.len() - 1;
+        let (prev, next) = (index.saturating_sub(1), (index + 1).min(last));
+
+        let delta_time = self.keyframe_times[next] - self.keyframe_times[prev];
+        if delta_time <= 0. {
+            return Vec4::ZERO;
+        }
+
+        let value = self.transforms.vec4(index);
+        let align = |v: Vec4| match self.is_rotation() && v.dot(value) < 0. {
+            true => -v,
+            false => v,
+        };
+
+        (align(self.transforms.vec4(next)) - align(self.transforms.vec4(prev))) / delta_time
+    }
+
+    fn is_rotation(&self) -> bool {
+        matches!(self.transforms, AnimationTransforms::Rotations(_))
+    }
 }
 
 /// The type of all animation transforms in the channel
@@ -234,9 +788,287 @@ pub enum AnimationTransforms {
     Scales(Vec<Vec3>),
 }
 
+impl AnimationTransforms {
+    /// Checks that none of the transforms contain NaN or Inf
+    pub fn is_finite(&self) -> bool {
+        match self {
+            AnimationTransforms::Translations(trans) => trans.iter().all(|t| t.is_finite()),
+            AnimationTransforms::Rotations(rotations) => rotations.iter().all(|r| r.is_finite()),
+            AnimationTransforms::Scales(scales) => scales.iter().all(|s| s.is_finite()),
+        }
+    }
+
+    /// Splits the outputs of a cubic spline (in-tangent, value, out-tangent for every keyframe)
+    /// into the values and the tangents. None if the number of the outputs doesn't match.
+    fn split_cubic_spline(
+        self,
+        keyframe_count: usize,
+    ) -> Option<(AnimationTransforms, ChannelTangents)> {
+        fn split<T: Copy>(outputs: Vec<T>, count: usize) -> Option<(Vec<T>, Vec<T>, Vec<T>)> {
+            if outputs.len() != count * 3 {
+                return None;
+            }
+
+            let nth = |offset: usize| outputs.iter().skip(offset).step_by(3).copied().collect();
+            Some((nth(0), nth(1), nth(2)))
+        }
+
+        let (values, in_tangents, out_tangents) = match self {
+            AnimationTransforms::Translations(trans) => {
+                let (i, v, o) = split(trans, keyframe_count)?;
+                (
+                    AnimationTransforms::Translations(v),
+                    AnimationTransforms::Translations(i),
+                    AnimationTransforms::Translations(o),
+                )
+            }
+            AnimationTransforms::Rotations(rotations) => {
+                let (i, v, o) = split(rotations, keyframe_count)?;
+                (
+                    AnimationTransforms::Rotations(v),
+                    AnimationTransforms::Rotations(i),
+                    AnimationTransforms::Rotations(o),
+                )
+            }
+            AnimationTransforms::Scales(scales) => {
+                let (i, v, o) = split(scales, keyframe_count)?;
+                (
+                    AnimationTransforms::Scales(v),
+                    AnimationTransforms::Scales(i),
+                    AnimationTransforms::Scales(o),
+                )
+            }
+        };
+
+        Some((
+            values,
+            ChannelTangents {
+                in_tangents,
+                out_tangents,
+            },
+        ))
+    }
+
+    /// The transform at the index as a vector (translations and scales have a W of 0)
+    fn vec4(&self, index: usize) -> Vec4 {
+        match self {
+            AnimationTransforms::Translations(trans) => trans[index].extend(0.),
+            AnimationTransforms::Rotations(rotations) => Vec4::from(rotations[index]),
+            AnimationTransforms::Scales(scales) => scales[index].extend(0.),
+        }
+    }
+
+    /// A transform of the same kind from a vector (rotations are normalized)
+    fn transform_from_vec4(&self, v: Vec4) -> AnimationTransform {
+        match self {
+            AnimationTransforms::Translations(_) => AnimationTransform::Translation(v.truncate()),
+            AnimationTransforms::Rotations(_) => {
+                AnimationTransform::Rotation(Quat::from_vec4(v).normalize())
+            }
+            AnimationTransforms::Scales(_) => AnimationTransform::Scale(v.truncate()),
+        }
+    }
+}
+
+/// The transform of a node sampled from an animation
+pub struct NodeAnimationTransform {
+    /// Index of the node
+    pub node: usize,
+    /// Transform that should overwrite the node's current transform
+    pub transform: AnimationTransform,
+}
+
+impl NodeAnimationTransform {
+    pub fn new(node: usize, transform: AnimationTransform) -> Self {
+        Self { node, transform }
+    }
+}
+
 /// The type of a single animation transform
 pub enum AnimationTransform {
     Translation(Vec3),
     Rotation(Quat),
     Scale(Vec3),
 }
+
+impl AnimationTransform {
+    /// Blends from this transform to `other` by the 'coeff' coefficient.
+    ///
+    /// Returns None if the transforms are of a different kind.
+    pub fn blend(&self, other: &AnimationTransform, coeff: f32) -> Option<AnimationTransform> {
+        match (self, other) {
+            (AnimationTransform::Translation(start), AnimationTransform::Translation(end)) => {
+                Some(AnimationTransform::Translation(start.lerp(*end, coeff)))
+            }
+            (AnimationTransform::Rotation(start), AnimationTransform::Rotation(end)) => {
+                let interpolated = if start.dot(*end) > 0. {
+                    start.slerp(*end, coeff)
+                } else {
+                    (-*start).slerp(*end, coeff)
+                };
+
+                Some(AnimationTransform::Rotation(interpolated.normalize()))
+            }
+            (AnimationTransform::Scale(start), AnimationTransform::Scale(end)) => {
+                Some(AnimationTransform::Scale(start.lerp(*end, coeff)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        f32::consts::{FRAC_PI_2, PI},
+        path::Path,
+    };
+
+    use super::*;
+    use crate::model::ImportOptions;
+
+    /// Node 0 moves along X and then along Y, node 1 turns by 90 degrees around Z
+    fn test_animation() -> Animation {
+        let translation = Channel::new(
+            0,
+            vec![0., 1., 2.],
+            AnimationTransforms::Translations(vec![
+                Vec3::ZERO,
+                Vec3::new(2., 0., 0.),
+                Vec3::new(2., 4., 0.),
+            ]),
+            Interpolation::Linear,
+        );
+        let rotation = Channel::new(
+            1,
+            vec![0., 2.],
+            AnimationTransforms::Rotations(vec![Quat::IDENTITY, Quat::from_rotation_z(FRAC_PI_2)]),
+            Interpolation::Linear,
+        );
+
+        Animation::new(vec![translation, rotation], Vec::new(), 0., 2., None)
+    }
+
+    fn translation(transforms: &[NodeAnimationTransform]) -> Vec3 {
+        match transforms
+            .iter()
+            .find(|nat| nat.node == 0)
+            .map(|nat| &nat.transform)
+        {
+            Some(AnimationTransform::Translation(translation)) => *translation,
+            _ => panic!("node 0 should have a translation"),
+        }
+    }
+
+    fn rotation(transforms: &[NodeAnimationTransform]) -> Quat {
+        match transforms
+            .iter()
+            .find(|nat| nat.node == 1)
+            .map(|nat| &nat.transform)
+        {
+            Some(AnimationTransform::Rotation(rotation)) => *rotation,
+            _ => panic!("node 1 should have a rotation"),
+        }
+    }
+
+    #[test]
+    fn sample_at_keyframes() {
+        let animation = test_animation();
+
+        let start = animation.sample(0.);
+        assert_eq!(start.len(), 2);
+        assert!(translation(&start).abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert!(rotation(&start).abs_diff_eq(Quat::IDENTITY, 1e-6));
+
+        let middle = animation.sample(1.);
+        assert!(translation(&middle).abs_diff_eq(Vec3::new(2., 0., 0.), 1e-6));
+
+        let end = animation.sample(2.);
+        assert!(translation(&end).abs_diff_eq(Vec3::new(2., 4., 0.), 1e-6));
+        assert!(rotation(&end).abs_diff_eq(Quat::from_rotation_z(FRAC_PI_2), 1e-6));
+    }
+
+    #[test]
+    fn sample_between_keyframes() {
+        let animation = test_animation();
+
+        let transforms = animation.sample(0.5);
+        assert!(translation(&transforms).abs_diff_eq(Vec3::new(1., 0., 0.), 1e-6));
+        assert!(rotation(&transforms).abs_diff_eq(Quat::from_rotation_z(FRAC_PI_2 / 4.), 1e-6));
+
+        let transforms = animation.sample(1.75);
+        assert!(translation(&transforms).abs_diff_eq(Vec3::new(2., 3., 0.), 1e-6));
+    }
+
+    #[test]
+    fn sample_outside_of_the_keyframes() {
+        let animation = test_animation();
+
+        let after = animation.sample(5.);
+        assert!(translation(&after).abs_diff_eq(Vec3::new(2., 4., 0.), 1e-6));
+        assert!(rotation(&after).abs_diff_eq(Quat::from_rotation_z(FRAC_PI_2), 1e-6));
+
+        let before = animation.sample(-1.);
+        assert!(translation(&before).abs_diff_eq(Vec3::ZERO, 1e-6));
+    }
+
+    #[test]
+    fn sample_keeps_the_playback_state() {
+        let mut animation = test_animation();
+        animation.current_time = 0.25;
+
+        animation.sample(1.5);
+        assert_eq!(animation.current_time, 0.25);
+    }
+
+    #[test]
+    fn node_animated_hierarchy() {
+        let path = Path::new("resources/spinning_propeller/SpinningPropeller.gltf");
+        let mut progress = |_, _| {};
+        let (gltf, bundle) =
+            DataBundle::import(path, ImportOptions::default(), &mut progress).unwrap();
+        assert_eq!(gltf.skins().len(), 0);
+
+        let animations = Animation::from_gltf(&gltf, &bundle).unwrap();
+        let spin = &animations.animations[0];
+        assert_eq!(spin.name.as_deref(), Some("Spin"));
+
+        // The propeller (node 1) turns by a half turn in half a second, the body (node 0) is at the top
+        let transforms = spin.sample(0.5);
+        let propeller = transforms.iter().find(|nat| nat.node == 1).unwrap();
+        match propeller.transform {
+            AnimationTransform::Rotation(rotation) => {
+                let expected = Quat::from_rotation_y(PI);
+                assert!(rotation.dot(expected).abs() > 1. - 1e-5);
+            }
+            _ => panic!("the propeller should be rotated"),
+        }
+
+        let body = transforms.iter().find(|nat| nat.node == 0).unwrap();
+        match body.transform {
+            AnimationTransform::Translation(translation) => {
+                assert!(translation.abs_diff_eq(Vec3::new(0., 1.2, 0.), 1e-6));
+            }
+            _ => panic!("the body should be translated"),
+        }
+    }
+
+    #[test]
+    fn channel_step_interpolation() {
+        let channel = Channel::new(
+            0,
+            vec![0., 1.],
+            AnimationTransforms::Scales(vec![Vec3::ONE, Vec3::splat(2.)]),
+            Interpolation::Step,
+        );
+
+        match channel.sample(0.9) {
+            Some(AnimationTransform::Scale(scale)) => assert_eq!(scale, Vec3::ONE),
+            _ => panic!("the channel should be sampled as a scale"),
+        }
+        match channel.sample(1.) {
+            Some(AnimationTransform::Scale(scale)) => assert_eq!(scale, Vec3::splat(2.)),
+            _ => panic!("the channel should be sampled as a scale"),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use glam::{Mat4, Vec3};
+use gltf::camera::Projection;
+
+use super::{MirrorAxis, Node};
+
+/// A camera defined by a node of the gltf file (the view framed by the author of the asset)
+pub struct AssetCamera {
+    /// Name of the camera (or of its node)
+    pub name: String,
+    /// Position of the camera in the space of the model
+    pub pos: Vec3,
+    /// Direction the camera is looking in (in the space of the model).
+    ///
+    /// The roll of the camera is ignored, the viewer camera is always upright.
+    pub dir: Vec3,
+    pub projection: CameraProjection,
+}
+
+/// The projection parameters of a gltf camera
+#[derive(Clone, Copy)]
+pub enum CameraProjection {
+    Perspective {
+        /// Vertical field of view in radians
+        yfov: f32,
+        znear: f32,
+        /// None is an infinite projection
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        /// Half of the height of the view volume
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl AssetCamera {
+    /// Finds the cameras of the nodes in the hierarchy and computes their transforms in the space of the model.
+    ///
+    /// The aspect ratio and the horizontal magnification are ignored, the viewer uses the aspect of the window.
+    pub fn collect(gltf: &gltf::Document, root: &Node, mirror: MirrorAxis) -> Vec<AssetCamera> {
+        let camera_nodes: HashMap<usize, (gltf::Camera, String)> = gltf
+            .nodes()
+            .filter_map(|node| {
+                let camera = node.camera()?;
+                let name = camera
+                    .name()
+                    .or(node.name())
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("Camera-{}", camera.index()));
+
+                Some((node.index(), (camera, name)))
+            })
+            .collect();
+
+        let mut cameras = Vec::new();
+        if !camera_nodes.is_empty() {
+            Self::collect_node(root, Mat4::IDENTITY, &camera_nodes, mirror, &mut cameras);
+        }
+
+        cameras
+    }
+
+    /// Recursive - adds the camera of the node and the cameras of its descendants
+    fn collect_node(
+        node: &Node,
+        outer_transform: Mat4,
+        camera_nodes: &HashMap<usize, (gltf::Camera, String)>,
+        mirror: MirrorAxis,
+        cameras: &mut Vec<AssetCamera>,
+    ) {
+        let transform = outer_transform * node.transform;
+
+        if let Some((camera, name)) = camera_nodes.get(&node.index) {
+            let projection = match camera.projection() {
+                Projection::Perspective(p) => CameraProjection::Perspective {
+                    yfov: p.yfov(),
+                    znear: p.znear(),
+                    zfar: p.zfar(),
+                },
+                Projection::Orthographic(o) => CameraProjection::Orthographic {
+                    ymag: o.ymag(),
+                    znear: o.znear(),
+                    zfar: o.zfar(),
+                },
+            };
+
+            // The gltf cameras look in the -Z direction of their node. The node transforms are
+            // already mirrored (M * T * M), so the local direction has to be mirrored too.
+            cameras.push(AssetCamera {
+                name: name.clone(),
+                pos: transform.transform_point3(Vec3::ZERO),
+                dir: transform.transform_vector3(mirror.vec3(-Vec3::Z)),
+                projection,
+            });
+        }
+
+        for child in &node.children {
+            Self::collect_node(child, transform, camera_nodes, mirror, cameras);
+        }
+    }
+}
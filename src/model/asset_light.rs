@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use glam::{Mat4, Vec3};
+use gltf::khr_lights_punctual::Kind;
+
+use super::{MirrorAxis, Node};
+
+/// A light defined by a node of the gltf file (KHR_lights_punctual)
+pub struct AssetLight {
+    /// Name of the light (or of its node)
+    pub name: String,
+    pub kind: LightKind,
+    /// Linear color of the light
+    pub color: Vec3,
+    /// Lux for the directional lights, candela for the point and spot lights
+    pub intensity: f32,
+    /// Distance where the light fades out completely, None is an unlimited range
+    pub range: Option<f32>,
+    /// Position of the light in the space of the model (ignored by the directional lights)
+    pub pos: Vec3,
+    /// Direction the light is shining in (in the space of the model, ignored by the point lights)
+    pub dir: Vec3,
+}
+
+/// The type of a gltf light
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        /// Angle from the direction in radians where the light starts to fade
+        inner_cone_angle: f32,
+        /// Angle from the direction in radians where the light is faded out completely
+        outer_cone_angle: f32,
+    },
+}
+
+impl AssetLight {
+    /// Finds the lights of the nodes in the hierarchy and computes their transforms in the space of the model
+    pub fn collect(gltf: &gltf::Document, root: &Node, mirror: MirrorAxis) -> Vec<AssetLight> {
+        let light_nodes: HashMap<usize, (gltf::khr_lights_punctual::Light, String)> = gltf
+            .nodes()
+            .filter_map(|node| {
+                let light = node.light()?;
+                let name = light
+                    .name()
+                    .or(node.name())
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("Light-{}", light.index()));
+
+                Some((node.index(), (light, name)))
+            })
+            .collect();
+
+        let mut lights = Vec::new();
+        if !light_nodes.is_empty() {
+            Self::collect_node(root, Mat4::IDENTITY, &light_nodes, mirror, &mut lights);
+        }
+
+        lights
+    }
+
+    /// Recursive - adds the light of the node and the lights of its descendants
+    fn collect_node(
+        node: &Node,
+        outer_transform: Mat4,
+        light_nodes: &HashMap<usize, (gltf::khr_lights_punctual::Light, String)>,
+        mirror: MirrorAxis,
+        lights: &mut Vec<AssetLight>,
+    ) {
+        let transform = outer_transform * node.transform;
+
+        if let Some((light, name)) = light_nodes.get(&node.index) {
+            let kind = match light.kind() {
+                Kind::Directional => LightKind::Directional,
+                Kind::Point => LightKind::Point,
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => LightKind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                },
+            };
+
+            // The lights shine in the -Z direction of their node, like the cameras look.
+            // The scale of the node doesn't affect the light.
+            lights.push(AssetLight {
+                name: name.clone(),
+                kind,
+                color: Vec3::from(light.color()),
+                intensity: light.intensity(),
+                range: light.range(),
+                pos: transform.transform_point3(Vec3::ZERO),
+                dir: transform
+                    .transform_vector3(mirror.vec3(-Vec3::Z))
+                    .normalize_or_zero(),
+            });
+        }
+
+        for child in &node.children {
+            Self::collect_node(child, transform, light_nodes, mirror, lights);
+        }
+    }
+}
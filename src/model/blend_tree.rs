@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+use super::Animation;
+
+/// A 1D blend space - blends between clips placed at positions on a single parameter axis
+/// (for example idle, walk and run placed on a speed axis).
+///
+/// The clips are played synchronized by a shared normalized phase, so a walk and a run cycle
+/// are always in the same part of the step when blended.
+pub struct BlendTree1D {
+    /// If the blend tree drives the model instead of the active animation
+    pub enabled: bool,
+    /// The clips and their positions on the parameter axis
+    pub points: Vec<BlendPoint>,
+    /// The current value of the blend parameter
+    pub parameter: f32,
+    /// Normalized time of the blended cycle (0..1)
+    phase: f32,
+    /// Time of the last update, None if the blend tree was paused
+    last_update: Option<Instant>,
+}
+
+/// A single clip in the blend tree
+#[derive(Clone, Copy)]
+pub struct BlendPoint {
+    /// Index of the animation
+    pub animation: usize,
+    /// Position of the clip on the parameter axis
+    pub position: f32,
+}
+
+/// The two clips that should be sampled and blended in the current frame
+pub struct BlendSample {
+    /// Index and time of the clip below the parameter
+    pub from: (usize, f32),
+    /// Index and time of the clip above the parameter
+    pub to: (usize, f32),
+    /// Weight of the 'to' clip
+    pub coeff: f32,
+}
+
+impl BlendTree1D {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            points: Vec::new(),
+            parameter: 0.,
+            phase: 0.,
+            last_update: None,
+        }
+    }
+
+    /// Starts the blended cycle from the beginning
+    pub fn reset(&mut self) {
+        self.phase = 0.;
+        self.last_update = None;
+    }
+
+    /// Moves the time of the last update forward, so that the duration isn't counted as playback time
+    pub fn delay(&mut self, duration: Duration) {
+        if let Some(last_update) = &mut self.last_update {
+            *last_update += duration;
+        }
+    }
+
+    /// The points surrounding the parameter and the weight of the upper point
+    fn bracket(&self) -> Option<(BlendPoint, BlendPoint, f32)> {
+        let mut points = self.points.clone();
+        points.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        let first = *points.first()?;
+        let last = *points.last()?;
+
+        if self.parameter <= first.position {
+            return Some((first, first, 0.));
+        }
+
+        if self.parameter >= last.position {
+            return Some((last, last, 0.));
+        }
+
+        points.windows(2).find_map(|window| {
+            let (from, to) = (window[0], window[1]);
+            if self.parameter >= from.position && self.parameter < to.position {
+                let coeff = (self.parameter - from.position) / (to.position - from.position);
+                Some((from, to, coeff))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Advances the shared phase and returns which clips to sample at which times.
+    ///
+    /// The cycle duration is interpolated between the durations of the blended clips,
+    /// the phase is then mapped onto the trimmed range of each of them.
+    pub fn advance(&mut self, animations: &[Animation]) -> Option<BlendSample> {
+        let now = Instant::now();
+        let delta = self
+            .last_update
+            .map(|last| now.duration_since(last).as_secs_f32())
+            .unwrap_or(0.);
+        self.last_update = Some(now);
+
+        let (from, to, coeff) = self.bracket()?;
+        let from_anim = animations.get(from.animation)?;
+        let to_anim = animations.get(to.animation)?;
+
+        let duration = |anim: &Animation| (anim.trim_end - anim.trim_start).max(f32::EPSILON);
+        let from_duration = duration(from_anim);
+        let to_duration = duration(to_anim);
+
+        let cycle_duration = from_duration + (to_duration - from_duration) * coeff;
+        self.phase = (self.phase + delta / cycle_duration).fract();
+
+        Some(BlendSample {
+            from: (
+                from.animation,
+                from_anim.trim_start + self.phase * from_duration,
+            ),
+            to: (to.animation, to_anim.trim_start + self.phase * to_duration),
+            coeff,
+        })
+    }
+}
+
+impl Default for BlendTree1D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
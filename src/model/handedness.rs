@@ -0,0 +1,112 @@
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use super::{AnimationTransforms, Transform};
+
+/// Conversions of the vertex data and transforms applied when the model is loaded,
+/// for assets authored in left-handed tools or exported with an inconsistent winding.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handedness {
+    /// Axis negated in the whole asset (vertices, node transforms, skins and animations).
+    ///
+    /// The winding of the triangles is reversed as well, so the mirrored faces keep facing outwards.
+    pub mirror: MirrorAxis,
+    /// Reverses the winding order of the triangles (clockwise front faces)
+    pub flip_winding: bool,
+    /// Negates the normals stored in the file (generated normals follow the winding)
+    pub flip_normals: bool,
+    /// The up axis of the asset, Z-up assets are rotated by the transform of the model (the data isn't changed)
+    pub up_axis: UpAxis,
+}
+
+impl Handedness {
+    /// If the triangles have to be reversed, mirroring and flipping the winding cancel each other out
+    pub fn reverses_winding(&self) -> bool {
+        (self.mirror != MirrorAxis::None) != self.flip_winding
+    }
+}
+
+/// The up axis of an asset (glTF is Y-up, some exporters produce Z-up assets lying on their side)
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    pub const ALL: [UpAxis; 2] = [UpAxis::Y, UpAxis::Z];
+
+    /// The rotation of the model standing the asset upright (-90° around X for Z-up)
+    pub fn correction(self) -> Mat4 {
+        match self {
+            UpAxis::Y => Mat4::IDENTITY,
+            UpAxis::Z => Mat4::from_rotation_x(-90f32.to_radians()),
+        }
+    }
+}
+
+/// The axis negated to convert a left-handed asset to the right-handed glTF coordinate system
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MirrorAxis {
+    #[default]
+    None,
+    X,
+    Z,
+}
+
+impl MirrorAxis {
+    pub const ALL: [MirrorAxis; 3] = [MirrorAxis::None, MirrorAxis::X, MirrorAxis::Z];
+
+    /// The scale of the reflection
+    fn scale(self) -> Vec3 {
+        match self {
+            MirrorAxis::None => Vec3::ONE,
+            MirrorAxis::X => Vec3::new(-1., 1., 1.),
+            MirrorAxis::Z => Vec3::new(1., 1., -1.),
+        }
+    }
+
+    /// Mirrors a position, a translation or a direction
+    pub fn vec3(self, v: Vec3) -> Vec3 {
+        v * self.scale()
+    }
+
+    /// Mirrors a rotation, the rotation axis is mirrored and the angle is negated
+    pub fn quat(self, q: Quat) -> Quat {
+        match self {
+            MirrorAxis::None => q,
+            MirrorAxis::X => Quat::from_xyzw(q.x, -q.y, -q.z, q.w),
+            MirrorAxis::Z => Quat::from_xyzw(-q.x, -q.y, q.z, q.w),
+        }
+    }
+
+    /// Mirrors a transformation matrix (M * m * M, where M is the reflection)
+    pub fn mat4(self, m: Mat4) -> Mat4 {
+        let reflection = Mat4::from_scale(self.scale());
+        reflection * m * reflection
+    }
+
+    /// Mirrors the components of a transform, the scale isn't affected
+    pub fn transform(self, t: Transform) -> Transform {
+        Transform {
+            translation: self.vec3(t.translation),
+            rotation: self.quat(t.rotation),
+            scale: t.scale,
+        }
+    }
+
+    /// Mirrors the animated values (including the cubic spline tangents, the mapping is linear)
+    pub fn animation_transforms(self, transforms: &mut AnimationTransforms) {
+        match transforms {
+            AnimationTransforms::Translations(translations) => {
+                translations.iter_mut().for_each(|t| *t = self.vec3(*t));
+            }
+            AnimationTransforms::Rotations(rotations) => {
+                rotations.iter_mut().for_each(|r| *r = self.quat(*r));
+            }
+            AnimationTransforms::Scales(_) => {}
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use eyre::Result;
 use glam::Mat4;
 
-use super::{DataBundle, Transform};
+use super::{DataBundle, LookAt, MirrorAxis, NodeAnimationTransform, Transform};
 
 /// A structure containing the joint data (the skeleton).
 /// The skeleton is represented as a tree in the gltf format.
@@ -9,6 +9,9 @@ use super::{DataBundle, Transform};
 /// I decided to store the joint hierarchy in a flat buffer.
 /// Every Joint has an Optional index to it's parent (the index of the root is `Option::None`).
 /// The parent nodes are always place *before* their child nodes in the buffer.
+///
+/// A model can contain multiple skins (e.g. a character and a separately skinned weapon),
+/// every skinned node has its own `Joints` and the renderer uploads its matrices before drawing the mesh.
 pub struct Joints {
     pub joints: Vec<Joint>,
 }
@@ -25,9 +28,12 @@ impl Joints {
         let mut joints = Vec::new();
 
         let reader = skin.reader(|buf| Some(&bundle.buffers[buf.index()]));
+        let mirror = bundle.options.handedness.mirror;
         let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
-            Some(matrices) => matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect(),
-            None => vec![Mat4::IDENTITY; joints.len()],
+            Some(matrices) => matrices
+                .map(|m| mirror.mat4(Mat4::from_cols_array_2d(&m)))
+                .collect(),
+            None => vec![Mat4::IDENTITY; joint_indices.len()],
         };
 
         // TODO: not great performance-wise
@@ -39,11 +45,100 @@ impl Joints {
             None,
             &mut joints,
             &inverse_bind_matrices,
+            mirror,
         );
 
         Ok(Self { joints })
     }
 
+    /// Applies the current animation transforms to the joints.
+    ///
+    /// When an animation is sampled, the joints and channels missing from it use the rest transform
+    /// (a scale left over from a previous animation isn't kept). Otherwise the joints keep their
+    /// current transform (edited in the GUI). The overrides set from code are applied last.
+    pub fn apply_animation(&mut self, transforms: &[NodeAnimationTransform]) {
+        for joint in &mut self.joints {
+            if !transforms.is_empty() {
+                joint.transform = joint.rest_transform;
+
+                for nat in transforms {
+                    if joint.node_index == nat.node {
+                        joint.transform.apply_animation(&nat.transform);
+                    }
+                }
+            }
+
+            // The transforms set from code win over the animations
+            if let Some(pose_override) = joint.pose_override {
+                joint.transform = pose_override;
+            }
+        }
+    }
+
+    /// Calculates the world transforms of the joints (in the order of the hierarchy) into 'world_transforms'.
+    ///
+    /// The look-at constraint is applied after the animations and the overrides,
+    /// the rotated joint carries its children along.
+    pub fn world_transforms(
+        &self,
+        outer_transform: Mat4,
+        look_at: Option<&LookAt>,
+        world_transforms: &mut Vec<Mat4>,
+    ) {
+        let joints = &self.joints;
+        world_transforms.clear();
+        world_transforms.resize(joints.len(), Mat4::IDENTITY);
+
+        // Cascade transformation from parent joints to child joints.
+        // Parent joints are always placed before child joints in the buffer, so they are processed first.
+        for i in 0..joints.len() {
+            let parent_transform = match joints[i].parent {
+                Some(parent_index) => world_transforms[parent_index],
+                None => outer_transform,
+            };
+
+            let local_transform = match look_at {
+                Some(look_at) if look_at.joint == joints[i].name => {
+                    look_at.apply(joints[i].transform, parent_transform)
+                }
+                _ => joints[i].transform,
+            };
+
+            world_transforms[i] = parent_transform * local_transform.matrix();
+        }
+    }
+
+    /// The sign of the determinant of the skinning matrices, -1 when most of the joints are mirrored.
+    ///
+    /// Mirrored joints (e.g. a negative scale of any joint, not only of the root) flip the winding order
+    /// of the triangles. A mesh is drawn with a single winding order, so the skin follows the majority
+    /// of its joints (skins without joints aren't mirrored).
+    pub fn determinant(&self, matrices: &[Mat4]) -> f32 {
+        let mirrored = self
+            .joints
+            .iter()
+            .filter(|joint| matrices[joint.skin_index].determinant() < 0.)
+            .count();
+
+        if mirrored * 2 > self.joints.len() {
+            -1.
+        } else {
+            1.
+        }
+    }
+
+    /// Calculates the skinning matrices from the world transforms of the joints into 'matrices'.
+    ///
+    /// The vertices index the joints in the order of the skin, not in the order of the hierarchy.
+    pub fn joint_matrices(&self, world_transforms: &[Mat4], matrices: &mut Vec<Mat4>) {
+        matrices.clear();
+        matrices.resize(self.joints.len(), Mat4::IDENTITY);
+
+        for (joint, world_transform) in self.joints.iter().zip(world_transforms) {
+            matrices[joint.skin_index] = *world_transform * joint.inverse_bind_matrix;
+        }
+    }
+
     /// Traverse the scene and arrange the joint nodes into a correct hierarchy
     /// <https://www.khronos.org/registry/glTF/specs/2.0/glTF-2.0.html#joint-hierarchy>
     /// "A node object does not specify whether it is a joint.
@@ -54,6 +149,7 @@ impl Joints {
         parent: Option<usize>,
         joints: &mut Vec<Joint>,
         inverse_bind_matrices: &[Mat4],
+        mirror: MirrorAxis,
     ) {
         for node in nodes {
             let children: Vec<gltf::Node> = node.children().collect();
@@ -66,10 +162,11 @@ impl Joints {
                 let matrix_index = joint_indices.iter().position(|i| *i == index).unwrap();
                 let name = node.name().unwrap_or(&format!("Joint-{index}")).to_string();
 
-                let transform = Transform::from_gltf(node);
+                let transform = mirror.transform(Transform::from_gltf(node));
 
                 joints.push(Joint::new(
                     index,
+                    matrix_index,
                     parent,
                     inverse_bind_matrices[matrix_index],
                     transform,
@@ -82,6 +179,7 @@ impl Joints {
                     Some(joints_index),
                     joints,
                     inverse_bind_matrices,
+                    mirror,
                 );
 
                 if parent.is_none() {
@@ -100,6 +198,7 @@ impl Joints {
                     parent,
                     joints,
                     inverse_bind_matrices,
+                    mirror,
                 );
             }
         }
@@ -110,12 +209,19 @@ impl Joints {
 pub struct Joint {
     /// The same node index as in the gltf file
     pub node_index: usize,
+    /// Index of the joint in the skin, the joint indices of the vertices refer to this order
+    /// (which doesn't have to match the order of the hierarchy)
+    pub skin_index: usize,
     /// An index to the parent node (None if this joint is the root)
     pub parent: Option<usize>,
     /// The matrix that transforms this node to the origin
     pub inverse_bind_matrix: Mat4,
     /// The transform of the joint relative to it's parent
     pub transform: Transform,
+    /// The transform of the joint in the rest pose
+    pub rest_transform: Transform,
+    /// Local transform set from code (procedural animation), replaces the sampled animation
+    pub pose_override: Option<Transform>,
     /// Name for debug purposes
     pub name: String,
 }
@@ -123,6 +229,7 @@ pub struct Joint {
 impl Joint {
     pub fn new(
         node_index: usize,
+        skin_index: usize,
         parent: Option<usize>,
         inverse_bind_matrix: Mat4,
         transform: Transform,
@@ -130,10 +237,177 @@ impl Joint {
     ) -> Self {
         Self {
             node_index,
+            skin_index,
             parent,
             inverse_bind_matrix,
             transform,
+            rest_transform: transform,
+            pose_override: None,
             name,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use glam::Vec3;
+
+    use super::*;
+    use crate::model::{Animation, Animations, ImportOptions, Model};
+
+    /// Loads the joints of all skins and the animations of the file
+    fn load_skins(path: &str) -> (Vec<Joints>, Animations) {
+        let mut progress = |_, _| {};
+        let (gltf, mut bundle) =
+            DataBundle::import(Path::new(path), ImportOptions::default(), &mut progress).unwrap();
+
+        let scene = gltf.scenes().next().unwrap();
+        let joints = gltf
+            .skins()
+            .map(|skin| Joints::from_gltf(&mut bundle, &skin, &scene).unwrap())
+            .collect();
+        let animations = Animation::from_gltf(&gltf, &bundle).unwrap();
+
+        (joints, animations)
+    }
+
+    /// The skinning matrices of the joints posed by the animation at the time
+    fn posed_matrices(joints: &mut Joints, animation: &Animation, time: f32) -> Vec<Mat4> {
+        joints.apply_animation(&animation.sample(time));
+
+        let mut world_transforms = Vec::new();
+        joints.world_transforms(Mat4::IDENTITY, None, &mut world_transforms);
+
+        let mut matrices = Vec::new();
+        joints.joint_matrices(&world_transforms, &mut matrices);
+        matrices
+    }
+
+    /// The skinning matrices of the model at the current time of its first animation
+    fn current_joint_matrices(model: &mut Model) -> Vec<Mat4> {
+        let animation = &model.animations.animations[0];
+        let transforms = animation.sample(animation.current_time);
+
+        let mut matrices = Vec::new();
+        model.visit_nodes_mut(|node, _| {
+            if let Some(joints) = &mut node.joints {
+                joints.apply_animation(&transforms);
+
+                let mut world_transforms = Vec::new();
+                joints.world_transforms(Mat4::IDENTITY, None, &mut world_transforms);
+                joints.joint_matrices(&world_transforms, &mut matrices);
+            }
+        });
+
+        matrices
+    }
+
+    #[test]
+    fn joint_matrices_keep_the_scale() {
+        let (mut skins, animations) = load_skins("resources/squash_stretch/SquashStretch.gltf");
+        let joints = &mut skins[0];
+        let squash_stretch = &animations.animations[0];
+        assert_eq!(squash_stretch.name.as_deref(), Some("SquashStretch"));
+
+        // 'Top' is stretched along Y and squashed along X and Z, the vertices above it are scaled around it
+        let matrices = posed_matrices(joints, squash_stretch, 0.5);
+        let top = matrices[1];
+        assert!(top
+            .transform_point3(Vec3::new(0., 2., 0.))
+            .abs_diff_eq(Vec3::new(0., 2.6, 0.), 1e-5));
+        assert!(top
+            .transform_point3(Vec3::new(1., 1., 0.))
+            .abs_diff_eq(Vec3::new(0.6, 1., 0.), 1e-5));
+
+        // The scale of 'Top' isn't kept when the other clip only animates 'Root'
+        let non_uniform_root = &animations.animations[1];
+        posed_matrices(joints, non_uniform_root, 0.5);
+        assert_eq!(joints.joints[1].transform.scale, Vec3::ONE);
+        assert!(joints.joints[0]
+            .transform
+            .scale
+            .abs_diff_eq(Vec3::new(2., 1., 0.5), 1e-6));
+    }
+
+    #[test]
+    fn joint_matrices_in_the_order_of_the_skin() {
+        let (mut skins, animations) = load_skins("resources/two_skins/TwoSkins.gltf");
+        let independent = &animations.animations[0];
+
+        let weapon = &mut skins[1];
+        let names: Vec<&str> = weapon.joints.iter().map(|j| j.name.as_str()).collect();
+        assert_eq!(names, ["Grip", "Blade"]);
+        assert_eq!(weapon.joints[0].skin_index, 1);
+        assert_eq!(weapon.joints[1].skin_index, 0);
+
+        // Only 'Blade' (the first joint of the skin) bends, around its origin at (1, 1, 0)
+        let matrices = posed_matrices(weapon, independent, 0.5);
+        assert!(matrices[1].abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        assert!(!matrices[0].abs_diff_eq(Mat4::IDENTITY, 1e-3));
+        assert!(matrices[0]
+            .transform_point3(Vec3::new(1., 1., 0.))
+            .abs_diff_eq(Vec3::new(1., 1., 0.), 1e-5));
+
+        // The character is listed in the order of the hierarchy, only 'Chest' bends
+        let character = &mut skins[0];
+        let matrices = posed_matrices(character, independent, 0.5);
+        assert!(matrices[0].abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        assert!(!matrices[1].abs_diff_eq(Mat4::IDENTITY, 1e-3));
+        assert!(matrices[1]
+            .transform_point3(Vec3::new(-1., 1., 0.))
+            .abs_diff_eq(Vec3::new(-1., 1., 0.), 1e-5));
+    }
+
+    #[test]
+    fn mirrored_skin_determinant() {
+        // The mirror is on the only joint of the first skin and on a non-root joint of the second one
+        let (mut skins, _) = load_skins("resources/mirrored_instances/MirroredInstances.gltf");
+        assert_eq!(skins.len(), 2);
+        assert_eq!(skins[1].joints[0].name, "ChildMirrorBase");
+
+        for mirrored in &mut skins {
+            let mut world_transforms = Vec::new();
+            mirrored.world_transforms(Mat4::IDENTITY, None, &mut world_transforms);
+            let mut matrices = Vec::new();
+            mirrored.joint_matrices(&world_transforms, &mut matrices);
+            assert_eq!(mirrored.determinant(&matrices), -1.);
+        }
+
+        let (mut skins, animations) = load_skins("resources/two_skins/TwoSkins.gltf");
+        for joints in &mut skins {
+            let matrices = posed_matrices(joints, &animations.animations[0], 0.5);
+            assert_eq!(joints.determinant(&matrices), 1.);
+        }
+    }
+
+    #[test]
+    fn instances_animate_independently() {
+        let path = "resources/bare_skeleton/BareSkeleton.gltf";
+        let mut first = Model::from_gltf(path).unwrap();
+        let mut second = Model::from_gltf(path).unwrap();
+
+        first.play_animation("Bend").unwrap();
+        second.play_animation("Bend").unwrap();
+
+        first.animations.animations[0].step(0.25);
+        second.animations.animations[0].step(0.75);
+        assert_eq!(first.animations.animations[0].current_time, 0.25);
+        assert_eq!(second.animations.animations[0].current_time, 0.75);
+
+        let first_matrices = current_joint_matrices(&mut first);
+        let second_matrices = current_joint_matrices(&mut second);
+        assert_eq!(first_matrices.len(), 2);
+        assert_eq!(second_matrices.len(), 2);
+
+        // The root is rotated by 22.5 and 67.5 degrees, the tip follows it
+        let first_tip = first_matrices[1].transform_point3(Vec3::ZERO);
+        let second_tip = second_matrices[1].transform_point3(Vec3::ZERO);
+        assert!(!first_tip.abs_diff_eq(second_tip, 1e-3));
+
+        let angle = |tip: Vec3| (-tip.x).atan2(tip.y).to_degrees();
+        assert!((angle(first_tip) - 22.5).abs() < 1e-3);
+        assert!((angle(second_tip) - 67.5).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+/// Why a model couldn't be loaded, returned by `Model::from_gltf` and the other loading functions.
+///
+/// The kinds of the failures can be matched, `Display` describes them for the user.
+pub enum LoadError {
+    /// The file couldn't be read
+    Io { path: PathBuf, source: io::Error },
+    /// The file isn't a valid gltf / glb file
+    GltfParse { path: PathBuf, source: gltf::Error },
+    /// The file requires an extension that isn't supported (listed in 'extensionsRequired')
+    UnsupportedExtension { path: PathBuf, extension: String },
+    /// A buffer couldn't be loaded or is shorter than declared
+    MissingBuffer {
+        index: usize,
+        /// The file or the data URI of the buffer
+        resource: String,
+        reason: String,
+    },
+    /// The file doesn't contain exactly one scene
+    MultipleScenes { count: usize },
+    /// The node requested by `ImportOptions::root_node` isn't in the scene
+    MissingNode { name: String },
+    /// The meshes, skins or animations contain invalid data
+    InvalidData(Box<dyn Error + Send + Sync>),
+}
+
+impl LoadError {
+    /// Sorts out the errors of the gltf crate, the unsupported required extensions get their own kind
+    pub(super) fn from_gltf(path: PathBuf, source: gltf::Error) -> Self {
+        if let gltf::Error::Validation(errors) = &source {
+            // The paths of the unsupported extensions look like 'extensionsRequired[0] = "KHR_draco_mesh_compression"'
+            let extension = errors.iter().find_map(|(path, error)| {
+                let path = path.as_str();
+                match (error, path.starts_with("extensionsRequired")) {
+                    (gltf::json::validation::Error::Unsupported, true) => {
+                        let value = path.split_once(" = ")?.1;
+                        Some(value.trim_matches('"').to_string())
+                    }
+                    _ => None,
+                }
+            });
+
+            if let Some(extension) = extension {
+                return LoadError::UnsupportedExtension { path, extension };
+            }
+        }
+
+        LoadError::GltfParse { path, source }
+    }
+
+    /// The errors of reading the meshes, skins and animations (the chain of the report is kept as the sources)
+    pub(super) fn invalid_data(report: eyre::Report) -> Self {
+        LoadError::InvalidData(report.into())
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => {
+                write!(f, "Couldn't read '{}': {source}", path.display())
+            }
+            LoadError::GltfParse { path, source } => {
+                write!(
+                    f,
+                    "Couldn't parse the gltf file '{}': {source}",
+                    path.display()
+                )
+            }
+            LoadError::UnsupportedExtension { path, extension } => write!(
+                f,
+                "'{}' requires the unsupported extension '{extension}'",
+                path.display()
+            ),
+            LoadError::MissingBuffer {
+                index,
+                resource,
+                reason,
+            } => write!(f, "Couldn't load buffer {index} ({resource}): {reason}"),
+            LoadError::MultipleScenes { count } => {
+                write!(
+                    f,
+                    "GLTF file contains {count} scenes, exactly 1 is supported"
+                )
+            }
+            LoadError::MissingNode { name } => {
+                write!(f, "The scene doesn't contain a node named '{name}'")
+            }
+            LoadError::InvalidData(source) => write!(f, "Invalid model data: {source}"),
+        }
+    }
+}
+
+/// The messages already contain the causes, only the invalid data prints the whole chain of its sources
+impl fmt::Debug for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")?;
+
+        if let LoadError::InvalidData(source) = self {
+            let mut cause = source.source();
+            while let Some(error) = cause {
+                write!(f, "\n    Caused by: {error}")?;
+                cause = error.source();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::GltfParse { source, .. } => Some(source),
+            LoadError::InvalidData(source) => source.source(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::eyre;
+
+    use super::*;
+
+    #[test]
+    fn invalid_data_keeps_the_chain() {
+        let report = eyre!("accessor 3 is out of bounds").wrap_err("couldn't read mesh 'Box'");
+        let error = LoadError::invalid_data(report);
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid model data: couldn't read mesh 'Box'"
+        );
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "accessor 3 is out of bounds"
+        );
+        assert_eq!(
+            format!("{error:?}"),
+            "Invalid model data: couldn't read mesh 'Box'\n    Caused by: accessor 3 is out of bounds"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use glam::{Mat4, Quat, Vec3};
+
+use super::Transform;
+
+/// A constraint rotating a joint (e.g. the head) to face a target, applied after the animations
+#[derive(Clone)]
+pub struct LookAt {
+    /// Name of the constrained joint
+    pub joint: String,
+    /// The position in the world the joint looks at
+    pub target: Vec3,
+    /// If the target follows the position of the camera (the renderer updates `target`)
+    pub track_camera: bool,
+    /// Blend between the animated rotation (0) and the constrained rotation (1)
+    pub weight: f32,
+    /// Axis of the joint pointing forward (the direction of the face)
+    pub aim_axis: JointAxis,
+    /// Axis of the joint kept pointing up (towards +Y of the world), prevents the rolling of the head
+    pub up_axis: JointAxis,
+    /// Maximum angle between the animated and the constrained direction (in radians), None is unlimited
+    pub max_angle: Option<f32>,
+}
+
+impl LookAt {
+    pub fn new(joint: String) -> Self {
+        Self {
+            joint,
+            target: Vec3::new(0., 1.5, 3.),
+            track_camera: true,
+            weight: 1.,
+            aim_axis: JointAxis::Z,
+            up_axis: JointAxis::Y,
+            max_angle: Some(60f32.to_radians()),
+        }
+    }
+
+    /// Computes the local transform of the joint rotated towards the target.
+    ///
+    /// `local` is the animated transform of the joint, `parent_world` is the world transform of its parent.
+    /// The parents are expected to be scaled uniformly.
+    pub fn apply(&self, local: Transform, parent_world: Mat4) -> Transform {
+        let (_, world_rot, world_pos) =
+            (parent_world * local.matrix()).to_scale_rotation_translation();
+
+        let to_target = self.target - world_pos;
+        if self.weight <= 0. || to_target.length_squared() < 1e-8 {
+            return local;
+        }
+
+        // Swing the aim axis towards the target, limited to the cone around the animated direction
+        let aim = world_rot * self.aim_axis.vec3();
+        let mut swing = Quat::from_rotation_arc(aim, to_target.normalize());
+        if let Some(max_angle) = self.max_angle {
+            let (axis, angle) = swing.to_axis_angle();
+            if angle > max_angle {
+                swing = Quat::from_axis_angle(axis, max_angle);
+            }
+        }
+
+        // Roll around the new aim direction, so the up axis of the joint is as close to the world up as possible
+        let aimed_rot = swing * world_rot;
+        let dir = aimed_rot * self.aim_axis.vec3();
+        let up = aimed_rot * self.up_axis.vec3();
+
+        let reject = |v: Vec3| (v - dir * v.dot(dir)).normalize_or_zero();
+        let (up, world_up) = (reject(up), reject(Vec3::Y));
+
+        let roll = match up == Vec3::ZERO || world_up == Vec3::ZERO {
+            // The up axis is parallel to the aim axis, or the joint looks straight up / down
+            true => Quat::IDENTITY,
+            false => {
+                let angle = dir.dot(up.cross(world_up)).atan2(up.dot(world_up));
+                Quat::from_axis_angle(dir, angle)
+            }
+        };
+
+        let world_delta = Quat::IDENTITY.slerp(roll * swing, self.weight.min(1.));
+
+        // The rotation applied in the world expressed in the space of the parent
+        let (_, parent_rot, _) = parent_world.to_scale_rotation_translation();
+        let local_delta = parent_rot.inverse() * world_delta * parent_rot;
+
+        Transform {
+            rotation: (local_delta * local.rotation).normalize(),
+            ..local
+        }
+    }
+}
+
+/// A signed axis of the local space of a joint
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JointAxis {
+    X,
+    NegX,
+    Y,
+    NegY,
+    Z,
+    NegZ,
+}
+
+impl JointAxis {
+    pub const ALL: [JointAxis; 6] = [
+        JointAxis::X,
+        JointAxis::NegX,
+        JointAxis::Y,
+        JointAxis::NegY,
+        JointAxis::Z,
+        JointAxis::NegZ,
+    ];
+
+    pub fn vec3(self) -> Vec3 {
+        match self {
+            JointAxis::X => Vec3::X,
+            JointAxis::NegX => -Vec3::X,
+            JointAxis::Y => Vec3::Y,
+            JointAxis::NegY => -Vec3::Y,
+            JointAxis::Z => Vec3::Z,
+            JointAxis::NegZ => -Vec3::Z,
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use glam::{Vec3, Vec4};
+use gltf::{accessor::Iter, animation::Interpolation, json::Value};
+
+use super::DataBundle;
+
+/// An animation channel of the KHR_animation_pointer extension.
+///
+/// The gltf crate can't deserialize these channels (they don't target a node),
+/// so they are removed from the JSON before it's parsed and resolved afterwards.
+pub struct PointerChannel {
+    /// Index of the animation containing the channel
+    pub animation: usize,
+    /// Index of the sampler inside of the animation
+    pub sampler: usize,
+    /// JSON pointer to the animated property
+    pub pointer: String,
+}
+
+/// Removes all KHR_animation_pointer channels from the gltf JSON and returns them
+pub fn strip_pointer_channels(json: &mut Value) -> Vec<PointerChannel> {
+    let mut pointer_channels = Vec::new();
+
+    let animations = match json.get_mut("animations").and_then(Value::as_array_mut) {
+        Some(animations) => animations,
+        None => return pointer_channels,
+    };
+
+    for (animation_index, animation) in animations.iter_mut().enumerate() {
+        let channels = match animation.get_mut("channels").and_then(Value::as_array_mut) {
+            Some(channels) => channels,
+            None => continue,
+        };
+
+        channels.retain(|channel| {
+            let target = &channel["target"];
+            if target["path"] != "pointer" {
+                return true;
+            }
+
+            let pointer = target["extensions"]["KHR_animation_pointer"]["pointer"].as_str();
+            let sampler = channel["sampler"].as_u64();
+
+            match (pointer, sampler) {
+                (Some(pointer), Some(sampler)) => pointer_channels.push(PointerChannel {
+                    animation: animation_index,
+                    sampler: sampler as usize,
+                    pointer: pointer.to_string(),
+                }),
+                _ => eprintln!(
+                    "WARN: animation {animation_index} contains an invalid KHR_animation_pointer channel"
+                ),
+            }
+
+            false
+        });
+    }
+
+    pointer_channels
+}
+
+/// A material property that can be animated
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MaterialProperty {
+    BaseColorFactor,
+    EmissiveFactor,
+}
+
+impl MaterialProperty {
+    /// Parses the JSON pointer of the property, returns the index of the material with the property.
+    ///
+    /// Returns None for unsupported properties.
+    fn from_pointer(pointer: &str) -> Option<(usize, Self)> {
+        let rest = pointer.strip_prefix("/materials/")?;
+        let (material, path) = rest.split_once('/')?;
+        let material = material.parse().ok()?;
+
+        let property = match path {
+            "pbrMetallicRoughness/baseColorFactor" => MaterialProperty::BaseColorFactor,
+            "emissiveFactor" => MaterialProperty::EmissiveFactor,
+            _ => return None,
+        };
+
+        Some((material, property))
+    }
+
+    /// Number of components of the property values
+    fn components(self) -> usize {
+        match self {
+            MaterialProperty::BaseColorFactor => 4,
+            MaterialProperty::EmissiveFactor => 3,
+        }
+    }
+}
+
+/// An animation channel targeting a property of a material
+pub struct MaterialChannel {
+    /// Index of the gltf material
+    pub material: usize,
+    pub property: MaterialProperty,
+    /// Times of the keyframes
+    pub keyframe_times: Vec<f32>,
+    /// Values of the property (emissive factors have a W component of 0)
+    pub values: Vec<Vec4>,
+    /// Linear or step interpolation
+    pub interpolation: Interpolation,
+}
+
+impl MaterialChannel {
+    /// Resolves the material channels of the animation.
+    ///
+    /// Channels with unsupported pointers, interpolations or data are skipped with a warning.
+    pub fn from_gltf(animation: &gltf::Animation, bundle: &DataBundle) -> Vec<MaterialChannel> {
+        let mut channels = Vec::new();
+
+        for pointer_channel in &bundle.pointer_channels {
+            if pointer_channel.animation != animation.index() {
+                continue;
+            }
+
+            let pointer = &pointer_channel.pointer;
+            let warn = |reason: &str| {
+                eprintln!(
+                    "WARN: ignoring the animation pointer '{pointer}' of animation '{}': {reason}",
+                    animation.name().unwrap_or("N/A")
+                );
+            };
+
+            let (material, property) = match MaterialProperty::from_pointer(pointer) {
+                Some(target) => target,
+                None => {
+                    warn("unsupported target");
+                    continue;
+                }
+            };
+
+            let sampler = match animation.samplers().nth(pointer_channel.sampler) {
+                Some(sampler) => sampler,
+                None => {
+                    warn("the sampler doesn't exist");
+                    continue;
+                }
+            };
+
+            let interpolation = sampler.interpolation();
+            if interpolation == Interpolation::CubicSpline {
+                warn("cubic spline interpolation isn't supported");
+                continue;
+            }
+
+            let get_buffer_data = |buf: gltf::Buffer| Some(&*bundle.buffers[buf.index()]);
+
+            let keyframe_times: Vec<f32> = match Iter::<f32>::new(sampler.input(), get_buffer_data)
+            {
+                Some(times) => times.collect(),
+                None => {
+                    warn("the keyframe times can't be read");
+                    continue;
+                }
+            };
+
+            let output = sampler.output();
+            let values: Option<Vec<Vec4>> = match property.components() {
+                4 if output.dimensions() == gltf::accessor::Dimensions::Vec4 => {
+                    Iter::<[f32; 4]>::new(output, get_buffer_data)
+                        .map(|values| values.map(Vec4::from).collect())
+                }
+                3 if output.dimensions() == gltf::accessor::Dimensions::Vec3 => {
+                    Iter::<[f32; 3]>::new(output, get_buffer_data)
+                        .map(|values| values.map(|v| Vec3::from(v).extend(0.)).collect())
+                }
+                _ => None,
+            };
+
+            let values = match values {
+                Some(values) if values.len() == keyframe_times.len() && !values.is_empty() => {
+                    values
+                }
+                _ => {
+                    warn("the values can't be read or don't match the keyframe times");
+                    continue;
+                }
+            };
+
+            channels.push(MaterialChannel {
+                material,
+                property,
+                keyframe_times,
+                values,
+                interpolation,
+            });
+        }
+
+        channels
+    }
+
+    /// The value of the property at a specific time
+    #[allow(unused)]
+    pub fn sample(&self, time: f32) -> Vec4 {
+        self.sample_with(time, self.interpolation)
+    }
+
+    /// The value of the property at a specific time with the interpolation (cubic splines are sampled linearly)
+    pub fn sample_with(&self, time: f32, interpolation: Interpolation) -> Vec4 {
+        let times = &self.keyframe_times;
+
+        // Index of the first keyframe after 'time'
+        let next = times.partition_point(|t| *t <= time);
+        if next == 0 {
+            return self.values[0];
+        }
+        if next == times.len() {
+            return self.values[times.len() - 1];
+        }
+
+        let (start, end) = (self.values[next - 1], self.values[next]);
+        match interpolation {
+            Interpolation::Step => start,
+            _ => {
+                let coeff = (time - times[next - 1]) / (times[next] - times[next - 1]);
+                start.lerp(end, coeff)
+            }
+        }
+    }
+}
@@ -5,13 +5,17 @@ use gl::types::GLenum;
 use glam::{Vec2, Vec3, Vec4};
 use gltf::{
     image::Format,
-    mesh::util::ReadIndices,
+    material::AlphaMode,
+    mesh::{util::ReadIndices, Mode},
     texture::{MagFilter, MinFilter, WrappingMode},
 };
 
 use crate::ogl;
 
-use super::DataBundle;
+use super::{
+    raycast::{ray_aabb, ray_triangle},
+    Aabb, DataBundle, Joint, Validation,
+};
 
 /// Gltf terminology is needlessly confusing.
 /// A gltf 'Mesh' contains multiple real sub-meshes (called Primitives in the gltf parlance)
@@ -24,18 +28,41 @@ pub struct Mesh {
 }
 
 impl Mesh {
-    /// Create a mesh from the gltf::Mesh struct and the DataBundle
+    /// Computes the bounds of the skinned primitives around the joints of the skin
+    pub fn compute_joint_radii(&mut self, joints: &[Joint]) {
+        for primitive in &mut self.primitives {
+            if let Some(skin) = &mut primitive.skin {
+                skin.compute_joint_radii(&primitive.positions, joints);
+            }
+        }
+    }
+
+    /// Create a mesh from the gltf::Mesh struct and the DataBundle.
+    ///
+    /// Meshes without primitives (used as locators) create an empty mesh that isn't drawn.
     pub fn from_gltf(mesh: &gltf::Mesh, bundle: &mut DataBundle) -> Result<Self> {
         let name = mesh.name().map(|n| n.to_owned());
 
+        let mesh_name = name.as_deref().unwrap_or("N/A");
+
         let mut primitives = Vec::new();
         for primitive in mesh.primitives() {
-            let primitive = Primitive::from_gltf(&primitive, bundle)?;
-            primitives.push(primitive);
+            if let Some(primitive) = Primitive::from_gltf(&primitive, mesh_name, bundle)? {
+                primitives.push(primitive);
+            }
         }
 
+        bundle.mesh_loaded();
+
         Ok(Mesh { primitives, name })
     }
+
+    /// Drops the CPU copies of the vertex data of all primitives (see `Primitive::release_vertex_data`)
+    pub fn release_vertex_data(&mut self) {
+        for primitive in &mut self.primitives {
+            primitive.release_vertex_data();
+        }
+    }
 }
 
 /// A Primitive represents a single 'mesh' in the normal meaning of that word
@@ -46,44 +73,130 @@ impl Mesh {
 pub struct Primitive {
     /// A texture (if any) of this mesh
     pub texture_info: PrimitiveTexture,
+    /// Metallic-roughness and occlusion parameters of the material
+    pub pbr: PbrMaterial,
     /// OpenGL VAO identifier
     pub vao: u32,
-    /// Vertex indices
-    pub indices: Indices,
+    /// OpenGL primitive type used for drawing (GL_TRIANGLES, GL_LINES...)
+    pub mode: GLenum,
+    /// Vertex indices (the primitive is drawn with glDrawArrays if there are none)
+    pub indices: Option<Indices>,
     /// Vertex positions
     pub positions: Vec<Vec3>,
     /// Vertex texture coordinates
     pub texcoords: Vec<Vec2>,
-    /// Vertex normals
+    /// Vertex colors (COLOR_0) in linear space, white if the primitive doesn't have them
+    pub colors: Vec<Vec4>,
+    /// Vertex normals (depending on the normal mode)
     pub normals: Vec<Vec3>,
+    /// Vertex normals stored in the file, None if the file doesn't contain them
+    file_normals: Option<Vec<Vec3>>,
+    /// How the normals used for rendering are obtained
+    pub normal_mode: NormalMode,
+    /// OpenGL buffer of the normals
+    normals_buffer: u32,
+    /// VAO and the vertex count of the de-indexed vertices with flat normals,
+    /// created when flat normals are used for the first time
+    flat_vao: Option<(u32, usize)>,
     /// Vertex skin data (joints indices, weights)
     pub skin: Option<PrimSkin>,
+    /// Bounding box of the vertex positions
+    pub aabb: Aabb,
+    /// Number of the vertices in the file
+    pub vertex_count: usize,
+    /// Number of the drawn triangles (strips and fans are converted to lists), 0 for points and lines
+    pub triangle_count: usize,
+    /// Number of the drawn indices, or vertices if the primitive isn't indexed
+    draw_count: usize,
+    /// OpenGL type of the indices, None if the primitive isn't indexed
+    index_type: Option<GLenum>,
+    /// If the CPU copies of the vertex data are kept after the upload
+    has_vertex_data: bool,
+}
+
+/// Intersection of a ray with a triangle of a primitive, in the space of the primitive
+pub struct TriangleHit {
+    /// Index of the triangle
+    pub triangle: usize,
+    /// Weights of the vertices of the triangle at the hit point
+    pub barycentric: Vec3,
+    /// Distance of the hit point from the origin of the ray, in the lengths of the direction
+    pub distance: f32,
 }
 
 impl Primitive {
-    /// Creates the primitive from the gltf::Primitive struct and the DataBundle
-    pub fn from_gltf(primitive: &gltf::Primitive, bundle: &mut DataBundle) -> Result<Self> {
-        let mode = primitive.mode();
+    /// Creates the primitive from the gltf::Primitive struct and the DataBundle.
+    ///
+    /// Returns None if the primitive was skipped because of invalid vertex data.
+    pub fn from_gltf(
+        primitive: &gltf::Primitive,
+        mesh_name: &str,
+        bundle: &mut DataBundle,
+    ) -> Result<Option<Self>> {
+        let mut prim = match Self::read_gltf(primitive, mesh_name, bundle)? {
+            Some(prim) => prim,
+            None => return Ok(None),
+        };
+
+        prim.create_buffers(&primitive.material(), bundle);
 
-        if mode != gltf::mesh::Mode::Triangles {
-            return Err(eyre!("primitive mode: '{mode:?}' is not impelemnted"));
+        if prim.vao == 0 {
+            return Err(eyre!("primitive VAO wasn't correctly initialized"));
         }
 
+        Ok(Some(prim))
+    }
+
+    /// Reads the vertex data of the primitive, the OpenGL buffers and textures aren't created yet.
+    ///
+    /// Returns None if the primitive was skipped because of invalid vertex data.
+    fn read_gltf(
+        primitive: &gltf::Primitive,
+        mesh_name: &str,
+        bundle: &DataBundle,
+    ) -> Result<Option<Self>> {
+        // The reader honors the byteOffset of the accessors and the byteStride of the buffer views,
+        // so interleaved attributes are copied into tightly packed vectors (uploaded with stride 0)
         let reader = primitive.reader(|buffer| Some(&bundle.buffers[buffer.index()]));
 
-        let positions = reader
+        let handedness = bundle.options.handedness;
+
+        let positions: Vec<Vec3> = reader
             .read_positions()
             .ok_or(eyre!("primitive doesn't containt positions"))?
-            .map(Vec3::from)
+            .map(|p| handedness.mirror.vec3(Vec3::from(p)))
             .collect();
 
-        let indices = match reader
-            .read_indices()
-            .ok_or(eyre!("primitive doesn't containt indices"))?
-        {
+        let indices = reader.read_indices().map(|indices| match indices {
             ReadIndices::U32(b) => Indices::U32(b.collect()),
             ReadIndices::U16(b) => Indices::U16(b.collect()),
             ReadIndices::U8(b) => Indices::U8(b.collect()),
+        });
+
+        // Triangle strips and fans are converted to triangle lists, so that they can be handled like regular triangles
+        let vertex_count = positions.len();
+        let (mode, indices) = match primitive.mode() {
+            Mode::Points => (gl::POINTS, indices),
+            Mode::Lines => (gl::LINES, indices),
+            Mode::LineLoop => (gl::LINE_LOOP, indices),
+            Mode::LineStrip => (gl::LINE_STRIP, indices),
+            Mode::Triangles => (gl::TRIANGLES, indices),
+            Mode::TriangleStrip => {
+                let indices = indices.unwrap_or_else(|| Indices::sequential(vertex_count));
+                (gl::TRIANGLES, Some(indices.strip_to_triangles()))
+            }
+            Mode::TriangleFan => {
+                let indices = indices.unwrap_or_else(|| Indices::sequential(vertex_count));
+                (gl::TRIANGLES, Some(indices.fan_to_triangles()))
+            }
+        };
+
+        let indices = match handedness.reverses_winding() && mode == gl::TRIANGLES {
+            true => {
+                let indices = indices.unwrap_or_else(|| Indices::sequential(vertex_count));
+                Some(indices.reverse_winding())
+            }
+            false => indices,
         };
 
         let mut texcoords = Vec::new();
@@ -100,21 +213,48 @@ impl Primitive {
             texture_set += 1;
         }
 
-        let normals = reader
-            .read_normals()
-            .ok_or(eyre!("primitive doesn't containt normals"))?
-            .map(Vec3::from)
-            .collect();
+        // The glTF vertex colors are linear, the default color doesn't change the base color
+        let colors: Vec<Vec4> = match reader.read_colors(0) {
+            Some(colors) => colors.into_rgba_f32().map(Vec4::from).collect(),
+            None => vec![Vec4::ONE; positions.len()],
+        };
+
+        // Points and lines usually don't have normals, missing normals of triangles are generated
+        let normal_sign = if handedness.flip_normals { -1. } else { 1. };
+        let file_normals: Option<Vec<Vec3>> = reader.read_normals().map(|normals| {
+            normals
+                .map(|n| handedness.mirror.vec3(Vec3::from(n)) * normal_sign)
+                .collect()
+        });
+        let (normals, normal_mode) = match &file_normals {
+            Some(normals) => (normals.clone(), NormalMode::File),
+            None if mode != gl::TRIANGLES => (vec![Vec3::ZERO; positions.len()], NormalMode::File),
+            None => {
+                let triangles = triangle_indices(indices.as_ref(), positions.len());
+                (smooth_normals(&positions, &triangles), NormalMode::Smooth)
+            }
+        };
+
+        if bundle.options.validation != Validation::Off {
+            let invalid_position = positions.iter().position(|p| !p.is_finite());
+            let invalid_normal = normals.iter().position(|n| !n.is_finite());
+
+            if let Some(index) = invalid_position.or(invalid_normal) {
+                eprintln!(
+                    "WARN: primitive {} of mesh '{mesh_name}' contains a non-finite vertex position or normal (vertex {index})",
+                    primitive.index()
+                );
+
+                if bundle.options.validation == Validation::Skip {
+                    return Ok(None);
+                }
+            }
+        }
 
         let skin = match (reader.read_joints(0), reader.read_weights(0)) {
             (Some(joints), Some(weights)) => {
                 let joints = joints.into_u16().map(|j| j.map(|ji| ji as u32)).collect();
-                // TODO: u8 / u16 joint weights normalization
-                match weights {
-                    gltf::mesh::util::ReadWeights::U8(_) => todo!("U8 weights"),
-                    gltf::mesh::util::ReadWeights::U16(_) => todo!("U16 weights"),
-                    _ => {}
-                }
+                // Normalized u8 / u16 weights are decoded to [0, 1]
                 let weights = weights.into_f32().collect();
 
                 Some(PrimSkin::new(joints, weights))
@@ -122,27 +262,38 @@ impl Primitive {
             _ => None,
         };
 
-        let material = primitive.material();
+        let triangle_count = match mode {
+            gl::TRIANGLES => indices.as_ref().map_or(vertex_count, |i| i.len()) / 3,
+            _ => 0,
+        };
 
-        let mut primitive = Self {
+        let draw_count = indices.as_ref().map_or(vertex_count, |i| i.len());
+        let index_type = indices.as_ref().map(|i| i.gl_type());
+
+        Ok(Some(Self {
             vao: 0,
+            mode,
+            aabb: Aabb::from_points(&positions),
+            vertex_count,
+            triangle_count,
+            draw_count,
+            index_type,
+            has_vertex_data: true,
             texture_info: PrimitiveTexture::None {
                 base_color_factor: Vec4::splat(1.),
             },
+            pbr: PbrMaterial::default(),
             indices,
             positions,
             texcoords,
+            colors,
             normals,
+            file_normals,
+            normal_mode,
+            normals_buffer: 0,
+            flat_vao: None,
             skin,
-        };
-
-        primitive.create_buffers(&material, bundle);
-
-        if primitive.vao == 0 {
-            return Err(eyre!("primitive VAO wasn't correctly initialized"));
-        }
-
-        Ok(primitive)
+        }))
     }
 
     /// Creates the OpenGL buffer from the loaded vertex data
@@ -157,7 +308,9 @@ impl Primitive {
             let _positions = ogl::create_float_buf(&self.positions, 3, ogl::POS_INDEX, gl::FLOAT);
             let _texcoords =
                 ogl::create_float_buf(&self.texcoords, 2, ogl::TEXCOORDS_INDEX, gl::FLOAT);
-            let _normals = ogl::create_float_buf(&self.normals, 3, ogl::NORMALS_INDEX, gl::FLOAT);
+            self.normals_buffer =
+                ogl::create_float_buf(&self.normals, 3, ogl::NORMALS_INDEX, gl::FLOAT);
+            let _colors = ogl::create_float_buf(&self.colors, 4, ogl::COLORS_INDEX, gl::FLOAT);
 
             if let Some(skin) = &self.skin {
                 let _joints =
@@ -167,16 +320,19 @@ impl Primitive {
             }
 
             // Indices
-            gl::GenBuffers(1, &mut indices);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, indices);
-
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                self.indices.size() as isize,
-                self.indices.ptr(),
-                gl::STATIC_DRAW,
-            );
+            if let Some(indices_buf) = &self.indices {
+                gl::GenBuffers(1, &mut indices);
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, indices);
+
+                gl::BufferData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    indices_buf.size() as isize,
+                    indices_buf.ptr(),
+                    gl::STATIC_DRAW,
+                );
+            }
 
+            // Color textures are sRGB encoded, the other textures contain linear data
             let pbr = material.pbr_metallic_roughness();
             let texture_index = match pbr.base_color_texture() {
                 Some(tex_info) => {
@@ -188,6 +344,35 @@ impl Primitive {
                 }
             };
 
+            let metallic_roughness_texture = pbr.metallic_roughness_texture().map(|tex_info| {
+                self.create_gl_texture(&tex_info.texture(), ColorSpace::Linear, bundle)
+            });
+            let occlusion = material.occlusion_texture();
+            let occlusion_texture = occlusion.as_ref().map(|tex_info| {
+                self.create_gl_texture(&tex_info.texture(), ColorSpace::Linear, bundle)
+            });
+            let normal = material.normal_texture();
+            let normal_texture = normal.as_ref().map(|tex_info| {
+                self.create_gl_texture(&tex_info.texture(), ColorSpace::Linear, bundle)
+            });
+
+            self.pbr = PbrMaterial {
+                material: material.index(),
+                metallic_factor: pbr.metallic_factor(),
+                roughness_factor: pbr.roughness_factor(),
+                metallic_roughness_texture,
+                occlusion_texture,
+                occlusion_strength: occlusion.map(|o| o.strength()).unwrap_or(1.),
+                normal_texture,
+                normal_scale: normal.map(|n| n.scale()).unwrap_or(1.),
+                emissive_factor: Vec3::from(material.emissive_factor()),
+                unlit: material.unlit(),
+                alpha_cutoff: match material.alpha_mode() {
+                    AlphaMode::Mask => Some(material.alpha_cutoff().unwrap_or(0.5)),
+                    AlphaMode::Opaque | AlphaMode::Blend => None,
+                },
+            };
+
             // Unbind buffers
             gl::BindVertexArray(0);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
@@ -199,19 +384,215 @@ impl Primitive {
         }
     }
 
-    /// Creates a new OpenGL texture.
+    /// Switches the normals used for rendering.
+    ///
+    /// Primitives without normals in the file use smooth normals instead of the file ones.
+    /// Only triangles with the CPU copies of the vertex data are affected.
+    pub fn set_normal_mode(&mut self, mode: NormalMode) {
+        if self.mode != gl::TRIANGLES || !self.has_vertex_data {
+            return;
+        }
+
+        let mode = match (mode, &self.file_normals) {
+            (NormalMode::File, None) => NormalMode::Smooth,
+            _ => mode,
+        };
+
+        if mode == self.normal_mode {
+            return;
+        }
+
+        match mode {
+            NormalMode::File => {
+                if let Some(file_normals) = &self.file_normals {
+                    self.normals = file_normals.clone();
+                    self.upload_normals();
+                }
+            }
+            NormalMode::Smooth => {
+                let triangles = triangle_indices(self.indices.as_ref(), self.positions.len());
+                self.normals = smooth_normals(&self.positions, &triangles);
+                self.upload_normals();
+            }
+            NormalMode::Flat => {
+                if self.flat_vao.is_none() {
+                    self.flat_vao = Some(self.create_flat_vao());
+                }
+            }
+        }
+
+        self.normal_mode = mode;
+    }
+
+    /// Drops the CPU copies of the vertex data, only the GPU buffers are kept.
+    ///
+    /// The normal mode can't be changed and the primitive can't be raycast afterwards.
+    pub fn release_vertex_data(&mut self) {
+        self.positions = Vec::new();
+        self.indices = None;
+        self.texcoords = Vec::new();
+        self.colors = Vec::new();
+        self.normals = Vec::new();
+        self.file_normals = None;
+        if let Some(skin) = &mut self.skin {
+            skin.joints = Vec::new();
+            skin.weights = Vec::new();
+        }
+
+        self.has_vertex_data = false;
+    }
+
+    /// If the CPU copies of the vertex data are kept (see `ImportOptions::keep_vertex_data`)
+    pub fn has_vertex_data(&self) -> bool {
+        self.has_vertex_data
+    }
+
+    /// Number of the drawn indices, or vertices if the primitive isn't indexed
+    pub fn draw_count(&self) -> usize {
+        self.draw_count
+    }
+
+    /// OpenGL type of the indices, None if the primitive isn't indexed
+    pub fn index_type(&self) -> Option<GLenum> {
+        self.index_type
+    }
+
+    /// The nearest triangle hit by the ray closer than `max_distance` (in the space of the primitive).
     ///
-    /// If the texture already exists (bundle.gl_textures\[texture_index\] == Some(...)),
-    /// no new texture is created, only the Texture struct is cloned.
+    /// Skinned primitives are tested in the bind pose. Points, lines and the primitives
+    /// without the CPU copies of the vertex data are never hit.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<TriangleHit> {
+        if self.mode != gl::TRIANGLES || !self.has_vertex_data {
+            return None;
+        }
+
+        match ray_aabb(origin, dir, &self.aabb) {
+            Some(distance) if distance <= max_distance => {}
+            _ => return None,
+        }
+
+        let triangles = triangle_indices(self.indices.as_ref(), self.positions.len());
+
+        let mut nearest: Option<TriangleHit> = None;
+        for (i, triangle) in triangles.chunks_exact(3).enumerate() {
+            let vertices = [0, 1, 2].map(|v| self.positions[triangle[v]]);
+            let max_distance = nearest.as_ref().map_or(max_distance, |hit| hit.distance);
+
+            if let Some((distance, barycentric)) = ray_triangle(origin, dir, vertices) {
+                if distance <= max_distance {
+                    nearest = Some(TriangleHit {
+                        triangle: i,
+                        barycentric,
+                        distance,
+                    });
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The VAO and the vertex count of the de-indexed vertices if flat normals are used
+    pub fn flat_vao(&self) -> Option<(u32, usize)> {
+        match self.normal_mode {
+            NormalMode::Flat => self.flat_vao,
+            _ => None,
+        }
+    }
+
+    /// Overwrites the normals buffer with the current normals
+    fn upload_normals(&self) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.normals_buffer);
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                (self.normals.len() * size_of::<Vec3>()) as isize,
+                self.normals.as_ptr() as _,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    /// Creates a VAO where every triangle has its own vertices with the normal of the triangle
+    fn create_flat_vao(&self) -> (u32, usize) {
+        let triangles = triangle_indices(self.indices.as_ref(), self.positions.len());
+
+        let mut positions = Vec::with_capacity(triangles.len());
+        let mut normals = Vec::with_capacity(triangles.len());
+        for triangle in triangles.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.positions[triangle[i]]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+
+            positions.extend_from_slice(&[a, b, c]);
+            normals.extend_from_slice(&[normal; 3]);
+        }
+
+        // Missing attributes (empty texture coordinates) stay empty
+        fn de_index<T: Copy>(values: &[T], triangles: &[usize]) -> Vec<T> {
+            match values.is_empty() {
+                true => Vec::new(),
+                false => triangles.iter().map(|i| values[*i]).collect(),
+            }
+        }
+
+        let mut vao = 0;
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            let texcoords: Vec<Vec2> = de_index(&self.texcoords, &triangles);
+            let colors: Vec<Vec4> = de_index(&self.colors, &triangles);
+
+            ogl::create_float_buf(&positions, 3, ogl::POS_INDEX, gl::FLOAT);
+            ogl::create_float_buf(&texcoords, 2, ogl::TEXCOORDS_INDEX, gl::FLOAT);
+            ogl::create_float_buf(&normals, 3, ogl::NORMALS_INDEX, gl::FLOAT);
+            ogl::create_float_buf(&colors, 4, ogl::COLORS_INDEX, gl::FLOAT);
+
+            if let Some(skin) = &self.skin {
+                let joints: Vec<[u32; 4]> = triangles.iter().map(|i| skin.joints[*i]).collect();
+                let weights: Vec<[f32; 4]> = triangles.iter().map(|i| skin.weights[*i]).collect();
+
+                ogl::create_int_buf(&joints, 4, ogl::JOINTS_INDEX, gl::UNSIGNED_INT);
+                ogl::create_float_buf(&weights, 4, ogl::WEIGHTS_INDEX, gl::FLOAT);
+            }
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        (vao, positions.len())
+    }
+
+    /// Creates the base color texture info of the primitive
     fn create_texture(
         &mut self,
         tex: &gltf::Texture,
         base_color_factor: [f32; 4],
         bundle: &mut DataBundle,
     ) -> PrimitiveTexture {
+        PrimitiveTexture::Some {
+            gl_id: self.create_gl_texture(tex, ColorSpace::Srgb, bundle),
+            base_color_factor: Vec4::from(base_color_factor),
+        }
+    }
+
+    /// Creates a new OpenGL texture with the sampler settings and returns its id.
+    ///
+    /// The image data is uploaded when the model is rendered for the first time (see `PendingTexture`).
+    /// If the texture already exists in the color space (bundle.gl_textures\[texture_index\]),
+    /// no new texture is created, only the existing id is returned.
+    /// Images used both as color and data textures are uploaded twice.
+    fn create_gl_texture(
+        &mut self,
+        tex: &gltf::Texture,
+        color_space: ColorSpace,
+        bundle: &mut DataBundle,
+    ) -> u32 {
         let tex_index = tex.source().index();
-        if let Some(texture) = bundle.gl_textures[tex_index].clone() {
-            return texture;
+        if let Some(gl_id) = *bundle.gl_textures[tex_index].get_mut(color_space) {
+            return gl_id;
         }
 
         let gl_tex_id = unsafe {
@@ -222,40 +603,45 @@ impl Primitive {
 
             self.set_texture_sampler(&tex.sampler());
 
-            let image = &bundle.images[tex_index];
+            texture
+        };
 
-            assert!(image.width.is_power_of_two());
-            assert!(image.height.is_power_of_two());
+        *bundle.gl_textures[tex_index].get_mut(color_space) = Some(gl_tex_id);
+        gl_tex_id
+    }
 
-            let (internal_format, format) = match image.format {
-                Format::R8G8 => (gl::RG8, gl::RG),
-                Format::R8G8B8 => (gl::RGB8, gl::RGB),
-                Format::R8G8B8A8 => (gl::RGBA8, gl::RGBA),
-                f => unimplemented!("Unimplemented image format: '{f:?}'"),
+    /// The internal format, the pixel format and the pixel type of a decoded image.
+    ///
+    /// sRGB textures of greyscale images with alpha are expanded to RGBA before the upload
+    /// (the alpha in the G channel would be decoded as a color).
+    fn texture_format(format: Format, color_space: ColorSpace) -> (GLenum, GLenum, GLenum) {
+        if color_space == ColorSpace::Srgb {
+            return match format {
+                Format::R8 => (gl::SRGB8, gl::RED, gl::UNSIGNED_BYTE),
+                Format::R16 => (gl::SRGB8, gl::RED, gl::UNSIGNED_SHORT),
+                Format::R8G8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+                Format::R16G16 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_SHORT),
+                Format::R8G8B8 => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE),
+                Format::R16G16B16 => (gl::SRGB8, gl::RGB, gl::UNSIGNED_SHORT),
+                Format::R32G32B32FLOAT => (gl::SRGB8, gl::RGB, gl::FLOAT),
+                Format::R8G8B8A8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+                Format::R16G16B16A16 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_SHORT),
+                Format::R32G32B32A32FLOAT => (gl::SRGB8_ALPHA8, gl::RGBA, gl::FLOAT),
             };
+        }
 
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                internal_format as i32,
-                image.width as i32,
-                image.height as i32,
-                0,
-                format,
-                gl::UNSIGNED_BYTE,
-                image.pixels.as_ptr() as _,
-            );
-            gl::GenerateMipmap(gl::TEXTURE_2D);
-
-            texture
-        };
-
-        let texture = PrimitiveTexture::Some {
-            gl_id: gl_tex_id,
-            base_color_factor: Vec4::from(base_color_factor),
-        };
-        bundle.gl_textures[tex_index] = Some(texture.clone());
-        texture
+        match format {
+            Format::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+            Format::R8G8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+            Format::R8G8B8 => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
+            Format::R8G8B8A8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            Format::R16 => (gl::R16, gl::RED, gl::UNSIGNED_SHORT),
+            Format::R16G16 => (gl::RG16, gl::RG, gl::UNSIGNED_SHORT),
+            Format::R16G16B16 => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT),
+            Format::R16G16B16A16 => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+            Format::R32G32B32FLOAT => (gl::RGB32F, gl::RGB, gl::FLOAT),
+            Format::R32G32B32A32FLOAT => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+        }
     }
 
     /// Sets the appropriate sampler functions for the currently created texture.
@@ -304,9 +690,52 @@ impl Primitive {
     }
 }
 
+/// How the vertex normals of a primitive are obtained
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NormalMode {
+    /// The normals stored in the file
+    File,
+    /// Normals of the adjacent triangles averaged (weighted by the areas of the triangles)
+    Smooth,
+    /// Normals of the triangles (the vertices aren't shared by the triangles)
+    Flat,
+}
+
+impl NormalMode {
+    pub const ALL: [NormalMode; 3] = [NormalMode::File, NormalMode::Smooth, NormalMode::Flat];
+}
+
+/// Indices of the vertices of the triangles, sequential if the primitive isn't indexed
+fn triangle_indices(indices: Option<&Indices>, vertex_count: usize) -> Vec<usize> {
+    match indices {
+        Some(Indices::U32(buf)) => buf.iter().map(|i| *i as usize).collect(),
+        Some(Indices::U16(buf)) => buf.iter().map(|i| *i as usize).collect(),
+        Some(Indices::U8(buf)) => buf.iter().map(|i| *i as usize).collect(),
+        None => (0..vertex_count).collect(),
+    }
+}
+
+/// Averages the normals of the triangles sharing a vertex.
+///
+/// The cross product isn't normalized, so larger triangles have a larger influence.
+fn smooth_normals(positions: &[Vec3], triangles: &[usize]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+
+    for triangle in triangles.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i]]);
+        let normal = (b - a).cross(c - a);
+
+        for i in triangle {
+            normals[*i] += normal;
+        }
+    }
+
+    normals.iter().map(|n| n.normalize_or_zero()).collect()
+}
+
 /// Texture info for a primitive.
 ///
-/// If the primitive has a texture, copy the texture information from the Model's gl_textures.
+/// If the primitive has a texture, the texture id is shared through the DataBundle's gl_textures.
 ///
 /// If not, the base_color_factor serves as the object color.
 #[derive(Clone)]
@@ -315,15 +744,238 @@ pub enum PrimitiveTexture {
     Some { gl_id: u32, base_color_factor: Vec4 },
 }
 
+/// Metallic-roughness, occlusion and emission data of the material of a primitive
+#[derive(Clone)]
+pub struct PbrMaterial {
+    /// Index of the gltf material (targeted by material animations), None for the default material
+    pub material: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Texture with the roughness and the metallicness (see `OrmChannels`)
+    pub metallic_roughness_texture: Option<u32>,
+    /// Texture with the ambient occlusion (see `OrmChannels`)
+    pub occlusion_texture: Option<u32>,
+    /// How much the occlusion texture is applied (0 = no occlusion)
+    pub occlusion_strength: f32,
+    /// Tangent space normal texture
+    pub normal_texture: Option<u32>,
+    /// Scale of the X and Y components of the normal texture
+    pub normal_scale: f32,
+    /// Color of the light emitted by the material
+    pub emissive_factor: Vec3,
+    /// If the material uses KHR_materials_unlit (only the base color is used, without lighting)
+    pub unlit: bool,
+    /// Fragments with a lower alpha are discarded (the MASK alpha mode), None for the OPAQUE and BLEND modes
+    pub alpha_cutoff: Option<f32>,
+}
+
+impl Default for PbrMaterial {
+    /// The default values of the gltf specification
+    fn default() -> Self {
+        Self {
+            material: None,
+            metallic_factor: 1.,
+            roughness_factor: 1.,
+            metallic_roughness_texture: None,
+            occlusion_texture: None,
+            occlusion_strength: 1.,
+            normal_texture: None,
+            normal_scale: 1.,
+            emissive_factor: Vec3::ZERO,
+            unlit: false,
+            alpha_cutoff: None,
+        }
+    }
+}
+
+/// The channels of the occlusion and the metallic-roughness textures.
+///
+/// The gltf specification stores the occlusion in the R channel of the occlusion texture,
+/// the roughness in the G channel and the metallicness in the B channel of the metallic-roughness texture,
+/// so exporters commonly pack all three into one ORM texture used in both slots.
+/// The texture shader reads the channels through the defines from `shader_defines`,
+/// these constants are the only place the mapping is specified.
+pub struct OrmChannels;
+
+impl OrmChannels {
+    pub const OCCLUSION: usize = 0;
+    pub const ROUGHNESS: usize = 1;
+    pub const METALLIC: usize = 2;
+
+    /// The defines of the channel indices for the shaders
+    pub fn shader_defines() -> [(&'static str, usize); 3] {
+        [
+            ("OCCLUSION_CHANNEL", Self::OCCLUSION),
+            ("ROUGHNESS_CHANNEL", Self::ROUGHNESS),
+            ("METALLIC_CHANNEL", Self::METALLIC),
+        ]
+    }
+}
+
+/// How the texels of a texture are interpreted, depends on the material slot the texture is used in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorSpace {
+    /// Data textures (normal, metallic-roughness, occlusion) are sampled as they are stored
+    Linear,
+    /// Color textures (base color, emissive) are decoded from sRGB by the hardware when sampled
+    Srgb,
+}
+
+/// The OpenGL textures created from a single image, one for each color space the image is used in
+#[derive(Clone, Copy, Default)]
+pub struct ImageTextures {
+    pub linear: Option<u32>,
+    pub srgb: Option<u32>,
+}
+
+impl ImageTextures {
+    /// The texture of the color space
+    pub fn get_mut(&mut self, color_space: ColorSpace) -> &mut Option<u32> {
+        match color_space {
+            ColorSpace::Linear => &mut self.linear,
+            ColorSpace::Srgb => &mut self.srgb,
+        }
+    }
+}
+
+/// Image data of the textures that were created, but aren't uploaded to the GPU yet.
+///
+/// Uploading the images is deferred until the model is rendered for the first time,
+/// so that models which are never selected don't slow down the startup.
+pub struct PendingTexture {
+    pub textures: ImageTextures,
+    pub image: gltf::image::Data,
+}
+
+impl PendingTexture {
+    /// Uploads the image into the textures and generates the mipmaps
+    pub fn upload(&self) {
+        let textures = [
+            (self.textures.linear, ColorSpace::Linear),
+            (self.textures.srgb, ColorSpace::Srgb),
+        ];
+
+        for (gl_id, color_space) in textures {
+            if let Some(gl_id) = gl_id {
+                self.upload_into(gl_id, color_space);
+            }
+        }
+    }
+
+    /// Uploads the image into the texture of the color space
+    fn upload_into(&self, gl_id: u32, color_space: ColorSpace) {
+        let image = &self.image;
+        let (internal_format, format, typ) = Primitive::texture_format(image.format, color_space);
+
+        let expanded;
+        let pixels = match (image.format, format) {
+            (Format::R8G8 | Format::R16G16, gl::RGBA) => {
+                let channel_size = if typ == gl::UNSIGNED_SHORT { 2 } else { 1 };
+                expanded = expand_grey_alpha(&image.pixels, channel_size);
+                &expanded
+            }
+            _ => &image.pixels,
+        };
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, gl_id);
+
+            // Rows of 1, 2 and 3 channel images aren't necessarily aligned to 4 bytes
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as i32,
+                image.width as i32,
+                image.height as i32,
+                0,
+                format,
+                typ,
+                pixels.as_ptr() as _,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            // Greyscale images are sampled as (grey, grey, grey, alpha)
+            let swizzle = match format {
+                gl::RED => Some([gl::RED, gl::RED, gl::RED, gl::ONE]),
+                gl::RG => Some([gl::RED, gl::RED, gl::RED, gl::GREEN]),
+                _ => None,
+            };
+
+            if let Some(swizzle) = swizzle {
+                let swizzle = swizzle.map(|s| s as i32);
+                gl::TexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+            }
+
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+    }
+}
+
+/// Converts (grey, alpha) pixels to (grey, grey, grey, alpha), the channels have `channel_size` bytes
+fn expand_grey_alpha(pixels: &[u8], channel_size: usize) -> Vec<u8> {
+    let mut expanded = Vec::with_capacity(pixels.len() * 2);
+
+    for pixel in pixels.chunks_exact(channel_size * 2) {
+        let (grey, alpha) = pixel.split_at(channel_size);
+        for _ in 0..3 {
+            expanded.extend_from_slice(grey);
+        }
+        expanded.extend_from_slice(alpha);
+    }
+
+    expanded
+}
+
 /// Optional skin data for a primitive.
 pub struct PrimSkin {
     pub joints: Vec<[u32; 4]>,
     pub weights: Vec<[f32; 4]>,
+    /// Joints influencing the primitive and the distance of the farthest influenced vertex
+    /// from the joint (in the space of the joint in the bind pose)
+    pub joint_radii: Vec<(usize, f32)>,
 }
 
 impl PrimSkin {
     pub fn new(joints: Vec<[u32; 4]>, weights: Vec<[f32; 4]>) -> Self {
-        Self { joints, weights }
+        Self {
+            joints,
+            weights,
+            joint_radii: Vec::new(),
+        }
+    }
+
+    /// Computes the radii of the joints, which bound the skinned vertices in any pose.
+    ///
+    /// The radii are indexed by the order of the hierarchy, the vertices use the order of the skin.
+    fn compute_joint_radii(&mut self, positions: &[Vec3], joints: &[Joint]) {
+        let mut radii = vec![None; joints.len()];
+
+        let mut hierarchy_indices = vec![0; joints.len()];
+        for (i, joint) in joints.iter().enumerate() {
+            hierarchy_indices[joint.skin_index] = i;
+        }
+
+        for ((pos, vertex_joints), weights) in positions.iter().zip(&self.joints).zip(&self.weights)
+        {
+            for (joint, weight) in vertex_joints.iter().zip(weights) {
+                let joint = *joint as usize;
+                if *weight <= 0. || joint >= joints.len() {
+                    continue;
+                }
+                let joint = hierarchy_indices[joint];
+
+                let local_pos = joints[joint].inverse_bind_matrix.transform_point3(*pos);
+                let radius: &mut Option<f32> = &mut radii[joint];
+                *radius = Some(radius.unwrap_or(0.).max(local_pos.length()));
+            }
+        }
+
+        self.joint_radii = radii
+            .into_iter()
+            .enumerate()
+            .filter_map(|(joint, radius)| radius.map(|radius| (joint, radius)))
+            .collect();
     }
 }
 
@@ -337,6 +989,17 @@ pub enum Indices {
 }
 
 impl Indices {
+    /// Indices 0..count stored in the smallest type that can hold them
+    pub fn sequential(count: usize) -> Self {
+        if count <= u8::MAX as usize + 1 {
+            Indices::U8((0..count).map(|i| i as u8).collect())
+        } else if count <= u16::MAX as usize + 1 {
+            Indices::U16((0..count).map(|i| i as u16).collect())
+        } else {
+            Indices::U32((0..count).map(|i| i as u32).collect())
+        }
+    }
+
     /// The size (in bytes) of the buffer
     pub fn size(&self) -> usize {
         match self {
@@ -364,6 +1027,69 @@ impl Indices {
         }
     }
 
+    /// Converts the indices of a triangle strip to a triangle list.
+    ///
+    /// Every other triangle has its vertices swapped to keep a consistent winding order.
+    pub fn strip_to_triangles(self) -> Self {
+        fn convert<T: Copy>(strip: Vec<T>) -> Vec<T> {
+            let mut triangles = Vec::with_capacity(strip.len().saturating_sub(2) * 3);
+
+            for (i, w) in strip.windows(3).enumerate() {
+                if i % 2 == 0 {
+                    triangles.extend_from_slice(&[w[0], w[1], w[2]]);
+                } else {
+                    triangles.extend_from_slice(&[w[1], w[0], w[2]]);
+                }
+            }
+
+            triangles
+        }
+
+        match self {
+            Indices::U32(buf) => Indices::U32(convert(buf)),
+            Indices::U16(buf) => Indices::U16(convert(buf)),
+            Indices::U8(buf) => Indices::U8(convert(buf)),
+        }
+    }
+
+    /// Converts the indices of a triangle fan to a triangle list
+    pub fn fan_to_triangles(self) -> Self {
+        fn convert<T: Copy>(fan: Vec<T>) -> Vec<T> {
+            let mut triangles = Vec::with_capacity(fan.len().saturating_sub(2) * 3);
+
+            if let Some((&center, rest)) = fan.split_first() {
+                for w in rest.windows(2) {
+                    triangles.extend_from_slice(&[center, w[0], w[1]]);
+                }
+            }
+
+            triangles
+        }
+
+        match self {
+            Indices::U32(buf) => Indices::U32(convert(buf)),
+            Indices::U16(buf) => Indices::U16(convert(buf)),
+            Indices::U8(buf) => Indices::U8(convert(buf)),
+        }
+    }
+
+    /// Swaps the last two vertices of every triangle (of a triangle list)
+    pub fn reverse_winding(self) -> Self {
+        fn convert<T: Copy>(mut triangles: Vec<T>) -> Vec<T> {
+            for triangle in triangles.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+
+            triangles
+        }
+
+        match self {
+            Indices::U32(buf) => Indices::U32(convert(buf)),
+            Indices::U16(buf) => Indices::U16(convert(buf)),
+            Indices::U8(buf) => Indices::U8(convert(buf)),
+        }
+    }
+
     /// A GL_TYPE corresponding to the variant of the buffer
     pub fn gl_type(&self) -> GLenum {
         match self {
@@ -373,3 +1099,164 @@ impl Indices {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::{model::ImportOptions, ogl::shader::insert_defines};
+
+    /// Reads the vertex data of the primitives of the first mesh (without creating the OpenGL buffers)
+    fn read_primitives(path: &str, options: ImportOptions) -> Vec<Primitive> {
+        let mut progress = |_, _| {};
+        let (gltf, bundle) = DataBundle::import(Path::new(path), options, &mut progress).unwrap();
+
+        let mesh = gltf.meshes().next().unwrap();
+        mesh.primitives()
+            .map(|primitive| {
+                Primitive::read_gltf(&primitive, "test", &bundle)
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Resolves the channel index of the `<expression>[NAME]` access through the define of NAME
+    fn sampled_channel(src: &str, expression: &str) -> usize {
+        let start = src.find(expression).expect(expression) + expression.len();
+        let name = &src[start..start + src[start..].find(']').unwrap()];
+
+        let define = format!("#define {name} ");
+        src.lines()
+            .find_map(|line| line.strip_prefix(&define))
+            .expect(name)
+            .trim()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn vertex_colors_are_linear() {
+        let prims = read_primitives(
+            "resources/vertex_colors/VertexColors.gltf",
+            ImportOptions::default(),
+        );
+
+        // The colors are passed to the shader as they are stored, without any sRGB conversion
+        assert_eq!(
+            prims[0].colors,
+            [
+                Vec4::new(1., 0., 0., 1.),
+                Vec4::new(0., 1., 0., 1.),
+                Vec4::new(0., 0., 1., 1.),
+                Vec4::ONE,
+            ]
+        );
+
+        // Primitives without vertex colors don't change the base color
+        let prims = read_primitives(
+            "resources/packed_orm/PackedOrm.gltf",
+            ImportOptions::default(),
+        );
+        assert_eq!(prims[0].colors, [Vec4::ONE; 4]);
+    }
+
+    #[test]
+    fn interleaved_attributes() {
+        let prims = read_primitives(
+            "resources/interleaved/InterleavedCube.gltf",
+            ImportOptions::default(),
+        );
+        let prim = &prims[0];
+
+        assert_eq!(prim.vertex_count, 24);
+        assert_eq!(prim.triangle_count, 12);
+        assert_eq!(prim.positions[0], Vec3::new(0.5, -0.5, 0.5));
+        assert_eq!(prim.normals[0], Vec3::X);
+        assert_eq!(prim.texcoords[0], Vec2::new(0., 1.));
+        assert_eq!(prim.texcoords[1], Vec2::new(1., 1.));
+
+        // Every vertex lies on the face its normal points out of, the texture coordinates are in the corners
+        for i in 0..prim.vertex_count {
+            let (position, normal, texcoords) =
+                (prim.positions[i], prim.normals[i], prim.texcoords[i]);
+
+            assert!(
+                (position.abs() - Vec3::splat(0.5)).length() < 1e-6,
+                "{position}"
+            );
+            assert!((normal.length() - 1.).abs() < 1e-6, "{normal}");
+            assert!(
+                (position.dot(normal) - 0.5).abs() < 1e-6,
+                "{position} {normal}"
+            );
+            assert!(texcoords.x == 0. || texcoords.x == 1., "{texcoords}");
+            assert!(texcoords.y == 0. || texcoords.y == 1., "{texcoords}");
+        }
+
+        assert_eq!(prim.aabb.min, Vec3::splat(-0.5));
+        assert_eq!(prim.aabb.max, Vec3::splat(0.5));
+    }
+
+    #[test]
+    fn packed_orm_channels() {
+        let path = Path::new("resources/packed_orm/PackedOrm.gltf");
+        let mut progress = |_, _| {};
+        let (gltf, bundle) =
+            DataBundle::import(path, ImportOptions::default(), &mut progress).unwrap();
+
+        // Both slots of the material use the same image
+        let material = gltf.materials().next().unwrap();
+        let occlusion = material.occlusion_texture().unwrap().texture().source();
+        let metallic_roughness = material
+            .pbr_metallic_roughness()
+            .metallic_roughness_texture()
+            .unwrap()
+            .texture()
+            .source();
+        assert_eq!(occlusion.index(), metallic_roughness.index());
+
+        let image = &bundle.images[occlusion.index()];
+        assert_eq!(
+            (image.width, image.height, image.format),
+            (2, 2, Format::R8G8B8)
+        );
+
+        let texels: Vec<Vec4> = image
+            .pixels
+            .chunks_exact(3)
+            .map(|rgb| Vec4::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32, 255.) / 255.)
+            .collect();
+
+        // The channels the generated texture shader reads for each slot
+        let src = fs::read_to_string("shaders/fs_texture.frag").unwrap();
+        let src = insert_defines(&src, &OrmChannels::shader_defines());
+        let occlusion_channel = sampled_channel(&src, "texture(occlusionTex, vsOut.texCoords)[");
+        let roughness_channel = sampled_channel(&src, "roughness *= metallicRoughness[");
+        let metallic_channel = sampled_channel(&src, "metallic *= metallicRoughness[");
+        assert_eq!(
+            (occlusion_channel, roughness_channel, metallic_channel),
+            (0, 1, 2)
+        );
+
+        // (occlusion, roughness, metallic) of the texels in the row-major order
+        let expected = [
+            (1., 1., 0.),
+            (1., 0.2, 1.),
+            (128. / 255., 128. / 255., 0.),
+            (1., 128. / 255., 1.),
+        ];
+
+        for (texel, expected) in texels.iter().zip(expected) {
+            let (occlusion, roughness, metallic) = (
+                texel[occlusion_channel],
+                texel[roughness_channel],
+                texel[metallic_channel],
+            );
+            assert!((occlusion - expected.0).abs() < 1e-6, "{texel}");
+            assert!((roughness - expected.1).abs() < 1e-6, "{texel}");
+            assert!((metallic - expected.2).abs() < 1e-6, "{texel}");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use glam::Vec3;
+
+use super::{Aabb, Model};
+
+/// The nearest intersection of a ray with the triangles of a model
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    /// Index of the gltf node of the hit mesh
+    pub node: usize,
+    /// Index of the hit primitive in the mesh of the node
+    pub primitive: usize,
+    /// Index of the hit triangle in the primitive (strips and fans are converted to lists)
+    pub triangle: usize,
+    /// Weights of the vertices of the triangle at the hit point
+    pub barycentric: Vec3,
+    /// Distance of the hit point from the origin of the ray, in the lengths of the direction
+    pub distance: f32,
+    /// World position of the hit point
+    pub position: Vec3,
+}
+
+/// The nearest hit of the models, the index of the hit model and the hit
+pub fn raycast_models(models: &[Model], origin: Vec3, dir: Vec3) -> Option<(usize, Hit)> {
+    models
+        .iter()
+        .enumerate()
+        .filter_map(|(i, model)| model.raycast(origin, dir).map(|hit| (i, hit)))
+        .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+}
+
+/// Intersection of the ray with the triangle (both sides), the distance and the barycentric coordinates.
+///
+/// Möller–Trumbore algorithm.
+pub fn ray_triangle(origin: Vec3, dir: Vec3, [a, b, c]: [Vec3; 3]) -> Option<(f32, Vec3)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+
+    let p = dir.cross(edge2);
+    let det = edge1.dot(p);
+    // The ray is parallel to the triangle (or the triangle is degenerate)
+    if det.abs() < f32::EPSILON * edge1.length() * edge2.length() * dir.length() {
+        return None;
+    }
+
+    let inv_det = 1. / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = dir.dot(q) * inv_det;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let t = edge2.dot(q) * inv_det;
+    match t >= 0. {
+        true => Some((t, Vec3::new(1. - u - v, u, v))),
+        false => None,
+    }
+}
+
+/// The distance where the ray enters the bounding box (0 if it starts inside), None if it misses it
+pub fn ray_aabb(origin: Vec3, dir: Vec3, aabb: &Aabb) -> Option<f32> {
+    if aabb.is_empty() {
+        return None;
+    }
+
+    // Division by zero gives infinities, which the comparisons handle
+    let inv_dir = dir.recip();
+    let t1 = (aabb.min - origin) * inv_dir;
+    let t2 = (aabb.max - origin) * inv_dir;
+
+    let t_min = t1.min(t2).max_element().max(0.);
+    let t_max = t1.max(t2).min_element();
+
+    match t_min <= t_max {
+        true => Some(t_min),
+        false => None,
+    }
+}
@@ -0,0 +1,91 @@
+use super::{
+    animation::{Channel, ChannelTangents},
+    AnimationTransforms, Transform,
+};
+
+/// Converts a channel animating a joint of the source skeleton to a channel animating
+/// the matching joint of the target skeleton.
+///
+/// The animated values are treated as deltas from the rest pose of the source joint,
+/// which are applied on top of the rest pose of the target joint:
+/// - rotations are applied in the local space of the joint (target_rest * source_rest^-1 * rotation)
+/// - translations are offsets from the rest translation, scaled by the ratio of the bone lengths
+/// - scales aren't retargeted (returns None), the target joint keeps its rest scale
+///
+/// This is only an approximation. It works for rigs with a similar hierarchy and similarly
+/// oriented local axes of the joints. Joints whose local axes differ (for example rigs
+/// exported by different tools) are distorted and the proportions of the characters
+/// aren't taken into account beyond the bone lengths (feet can slide or float).
+pub fn retarget_channel(
+    channel: &Channel,
+    target_node: usize,
+    source_rest: &Transform,
+    target_rest: &Transform,
+) -> Option<Channel> {
+    // The tangents of cubic splines are only scaled / rotated, the rest poses are offsets of the values
+    let (transforms, tangents) = match &channel.transforms {
+        AnimationTransforms::Rotations(_) => {
+            let delta = target_rest.rotation * source_rest.rotation.inverse();
+            let map = |transforms: &AnimationTransforms, normalize: bool| match transforms {
+                AnimationTransforms::Rotations(rotations) => AnimationTransforms::Rotations(
+                    rotations
+                        .iter()
+                        .map(|rotation| match normalize {
+                            true => (delta * *rotation).normalize(),
+                            false => delta * *rotation,
+                        })
+                        .collect(),
+                ),
+                _ => unreachable!("the tangents are of the same kind as the values"),
+            };
+
+            let tangents = channel.tangents.as_ref().map(|t| ChannelTangents {
+                in_tangents: map(&t.in_tangents, false),
+                out_tangents: map(&t.out_tangents, false),
+            });
+
+            (map(&channel.transforms, true), tangents)
+        }
+        AnimationTransforms::Translations(_) => {
+            let source_length = source_rest.translation.length();
+            let scale = match source_length > f32::EPSILON {
+                true => target_rest.translation.length() / source_length,
+                false => 1.,
+            };
+            let map = |transforms: &AnimationTransforms, offset: bool| match transforms {
+                AnimationTransforms::Translations(translations) => {
+                    AnimationTransforms::Translations(
+                        translations
+                            .iter()
+                            .map(|t| match offset {
+                                true => {
+                                    target_rest.translation + (*t - source_rest.translation) * scale
+                                }
+                                false => *t * scale,
+                            })
+                            .collect(),
+                    )
+                }
+                _ => unreachable!("the tangents are of the same kind as the values"),
+            };
+
+            let tangents = channel.tangents.as_ref().map(|t| ChannelTangents {
+                in_tangents: map(&t.in_tangents, false),
+                out_tangents: map(&t.out_tangents, false),
+            });
+
+            (map(&channel.transforms, true), tangents)
+        }
+        AnimationTransforms::Scales(_) => return None,
+    };
+
+    let mut retargeted = Channel::new(
+        target_node,
+        channel.keyframe_times.clone(),
+        transforms,
+        channel.interpolation_type,
+    );
+    retargeted.tangents = tangents;
+
+    Some(retargeted)
+}
@@ -1,7 +1,13 @@
 use glam::{Mat4, Quat, Vec3};
 use gltf::scene::Transform as GTransform;
 
+use super::AnimationTransform;
+
+/// Transforms with a smaller absolute determinant are treated as non-invertible (zero-scaled)
+pub const MIN_DETERMINANT: f32 = 1e-12;
+
 /// Describes the transformation of a Node or a Joint
+#[derive(Clone, Copy)]
 pub struct Transform {
     /// Local translation relative to the parent joint
     pub translation: Vec3,
@@ -16,9 +22,23 @@ impl Transform {
     pub fn from_gltf(node: &gltf::Node) -> Self {
         let (translation, rotation, scale) = match node.transform() {
             GTransform::Matrix { matrix: mat } => {
+                let mat = Mat4::from_cols_array_2d(&mat);
+
                 // https://www.khronos.org/registry/glTF/specs/2.0/glTF-2.0.html#transformations
                 // "When matrix is defined, it MUST be decomposable to TRS properties."
-                Mat4::from_cols_array_2d(&mat).to_scale_rotation_translation()
+                // Zero-scaled matrices can't be decomposed (the rotation is undefined).
+                if mat.determinant().abs() < MIN_DETERMINANT {
+                    let scale = Vec3::new(
+                        mat.x_axis.truncate().length(),
+                        mat.y_axis.truncate().length(),
+                        mat.z_axis.truncate().length(),
+                    );
+
+                    (mat.w_axis.truncate(), Quat::IDENTITY, scale)
+                } else {
+                    let (scale, rotation, translation) = mat.to_scale_rotation_translation();
+                    (translation, rotation, scale)
+                }
             }
             GTransform::Decomposed {
                 translation,
@@ -55,4 +75,23 @@ impl Transform {
             * Mat4::from_quat(self.rotation)
             * Mat4::from_scale(self.scale)
     }
+
+    /// Overwrites the component of the transform targeted by the animation
+    pub fn apply_animation(&mut self, transform: &AnimationTransform) {
+        match *transform {
+            AnimationTransform::Translation(translation) => self.translation = translation,
+            AnimationTransform::Rotation(rotation) => self.rotation = rotation,
+            AnimationTransform::Scale(scale) => self.scale = scale,
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
 }
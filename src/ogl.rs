@@ -4,6 +4,9 @@ use std::{
     ptr,
 };
 
+/// Abstraction for working with OpenGL Framebuffers.
+pub mod framebuffer;
+
 /// Abstraction for working with OpenGL Shaders.
 pub mod shader;
 
@@ -16,6 +19,113 @@ pub const TEXCOORDS_INDEX: u32 = 1;
 pub const NORMALS_INDEX: u32 = 2;
 pub const JOINTS_INDEX: u32 = 3;
 pub const WEIGHTS_INDEX: u32 = 4;
+pub const COLORS_INDEX: u32 = 5;
+
+// Anisotropic filtering is core only since OpenGL 4.6, the bindings are generated for 4.5.
+// The values are the same for ARB_texture_filter_anisotropic and EXT_texture_filter_anisotropic.
+pub const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+pub const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+/// Checks if the OpenGL context supports the extension
+pub fn has_extension(name: &str) -> bool {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+
+        (0..count as u32).any(|i| {
+            let ext = gl::GetStringi(gl::EXTENSIONS, i);
+            !ext.is_null() && CStr::from_ptr(ext as _).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+/// The extensions used by the optional features of the renderer (reported in the system info)
+pub const RELEVANT_EXTENSIONS: [&str; 6] = [
+    "GL_ARB_texture_filter_anisotropic",
+    "GL_EXT_texture_filter_anisotropic",
+    "GL_ARB_texture_compression_bptc",
+    "GL_EXT_texture_compression_s3tc",
+    "GL_KHR_debug",
+    "GL_ARB_gl_spirv",
+];
+
+/// Description of the OpenGL implementation, for the bug reports
+pub struct GpuInfo {
+    pub renderer: String,
+    pub vendor: String,
+    pub version: String,
+    pub glsl_version: String,
+    /// The relevant extensions and if they are supported
+    pub extensions: Vec<(&'static str, bool)>,
+}
+
+impl GpuInfo {
+    /// Queries the current OpenGL context
+    pub fn query() -> Self {
+        Self {
+            renderer: get_string(gl::RENDERER),
+            vendor: get_string(gl::VENDOR),
+            version: get_string(gl::VERSION),
+            glsl_version: get_string(gl::SHADING_LANGUAGE_VERSION),
+            extensions: RELEVANT_EXTENSIONS
+                .iter()
+                .map(|&ext| (ext, has_extension(ext)))
+                .collect(),
+        }
+    }
+
+    /// Prints the info to the standard output
+    pub fn log(&self) {
+        println!("OpenGL renderer: {}", self.renderer);
+        println!("OpenGL vendor: {}", self.vendor);
+        println!("OpenGL version: {}", self.version);
+        println!("GLSL version: {}", self.glsl_version);
+
+        for (ext, supported) in &self.extensions {
+            let state = if *supported { "yes" } else { "no" };
+            println!("{ext}: {state}");
+        }
+    }
+}
+
+/// A string describing the OpenGL context (glGetString), "N/A" if the query fails
+fn get_string(name: u32) -> String {
+    unsafe {
+        let s = gl::GetString(name);
+        if s.is_null() {
+            return "N/A".to_string();
+        }
+
+        CStr::from_ptr(s as _).to_string_lossy().into_owned()
+    }
+}
+
+/// The range of supported line widths (wide lines are optional in the core profile)
+pub fn line_width_range() -> [f32; 2] {
+    let mut range = [1.; 2];
+    unsafe {
+        gl::GetFloatv(gl::ALIASED_LINE_WIDTH_RANGE, range.as_mut_ptr());
+    }
+
+    range
+}
+
+/// The highest supported anisotropy level, None if anisotropic filtering isn't supported
+pub fn max_anisotropy() -> Option<f32> {
+    let supported = has_extension("GL_ARB_texture_filter_anisotropic")
+        || has_extension("GL_EXT_texture_filter_anisotropic");
+
+    if !supported {
+        return None;
+    }
+
+    let mut max = 0.;
+    unsafe {
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+    }
+
+    Some(max.max(1.))
+}
 
 /// Create an opengl buffer with floating-point content.
 ///
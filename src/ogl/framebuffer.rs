@@ -0,0 +1,151 @@
+use eyre::{eyre, Result};
+use gl::types::GLenum;
+
+/// An offscreen framebuffer with a color attachment and a depth attachment.
+///
+/// The color attachment is either RGBA8 or R32UI (for object IDs).
+pub struct Framebuffer {
+    pub id: u32,
+    /// Renderbuffer containing the color data
+    color: u32,
+    /// Renderbuffer containing the depth data
+    depth: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Framebuffer {
+    /// Creates the framebuffer with an RGBA8 color attachment
+    pub fn new(width: u32, height: u32) -> Result<Self> {
+        Self::with_color_format(width, height, gl::RGBA8)
+    }
+
+    /// Creates the framebuffer with an R32UI color attachment for object IDs.
+    ///
+    /// Integer attachments can't be multisampled and resolved, so the IDs are always single-sampled.
+    pub fn new_id(width: u32, height: u32) -> Result<Self> {
+        Self::with_color_format(width, height, gl::R32UI)
+    }
+
+    /// Creates the framebuffer and checks that it's complete
+    fn with_color_format(width: u32, height: u32, color_format: GLenum) -> Result<Self> {
+        let mut id = 0;
+        let mut color = 0;
+        let mut depth = 0;
+
+        let status = unsafe {
+            gl::GenFramebuffers(1, &mut id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+
+            gl::GenRenderbuffers(1, &mut color);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, color);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, color_format, width as i32, height as i32);
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                color,
+            );
+
+            gl::GenRenderbuffers(1, &mut depth);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH_COMPONENT24,
+                width as i32,
+                height as i32,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                depth,
+            );
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            status
+        };
+
+        let framebuffer = Self {
+            id,
+            color,
+            depth,
+            width,
+            height,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(eyre!("Framebuffer is incomplete: '{status:#x}'"));
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Reads the color data as tightly packed RGBA8 rows, starting with the top row
+    pub fn read_pixels(&self) -> Vec<u8> {
+        read_pixels(self.id, self.width, self.height)
+    }
+
+    /// Reads the ID at the pixel of an R32UI framebuffer, (0, 0) is the bottom-left pixel
+    pub fn read_id(&self, x: u32, y: u32) -> u32 {
+        let mut id = 0u32;
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::ReadPixels(
+                x as i32,
+                y as i32,
+                1,
+                1,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                &mut id as *mut u32 as _,
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+
+        id
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+            gl::DeleteRenderbuffers(2, [self.color, self.depth].as_ptr());
+        }
+    }
+}
+
+/// Reads the color data of the framebuffer (0 is the window) as tightly packed RGBA8 rows, starting with the top row
+pub fn read_pixels(framebuffer: u32, width: u32, height: u32) -> Vec<u8> {
+    let row_size = width as usize * 4;
+    let mut pixels = vec![0u8; row_size * height as usize];
+
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as _,
+        );
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    }
+
+    // OpenGL starts with the bottom row
+    pixels
+        .chunks_exact(row_size)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
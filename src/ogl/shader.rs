@@ -1,7 +1,9 @@
 use eyre::{eyre, Context, Result};
 use gl::types::GLenum;
-use glam::{Mat4, Vec3, Vec4};
-use std::{fs, ptr};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::{ffi::CString, fs, ptr};
+
+use super::uniform_buffer::BlockLayout;
 
 /// Represents an OpenGL shader.
 ///
@@ -15,19 +17,51 @@ pub struct Shader {
 impl Shader {
     /// Loads a vertex shader and a fragment shader from specified paths and tries to create a shader program
     pub fn from_file(vs_path: &str, fs_path: &str) -> Result<Shader> {
-        let mut vs_src = fs::read(vs_path).wrap_err("Couldn't load the vertex shader file")?;
-        let mut fs_src = fs::read(fs_path).wrap_err("Couldn't load the fragment shader file")?;
+        let vs = Self::load_shader(vs_path, gl::VERTEX_SHADER)?;
+        let fs = Self::load_shader(fs_path, gl::FRAGMENT_SHADER)?;
+        let shader_program = Self::link_shaders(&[vs, fs])?;
+        Ok(Shader { id: shader_program })
+    }
 
-        // Add null-terminators
-        vs_src.push(b'\0');
-        fs_src.push(b'\0');
+    /// Loads a vertex shader and a fragment shader, the defines are added to the start of the fragment shader
+    pub fn from_file_with_defines(
+        vs_path: &str,
+        fs_path: &str,
+        defines: &[(&str, usize)],
+    ) -> Result<Shader> {
+        let vs = Self::load_shader(vs_path, gl::VERTEX_SHADER)?;
+        let fs = Self::load_shader_with_defines(fs_path, gl::FRAGMENT_SHADER, defines)?;
+        let shader_program = Self::link_shaders(&[vs, fs])?;
+        Ok(Shader { id: shader_program })
+    }
 
-        let vs = Self::compile_shader(&vs_src, gl::VERTEX_SHADER)?;
-        let fs = Self::compile_shader(&fs_src, gl::FRAGMENT_SHADER)?;
-        let shader_program = Self::link_shaders(vs, fs)?;
+    /// Loads a vertex shader, a geometry shader and a fragment shader from specified paths
+    /// and tries to create a shader program
+    pub fn from_file_with_geometry(vs_path: &str, gs_path: &str, fs_path: &str) -> Result<Shader> {
+        let vs = Self::load_shader(vs_path, gl::VERTEX_SHADER)?;
+        let gs = Self::load_shader(gs_path, gl::GEOMETRY_SHADER)?;
+        let fs = Self::load_shader(fs_path, gl::FRAGMENT_SHADER)?;
+        let shader_program = Self::link_shaders(&[vs, gs, fs])?;
         Ok(Shader { id: shader_program })
     }
 
+    /// Loads the shader file and compiles it
+    fn load_shader(path: &str, typ: GLenum) -> Result<u32> {
+        Self::load_shader_with_defines(path, typ, &[])
+    }
+
+    /// Loads the shader file, adds the defines after the '#version' directive and compiles it
+    fn load_shader_with_defines(path: &str, typ: GLenum, defines: &[(&str, usize)]) -> Result<u32> {
+        let src = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Couldn't load the shader file '{path}'"))?;
+        let mut src = insert_defines(&src, defines).into_bytes();
+
+        // Add null-terminator
+        src.push(b'\0');
+
+        Self::compile_shader(&src, typ).wrap_err_with(|| format!("Shader file '{path}'"))
+    }
+
     /// Use this shader to render.
     ///
     /// Draw calls should be passed using the `render` function parameter.
@@ -67,12 +101,13 @@ impl Shader {
         }
     }
 
-    /// Tries to link the vertex and fragment shaders (passed by their ids) and checks for linking errors.
-    fn link_shaders(vs: u32, fs: u32) -> Result<u32> {
+    /// Tries to link the shaders (passed by their ids) and checks for linking errors.
+    fn link_shaders(shaders: &[u32]) -> Result<u32> {
         unsafe {
             let shader_program = gl::CreateProgram();
-            gl::AttachShader(shader_program, vs);
-            gl::AttachShader(shader_program, fs);
+            for shader in shaders {
+                gl::AttachShader(shader_program, *shader);
+            }
             gl::LinkProgram(shader_program);
 
             let mut res = 0;
@@ -92,13 +127,72 @@ impl Shader {
                 return Err(eyre!("Failed to create a shader program: '{}'", info_msg));
             }
 
-            gl::DeleteShader(vs);
-            gl::DeleteShader(fs);
+            for shader in shaders {
+                gl::DeleteShader(*shader);
+            }
 
             Ok(shader_program)
         }
     }
 
+    /// Compares the layout of a uniform block in the program with the layout of the uploaded data.
+    ///
+    /// Blocks that aren't used by the program are skipped.
+    pub fn check_block_layout(&self, layout: &BlockLayout) -> Result<()> {
+        let block_name = CString::new(layout.block_name)?;
+
+        unsafe {
+            let block_index = gl::GetUniformBlockIndex(self.id, block_name.as_ptr());
+            if block_index == gl::INVALID_INDEX {
+                return Ok(());
+            }
+
+            let mut gpu_size = 0;
+            gl::GetActiveUniformBlockiv(
+                self.id,
+                block_index,
+                gl::UNIFORM_BLOCK_DATA_SIZE,
+                &mut gpu_size,
+            );
+
+            if gpu_size as usize > layout.size {
+                return Err(eyre!(
+                    "Uniform block '{}' of shader {} has {gpu_size} bytes, but only {} bytes are uploaded",
+                    layout.block_name,
+                    self.id,
+                    layout.size
+                ));
+            }
+
+            for (member, offset) in layout.members {
+                let member_name = CString::new(*member)?;
+
+                let mut index = gl::INVALID_INDEX;
+                gl::GetUniformIndices(self.id, 1, &member_name.as_ptr(), &mut index);
+                if index == gl::INVALID_INDEX {
+                    return Err(eyre!(
+                        "Uniform block '{}' of shader {} doesn't have the member '{member}'",
+                        layout.block_name,
+                        self.id
+                    ));
+                }
+
+                let mut gpu_offset = 0;
+                gl::GetActiveUniformsiv(self.id, 1, &index, gl::UNIFORM_OFFSET, &mut gpu_offset);
+
+                if gpu_offset as usize != *offset {
+                    return Err(eyre!(
+                        "Member '{member}' of the uniform block '{}' of shader {} is at offset {gpu_offset}, but it's uploaded at offset {offset}",
+                        layout.block_name,
+                        self.id
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     //
     // Uniform setters...
     //
@@ -133,6 +227,14 @@ impl Shader {
         }
     }
 
+    pub fn set_vec2(&self, vec: Vec2, name: &str) {
+        Self::check_inform_name(name);
+        unsafe {
+            let loc = gl::GetUniformLocation(self.id, name.as_ptr() as _);
+            gl::Uniform2f(loc, vec.x, vec.y);
+        }
+    }
+
     #[allow(unused)]
     pub fn set_vec4(&self, vec: Vec4, name: &str) {
         Self::check_inform_name(name);
@@ -166,3 +268,36 @@ impl Shader {
         assert!(name.ends_with('\0'));
     }
 }
+
+/// Adds the defines after the '#version' directive, which has to be the first line of the shader
+pub(crate) fn insert_defines(src: &str, defines: &[(&str, usize)]) -> String {
+    let (version, rest) = match src.find('\n') {
+        Some(end) if src.starts_with("#version") => src.split_at(end + 1),
+        _ => ("", src),
+    };
+
+    let mut result = version.to_string();
+    for (name, value) in defines {
+        result.push_str(&format!("#define {name} {value}\n"));
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defines_follow_the_version() {
+        let src = "#version 420 core\n\nout vec4 FragColor;\n";
+        let defines = [("OCCLUSION_CHANNEL", 0), ("METALLIC_CHANNEL", 2)];
+
+        assert_eq!(
+            insert_defines(src, &defines),
+            "#version 420 core\n#define OCCLUSION_CHANNEL 0\n#define METALLIC_CHANNEL 2\n\nout vec4 FragColor;\n"
+        );
+        assert_eq!(insert_defines(src, &[]), src);
+    }
+}
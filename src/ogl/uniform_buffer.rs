@@ -44,8 +44,20 @@ where
 pub trait UniformBufferElement {
     /// The binding port
     const BINDING: u32;
+    /// The layout the data is uploaded in, checked against the shaders at startup
+    const LAYOUT: Option<BlockLayout> = None;
     /// Update buffer data using gl::BufferSubData
     fn update(&self);
     /// Allocate data for the element with gl::BufferData
     fn init_buffer(&self);
 }
+
+/// The std140 layout of a uniform block as it's uploaded by the CPU side
+pub struct BlockLayout {
+    /// Name of the uniform block in the shaders
+    pub block_name: &'static str,
+    /// Size of the uploaded data in bytes
+    pub size: usize,
+    /// Names of the members of the block and their offsets in bytes
+    pub members: &'static [(&'static str, usize)],
+}
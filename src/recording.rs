@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use eyre::Result;
+
+use crate::{
+    headless::save_png,
+    model::{AnimationControl, Model},
+    ogl::framebuffer,
+    window::MyWindow,
+};
+
+/// Recording of the active clip of a model into a sequence of PNG images (`frame_0001.png`, ...).
+///
+/// Every frame poses the clip at the next multiple of the step (from the start of its trimmed part),
+/// so the sequence doesn't depend on the speed of the rendering.
+pub struct ClipRecording {
+    /// The directory the images are written to
+    dir: PathBuf,
+    /// Index of the recorded model in the scene
+    pub model: usize,
+    /// Index of the recorded clip
+    animation: usize,
+    /// Time between the frames in seconds
+    step: f32,
+    /// Index of the next captured frame
+    frame: u32,
+    /// Number of the frames of the whole clip
+    frame_count: u32,
+}
+
+impl ClipRecording {
+    /// Starts recording the active clip of the model, None if the model doesn't have an active clip
+    pub fn new(dir: PathBuf, model_index: usize, model: &Model, step: f32) -> Option<Self> {
+        let animation = model.animations.active_animation()?;
+        let duration = model.animations.duration()?;
+
+        // The end of a loop is the same pose as its start
+        let frame_count = ((duration / step).ceil() as u32).max(1);
+
+        Some(Self {
+            dir,
+            model: model_index,
+            animation,
+            step,
+            frame: 0,
+            frame_count,
+        })
+    }
+
+    /// Poses the recorded clip at the time of the next frame (the clip is paused)
+    pub fn pose(&self, model: &mut Model) {
+        let animations = &mut model.animations;
+        animations.switch_animation(self.animation, None);
+
+        let animation = &mut animations.animations[self.animation];
+        animation.current_time =
+            (animation.trim_start + self.frame as f32 * self.step).min(animation.trim_end);
+
+        animations.animation_control = AnimationControl::Controllable {
+            active_animation: self.animation,
+        };
+    }
+
+    /// Saves the rendered frame from the back buffer of the window (before the GUI is drawn over it).
+    ///
+    /// Returns true if it was the last frame of the clip.
+    pub fn capture(&mut self, window: &MyWindow) -> Result<bool> {
+        let pixels = framebuffer::read_pixels(0, window.width, window.height);
+
+        self.frame += 1;
+        let path = self.dir.join(format!("frame_{:04}.png", self.frame));
+        save_png(
+            &path.to_string_lossy(),
+            window.width,
+            window.height,
+            &pixels,
+        )?;
+
+        Ok(self.frame >= self.frame_count)
+    }
+
+    /// The number of the captured frames and the number of the frames of the whole clip
+    pub fn progress(&self) -> (u32, u32) {
+        (self.frame, self.frame_count)
+    }
+
+    /// The directory the images are written to
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+}
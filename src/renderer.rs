@@ -1,36 +1,96 @@
 use std::{ptr, time::Instant};
 
 use eyre::Result;
-use glam::{Mat4, Vec3, Vec4};
+use gl::types::GLenum;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use gltf::animation::Interpolation;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    auto_quality::QualityFeature,
     camera::Camera,
     gui::Gui,
     model::{
-        AnimationControl, AnimationTransform, Joint, Mesh, Model, Node, Primitive, PrimitiveTexture,
+        Aabb, Animation, AnimationControl, AnimationTransform, Joint, Joints, LightKind, LookAt,
+        MaterialProperty, Mesh, Model, Node, NodeAnimationTransform, OrmChannels, PbrMaterial,
+        PrimSkin, Primitive, PrimitiveTexture, MIN_DETERMINANT,
+    },
+    ogl::{
+        self,
+        framebuffer::Framebuffer,
+        shader::Shader,
+        uniform_buffer::{UniformBuffer, UniformBufferElement},
+        GpuInfo,
     },
-    ogl::{shader::Shader, uniform_buffer::UniformBuffer},
     window::MyWindow,
 };
 
+mod background;
+mod blob_shadow;
+mod bounds_mesh;
+mod frustum;
+mod fxaa;
 mod joint_transforms;
 mod lighting;
+mod line_renderer;
 mod material;
+mod root_trail;
 mod settings;
+mod shadow_maps;
+mod shadows;
 mod skeleton_mesh;
 mod transforms;
 
 use self::{
-    joint_transforms::JointTransforms, lighting::Lighting, material::Material, settings::Settings,
+    background::Background,
+    blob_shadow::BlobShadow,
+    frustum::Frustum,
+    fxaa::Fxaa,
+    joint_transforms::JointTransforms,
+    lighting::{Lighting, PunctualLight},
+    line_renderer::LineRenderer,
+    material::Material,
+    root_trail::RootTrail,
+    shadow_maps::ShadowMaps,
+    shadows::Shadows,
     transforms::Transforms,
 };
 
+pub use self::{
+    lighting::{FogMode, SpotLight, MAX_LIGHTS},
+    settings::{Settings, ShadingMode},
+    shadows::MAX_CASCADES,
+};
+
+/// The light casting the shadows
+enum ShadowCaster {
+    /// Direction towards a distant light, the shadows are split into the cascades
+    Directional(Vec3),
+    /// A spot light, its cone is covered by a single perspective shadow map
+    Spot {
+        pos: Vec3,
+        dir: Vec3,
+        outer_cone_angle: f32,
+        range: Option<f32>,
+    },
+}
+
+/// Texture unit of the shadow maps (the units 0 - 3 are used by the materials)
+const SHADOW_MAP_UNIT: u32 = 4;
+
+/// Color the window is cleared with
+pub const BACKGROUND_COLOR: [f32; 3] = [0.15, 0.15, 0.15];
+
 /// A component responsible for rendering the scene.
 pub struct Renderer {
     /// Shader for meshes containing texture data
     texture_shader: Shader,
     /// Shader for meshes without textures
     color_shader: Shader,
+    /// Shader writing the object IDs of the primitives
+    id_shader: Shader,
+    /// Shader writing only the depth into the shadow maps
+    shadow_shader: Shader,
     /// Current MVP transformation matrices
     transforms: UniformBuffer<Transforms>,
     /// Joint transformation matrices
@@ -39,33 +99,258 @@ pub struct Renderer {
     settings: UniformBuffer<Settings>,
     /// Current mesh material
     material: UniformBuffer<Material>,
-    #[allow(unused)]
+    /// The material last uploaded to the uniform buffer in the current frame
+    uploaded_material: Option<Material>,
+    /// Textures bound to the texture units 0 - 3 in the current frame
+    bound_textures: [Option<u32>; 4],
+    /// Counts of the material state changes in the last frame
+    state_changes: StateChanges,
     /// Current lighting settings
     lighting: UniformBuffer<Lighting>,
+    /// The cascades of the shadow maps
+    shadows: UniformBuffer<Shadows>,
+    /// Depth textures of the shadow cascades, created when the shadows are enabled for the first time
+    shadow_maps: Option<ShadowMaps>,
+    /// If the shadow maps couldn't be created (the shadows are disabled)
+    shadow_maps_failed: bool,
+    /// If the current pass renders the depth of the shadow casters
+    shadow_pass: bool,
     /// Current joint / node transforms
     node_animation_transforms: Vec<NodeAnimationTransform>,
+    /// Current values of the animated material properties
+    material_animation_values: Vec<MaterialAnimationValue>,
+    /// Interpolation forcing all animation channels in the current frame, None uses the authored ones
+    interpolation: Option<Interpolation>,
+    /// The highest anisotropy level supported by the hardware, None if it's unsupported
+    max_anisotropy: Option<f32>,
+    /// Anisotropy level of the textures in the current frame
+    anisotropy: Option<f32>,
+    /// Range of line widths supported by the hardware
+    line_width_range: [f32; 2],
+    /// If the wireframe of the currently rendered model should be drawn
+    wireframe: bool,
+    /// Offscreen target of the ID pass, recreated when the window is resized
+    id_framebuffer: Option<Framebuffer>,
+    /// If the object IDs are rendered instead of the colors
+    id_pass: bool,
+    /// Primitives drawn in the ID pass, the ID of a primitive is its index + 1
+    pick_targets: Vec<PickTarget>,
+    /// View frustum of the current frame
+    frustum: Frustum,
+    /// If the primitives outside of the view frustum are skipped in the current frame
+    frustum_culling: bool,
+    /// Number of primitives skipped in the last frame because they were outside of the view
+    culled_primitives: u32,
+    /// World transforms of the joints of the skin that is currently rendered
+    joint_world_transforms: Vec<Mat4>,
+    /// Bounding boxes drawn for debugging in the current frame and if they are inside of the view frustum
+    debug_boxes: Vec<(Aabb, bool)>,
+    /// Draws the lines of the debug overlays (bones, bounding boxes)
+    line_renderer: LineRenderer,
+    /// Width of the lines of the debug overlays in pixels in the current frame
+    overlay_line_width: f32,
+    /// Size of the render target in pixels in the current frame
+    viewport_size: Vec2,
+    /// World position of the root joint of the first skin processed in the current frame
+    skin_root: Option<Vec3>,
+    /// Recent positions of the root of the selected model
+    root_trail: RootTrail,
+    /// The look-at constraint of the model rendered in the current frame
+    look_at: Option<LookAt>,
+    /// Determinant of the skinning matrix of the root joint of the last processed skin
+    skin_determinant: f32,
+    /// Number of the samples per pixel of the render target
+    msaa_samples: i32,
+    /// Description of the OpenGL implementation
+    gpu_info: GpuInfo,
+    /// The HDR image drawn behind the models
+    background: Background,
+    /// The soft shadow on the ground below the model
+    blob_shadow: BlobShadow,
+    /// The post-process anti-aliasing
+    fxaa: Fxaa,
 }
 
 impl Renderer {
     /// Create a new renderer
     pub fn new() -> Result<Self> {
-        let texture_shader =
-            Shader::from_file("shaders/vs_combined.vert", "shaders/fs_texture.frag")?;
+        let texture_shader = Shader::from_file_with_defines(
+            "shaders/vs_combined.vert",
+            "shaders/fs_texture.frag",
+            &OrmChannels::shader_defines(),
+        )?;
         let color_shader = Shader::from_file("shaders/vs_combined.vert", "shaders/fs_color.frag")?;
+        let id_shader = Shader::from_file("shaders/vs_combined.vert", "shaders/fs_id.frag")?;
+        let shadow_shader =
+            Shader::from_file("shaders/vs_combined.vert", "shaders/fs_shadow.frag")?;
+        let line_renderer = LineRenderer::new()?;
+        let background = Background::new()?;
+        let blob_shadow = BlobShadow::new()?;
+        let fxaa = Fxaa::new()?;
+
+        Self::check_block_layouts(&[
+            &texture_shader,
+            &color_shader,
+            &id_shader,
+            &shadow_shader,
+            line_renderer.shader(),
+            blob_shadow.shader(),
+        ]);
+
+        let gpu_info = GpuInfo::query();
+        gpu_info.log();
+
+        // Used by the VAOs without vertex colors (the debug meshes)
+        unsafe {
+            gl::VertexAttrib4f(ogl::COLORS_INDEX, 1., 1., 1., 1.);
+        }
 
         Ok(Self {
             texture_shader,
             color_shader,
+            id_shader,
+            shadow_shader,
             transforms: UniformBuffer::new(Transforms::new_indentity()),
             joint_transforms: UniformBuffer::new(JointTransforms::new()),
             settings: UniformBuffer::new(Settings::new()),
             material: UniformBuffer::new(Material::new()),
+            uploaded_material: None,
+            bound_textures: [None; 4],
+            state_changes: StateChanges::default(),
             lighting: UniformBuffer::new(Lighting::new(Vec3::new(400., 1000., 400.))),
+            shadows: UniformBuffer::new(Shadows::new()),
+            shadow_maps: None,
+            shadow_maps_failed: false,
+            shadow_pass: false,
             node_animation_transforms: Vec::new(),
+            material_animation_values: Vec::new(),
+            interpolation: None,
+            max_anisotropy: ogl::max_anisotropy(),
+            anisotropy: None,
+            line_width_range: ogl::line_width_range(),
+            wireframe: false,
+            id_framebuffer: None,
+            id_pass: false,
+            pick_targets: Vec::new(),
+            frustum: Frustum::from_matrix(Mat4::IDENTITY),
+            frustum_culling: true,
+            culled_primitives: 0,
+            joint_world_transforms: Vec::new(),
+            debug_boxes: Vec::new(),
+            line_renderer,
+            overlay_line_width: 1.,
+            viewport_size: Vec2::ONE,
+            skin_root: None,
+            root_trail: RootTrail::new(),
+            look_at: None,
+            skin_determinant: 1.,
+            msaa_samples: 0,
+            gpu_info,
+            background,
+            blob_shadow,
+            fxaa,
         })
     }
 
-    /// Render a new frame
+    /// Logs the uniform blocks whose layout in the shaders doesn't match the uploaded data
+    fn check_block_layouts(shaders: &[&Shader]) {
+        let layouts = [
+            Transforms::LAYOUT,
+            Settings::LAYOUT,
+            Material::LAYOUT,
+            Lighting::LAYOUT,
+            Shadows::LAYOUT,
+        ];
+
+        for shader in shaders {
+            for layout in layouts.iter().flatten() {
+                if let Err(e) = shader.check_block_layout(layout) {
+                    eprintln!("ERROR: {e}");
+                }
+            }
+        }
+    }
+
+    /// Description of the OpenGL implementation (the GPU, the driver and the supported extensions)
+    pub fn gpu_info(&self) -> &GpuInfo {
+        &self.gpu_info
+    }
+
+    /// Number of the samples per pixel of the last render target (0 if it isn't multisampled)
+    pub fn msaa_samples(&self) -> i32 {
+        self.msaa_samples
+    }
+
+    /// The rendering settings last uploaded to the uniform buffer
+    pub fn settings(&self) -> &Settings {
+        &self.settings.inner
+    }
+
+    /// Number of primitives outside of the view frustum in the last rendered frame
+    pub fn culled_primitives(&self) -> u32 {
+        self.culled_primitives
+    }
+
+    /// Counts of the material state changes in the last rendered frame
+    pub fn state_changes(&self) -> StateChanges {
+        self.state_changes
+    }
+
+    /// Finds the primitive of the selected model under the cursor.
+    ///
+    /// Renders the object IDs into an offscreen integer buffer and reads back the pixel at (x, y)
+    /// (in window coordinates, starting at the top-left corner).
+    /// Only meant to be called on click, the ID pass isn't run every frame.
+    pub fn pick(
+        &mut self,
+        models: &mut [Model],
+        camera: &mut Camera,
+        window: &MyWindow,
+        gui_state: &Gui,
+        x: u32,
+        y: u32,
+    ) -> Option<PickTarget> {
+        if x >= window.width || y >= window.height {
+            return None;
+        }
+
+        let resized = self
+            .id_framebuffer
+            .as_ref()
+            .map(|fb| fb.width != window.width || fb.height != window.height)
+            .unwrap_or(true);
+
+        if resized {
+            match Framebuffer::new_id(window.width, window.height) {
+                Ok(framebuffer) => self.id_framebuffer = Some(framebuffer),
+                Err(e) => {
+                    eprintln!("WARN: couldn't create the picking framebuffer: {e:?}");
+                    return None;
+                }
+            }
+        }
+
+        let target = RenderTarget::framebuffer(self.id_framebuffer.as_ref()?);
+
+        self.pick_targets.clear();
+        self.id_pass = true;
+        self.render_to(models, camera, gui_state, target);
+        self.id_pass = false;
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        // OpenGL starts with the bottom row
+        let framebuffer = self.id_framebuffer.as_ref()?;
+        let id = framebuffer.read_id(x, window.height - 1 - y);
+
+        id.checked_sub(1)
+            .and_then(|index| self.pick_targets.get(index as usize))
+            .copied()
+    }
+
+    /// Render a new frame into the window
     pub fn render(
         &mut self,
         models: &mut [Model],
@@ -73,66 +358,595 @@ impl Renderer {
         window: &MyWindow,
         gui_state: &Gui,
     ) {
+        self.render_to(models, camera, gui_state, RenderTarget::window(window));
+    }
+
+    /// Render a new frame into the target.
+    ///
+    /// The target framebuffer stays bound after rendering.
+    pub fn render_to(
+        &mut self,
+        models: &mut [Model],
+        camera: &mut Camera,
+        gui_state: &Gui,
+        target: RenderTarget,
+    ) {
+        // The IDs can't be filtered
+        let fxaa = gui_state.fxaa && !gui_state.auto_quality.is_disabled(QualityFeature::Fxaa);
+        let fxaa_target = match fxaa && !self.id_pass {
+            true => self.fxaa.scene_target(target.width, target.height),
+            false => None,
+        };
+
+        match fxaa_target {
+            Some(scene_target) => {
+                self.render_scene(models, camera, gui_state, scene_target);
+
+                self.fxaa.resolve(&target);
+                unsafe {
+                    if gui_state.depth_test {
+                        gl::Enable(gl::DEPTH_TEST);
+                    }
+                    if gui_state.face_culling {
+                        gl::Enable(gl::CULL_FACE);
+                    }
+                    gl::Enable(gl::BLEND);
+                    set_polygon_mode(gui_state);
+                }
+            }
+            None => self.render_scene(models, camera, gui_state, target),
+        }
+    }
+
+    /// Renders the selected model with the overlays into the target, the target stays bound
+    fn render_scene(
+        &mut self,
+        models: &mut [Model],
+        camera: &mut Camera,
+        gui_state: &Gui,
+        target: RenderTarget,
+    ) {
+        let anisotropy = match gui_state
+            .auto_quality
+            .is_disabled(QualityFeature::Anisotropy)
+        {
+            true => 1.,
+            false => gui_state.anisotropy,
+        };
+        self.anisotropy = self.max_anisotropy.map(|max| anisotropy.clamp(1., max));
+
+        // Other code (the GUI) binds its own textures between the frames
+        self.uploaded_material = None;
+        self.bound_textures = [None; 4];
+        if !self.id_pass {
+            self.state_changes = StateChanges::default();
+            self.culled_primitives = 0;
+        }
+
         unsafe {
-            gl::Viewport(0, 0, window.width as i32, window.height as i32);
-            gl::Enable(gl::DEPTH_TEST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+        }
+        if !self.id_pass {
+            self.msaa_samples = target_samples();
+        }
 
-            gl::Enable(gl::CULL_FACE);
-            gl::FrontFace(gl::CCW);
+        unsafe {
+            gl::Viewport(0, 0, target.width as i32, target.height as i32);
+
+            if gui_state.depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
 
-            if gui_state.draw_skeleton {
-                gl::CullFace(gl::FRONT);
-                gl::PolygonMode(gl::BACK, gl::LINE);
+            if gui_state.face_culling {
+                gl::Enable(gl::CULL_FACE);
             } else {
-                gl::CullFace(gl::BACK);
-                gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+                gl::Disable(gl::CULL_FACE);
             }
 
+            gl::FrontFace(gl::CCW);
+
+            set_polygon_mode(gui_state);
+
             gl::Enable(gl::BLEND);
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
 
-            gl::ClearColor(0.15, 0.15, 0.15, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            set_depth_range(camera.reversed_z, camera.reversed_z);
+
+            // Moves the wireframe lines towards the camera
+            let wireframe_offset = if camera.reversed_z { 1. } else { -1. };
+            gl::PolygonOffset(wireframe_offset, wireframe_offset);
+
+            if self.id_pass {
+                // Integer attachments can't be cleared by glClear
+                gl::ClearBufferuiv(gl::COLOR, 0, [0u32; 4].as_ptr());
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+            } else {
+                let [r, g, b] = BACKGROUND_COLOR;
+                gl::ClearColor(r, g, b, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
         }
 
         self.node_animation_transforms.clear();
+        self.material_animation_values.clear();
 
-        // TODO: možná glu perspective
-        let persp = Mat4::perspective_rh(
-            f32::to_radians(60.),
-            window.width as f32 / window.height as f32,
-            0.1,
-            3000.,
-        );
+        let persp = camera.projection_mat(target.width as f32 / target.height as f32);
+        self.viewport_size = Vec2::new(target.width as f32, target.height as f32);
+        self.overlay_line_width = gui_state.overlay_line_width;
 
+        // Only the selected model is drawn, so only its textures are uploaded,
+        // the other models keep their texture names without images until they are selected
         let model = &mut models[gui_state.selected_model];
+        model.upload_textures();
 
         self.transforms.inner.projection = persp;
         self.transforms.inner.view = camera.view_mat();
+        self.frustum = Frustum::from_matrix(persp * self.transforms.inner.view);
+        self.frustum_culling = gui_state.frustum_culling;
+        self.interpolation = gui_state.interpolation_override;
         self.transforms.inner.model = model.transform;
         self.transforms.update();
 
-        self.recalculate_animation(model);
+        self.lighting.inner.ambient_color = Vec3::from(gui_state.ambient_color);
+        self.lighting.inner.ambient_intensity = gui_state.ambient_intensity;
+        self.lighting.inner.fog_mode = gui_state.fog_mode;
+        self.lighting.inner.fog_color = Vec3::from(gui_state.fog_color);
+        self.lighting.inner.fog_density = gui_state.fog_density;
+        self.lighting.inner.fog_start = gui_state.fog_start;
+        self.lighting.inner.fog_end = gui_state.fog_end;
+        self.set_lights(model, gui_state);
+        self.lighting.update();
+
+        self.settings.inner.shading_mode = gui_state.shading_mode;
+        self.settings.inner.selected_joint = gui_state.selected_joint as u32;
+        self.settings.inner.flip_v = model.flip_v;
+        self.settings.inner.gamma_correct = gui_state.gamma_correct;
+        self.settings.inner.apply_vertex_colors = gui_state.apply_vertex_colors;
+        self.settings.inner.alpha_to_coverage =
+            gui_state.alpha_to_coverage && self.msaa_samples > 1;
+        self.settings.inner.uv_checker_scale = match gui_state.uv_checker {
+            true => gui_state.uv_checker_scale,
+            false => 0.,
+        };
+        self.settings.inner.point_size = gui_state.point_size;
+        self.settings.inner.point_size_attenuation = gui_state.point_size_attenuation;
+        self.settings.update();
+
+        self.recalculate_animation(model, gui_state);
+
+        self.wireframe = (gui_state.wireframe || model.wireframe)
+            && !gui_state
+                .auto_quality
+                .is_disabled(QualityFeature::Wireframe);
+
+        self.debug_boxes.clear();
+        self.skin_root = None;
+
+        if let Some(look_at) = &mut model.look_at {
+            if look_at.track_camera {
+                look_at.target = camera.pos();
+            }
+        }
+        self.look_at = model.look_at.clone();
+
+        self.render_shadow_maps(model, camera, gui_state, &target);
+
+        if self.is_main_pass() {
+            self.draw_background(gui_state);
+        }
 
         let transform = model.transform;
         self.render_node(&mut model.root, transform, gui_state);
+
+        if !self.id_pass {
+            // Models without any primitives (only empty nodes) don't have bounds
+            let bounds = model.bounds();
+
+            if gui_state.blob_shadow {
+                self.draw_blob_shadow(&bounds, gui_state);
+            }
+
+            if gui_state.draw_model_bounds && !bounds.is_empty() {
+                let in_frustum = self.frustum.intersects_aabb(&bounds);
+                self.debug_boxes.push((bounds, in_frustum));
+            }
+
+            self.draw_debug_boxes(gui_state);
+
+            if gui_state.draw_root_trail {
+                self.draw_root_trail(model, gui_state);
+            } else {
+                self.root_trail.clear();
+            }
+        }
+    }
+
+    /// If the current pass renders the colors into the target (not the object IDs or the shadow maps),
+    /// only the main pass draws the overlays and counts the statistics
+    fn is_main_pass(&self) -> bool {
+        !self.id_pass && !self.shadow_pass
+    }
+
+    /// Draws the HDR background over the cleared target and restores the depth and culling state
+    fn draw_background(&mut self, gui_state: &Gui) {
+        self.background
+            .set_image(gui_state.background_path.as_deref());
+        if !self.background.is_loaded() {
+            return;
+        }
+
+        self.background.draw(
+            self.transforms.inner.projection,
+            self.transforms.inner.view,
+            gui_state.background_rotation,
+            gui_state.background_exposure,
+        );
+        // The background texture replaced the material texture
+        self.bound_textures[0] = None;
+
+        unsafe {
+            if gui_state.depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+
+            if gui_state.face_culling {
+                gl::Enable(gl::CULL_FACE);
+            }
+        }
+    }
+
+    /// Transforms the lights of the gltf file into the world space and adds the enabled spot lights of the user.
+    /// The light of the viewer is used if the model doesn't have any lights (or they are disabled).
+    ///
+    /// The model is scaled uniformly by its transform, so the intensities of the point and spot lights are
+    /// scaled too, the lights have the same effect as in the unscaled model.
+    fn set_lights(&mut self, model: &Model, gui_state: &Gui) {
+        let lighting = &mut self.lighting.inner;
+        lighting.lights.clear();
+
+        let asset_lights = match gui_state.asset_lights {
+            true => &model.lights[..model.lights.len().min(MAX_LIGHTS)],
+            false => &[],
+        };
+        let scale = model.transform.determinant().abs().cbrt();
+
+        for light in asset_lights {
+            let distance_scale = match light.kind {
+                LightKind::Directional => 1.,
+                _ => scale * scale,
+            };
+
+            lighting.lights.push(PunctualLight {
+                kind: light.kind,
+                pos: model.transform.transform_point3(light.pos),
+                dir: model
+                    .transform
+                    .transform_vector3(light.dir)
+                    .normalize_or_zero(),
+                radiance: light.color
+                    * light.intensity
+                    * distance_scale
+                    * gui_state.asset_light_intensity,
+                range: light.range.map(|range| range * scale),
+            });
+        }
+
+        lighting.viewer_light = lighting.lights.is_empty();
+        // The first directional asset light replaces the light of the viewer as the caster of the shadows
+        lighting.shadow_light = lighting
+            .lights
+            .iter()
+            .position(|light| light.kind == LightKind::Directional);
+
+        // The disabled spots aren't uploaded at all
+        let spots = gui_state.spot_lights.iter().filter(|spot| spot.enabled);
+        for spot in spots.take(MAX_LIGHTS - lighting.lights.len()) {
+            if spot.cast_shadows {
+                lighting.shadow_light = Some(lighting.lights.len());
+            }
+
+            lighting.lights.push(spot.punctual_light());
+        }
+    }
+
+    /// The light casting the shadows, None if it's a point light (or none of the asset lights is directional).
+    ///
+    /// The light of the viewer is far away, so the shadows treat it as a directional light.
+    fn shadow_caster(&self) -> Option<ShadowCaster> {
+        let lighting = &self.lighting.inner;
+        let light = match lighting.shadow_light {
+            Some(light) => lighting.lights[light],
+            None if lighting.viewer_light => {
+                let light_dir = lighting.light_pos.normalize_or_zero();
+                return Some(ShadowCaster::Directional(light_dir));
+            }
+            None => return None,
+        };
+
+        match light.kind {
+            LightKind::Directional => Some(ShadowCaster::Directional(-light.dir)),
+            LightKind::Spot {
+                outer_cone_angle, ..
+            } => Some(ShadowCaster::Spot {
+                pos: light.pos,
+                dir: light.dir,
+                outer_cone_angle,
+                range: light.range,
+            }),
+            LightKind::Point => None,
+        }
+    }
+
+    /// Renders the depth of the model into the shadow map of every cascade and uploads the cascades.
+    ///
+    /// The cascades of a directional light split the view frustum up to the shadow distance,
+    /// a spot light has a single cascade covering its cone. The state of the main pass is restored afterwards.
+    fn render_shadow_maps(
+        &mut self,
+        model: &mut Model,
+        camera: &Camera,
+        gui_state: &Gui,
+        target: &RenderTarget,
+    ) {
+        // The picking doesn't use the shadows, the maps of the last frame stay in place
+        if self.id_pass {
+            return;
+        }
+
+        self.shadows.inner.cascade_count = 0;
+        let shadows = gui_state.shadows
+            && !gui_state
+                .auto_quality
+                .is_disabled(QualityFeature::ShadowMaps);
+        let caster = match self.shadow_caster() {
+            Some(caster) if shadows && !self.shadow_maps_failed => caster,
+            _ => {
+                self.shadows.update();
+                return;
+            }
+        };
+
+        let shadow_maps = match self.shadow_maps.take() {
+            Some(shadow_maps) => shadow_maps,
+            None => match ShadowMaps::new() {
+                Ok(shadow_maps) => shadow_maps,
+                Err(e) => {
+                    eprintln!("WARN: couldn't create the shadow maps: {e:?}");
+                    self.shadow_maps_failed = true;
+                    self.shadows.update();
+                    return;
+                }
+            },
+        };
+
+        let (projection, view) = (self.transforms.inner.projection, self.transforms.inner.view);
+        let bounds = model.bounds();
+
+        let (splits, light_spaces) = match caster {
+            ShadowCaster::Directional(light_dir) => {
+                let aspect_ratio = target.width as f32 / target.height as f32;
+                let near = camera.near.max(1e-3);
+                let far = gui_state.shadow_distance.min(camera.far).max(near * 1.01);
+                let count = gui_state.shadow_cascades.clamp(1, MAX_CASCADES);
+                let splits =
+                    shadow_maps::cascade_splits(near, far, count, gui_state.shadow_split_lambda);
+
+                let starts = std::iter::once(near).chain(splits.iter().copied());
+                let light_spaces = starts
+                    .zip(&splits)
+                    .map(|(start, &end)| {
+                        shadow_maps::cascade_matrix(
+                            camera,
+                            view,
+                            aspect_ratio,
+                            (start, end),
+                            light_dir,
+                            &bounds,
+                        )
+                    })
+                    .collect();
+
+                (splits, light_spaces)
+            }
+            ShadowCaster::Spot {
+                pos,
+                dir,
+                outer_cone_angle,
+                range,
+            } => {
+                let light_space =
+                    shadow_maps::spot_matrix(pos, dir, outer_cone_angle, range, &bounds);
+                (vec![f32::MAX], vec![light_space])
+            }
+        };
+
+        let camera_frustum =
+            std::mem::replace(&mut self.frustum, Frustum::from_matrix(Mat4::IDENTITY));
+
+        unsafe {
+            set_depth_range(true, false);
+            gl::Enable(gl::DEPTH_TEST);
+            // Both sides cast shadows, the slope-scaled offset prevents the self-shadowing
+            gl::Disable(gl::CULL_FACE);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(2., 4.);
+        }
+
+        self.shadow_pass = true;
+        for (cascade, light_space) in light_spaces.into_iter().enumerate() {
+            self.shadows.inner.light_space[cascade] = light_space;
+            self.transforms.inner.projection = light_space;
+            self.transforms.inner.view = Mat4::IDENTITY;
+            self.frustum = Frustum::from_matrix(light_space);
+
+            shadow_maps.begin_cascade(cascade);
+            self.render_node(&mut model.root, model.transform, gui_state);
+        }
+        self.shadow_pass = false;
+
+        self.transforms.inner.projection = projection;
+        self.transforms.inner.view = view;
+        self.frustum = camera_frustum;
+
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+            let wireframe_offset = if camera.reversed_z { 1. } else { -1. };
+            gl::PolygonOffset(wireframe_offset, wireframe_offset);
+
+            set_depth_range(camera.reversed_z, camera.reversed_z);
+            if !gui_state.depth_test {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+            if gui_state.face_culling {
+                gl::Enable(gl::CULL_FACE);
+            }
+            set_polygon_mode(gui_state);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+            gl::Viewport(0, 0, target.width as i32, target.height as i32);
+
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_MAP_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, shadow_maps.texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        let count = splits.len();
+        let mut cascade_splits = [splits[count - 1]; MAX_CASCADES];
+        cascade_splits[..count].copy_from_slice(&splits);
+
+        let shadows = &mut self.shadows.inner;
+        shadows.cascade_splits = Vec4::from(cascade_splits);
+        shadows.cascade_count = count as u32;
+        shadows.cascade_debug = gui_state.shadow_cascade_debug;
+        shadows.zero_to_one_depth = gl::ClipControl::is_loaded();
+        self.shadows.update();
+
+        self.shadow_maps = Some(shadow_maps);
+    }
+
+    /// Records the position of the root of the model in the current frame and draws its recent path.
+    ///
+    /// The root is the root joint of the skin, or the first node of the scene for the models without skins.
+    fn draw_root_trail(&mut self, model: &Model, gui_state: &Gui) {
+        let root_pos = self.skin_root.or_else(|| {
+            let root = model.root.children.first()?;
+            Some((model.transform * root.transform).transform_point3(Vec3::ZERO))
+        });
+
+        if let Some(pos) = root_pos {
+            let playhead = model.animations.active_animation().map(|active| {
+                let time = model.animations.animations[active].current_time;
+                (active, time)
+            });
+
+            self.root_trail.record(
+                pos,
+                gui_state.selected_model,
+                playhead,
+                gui_state.root_trail_length,
+            );
+        }
+
+        let color = Vec4::from(gui_state.root_trail_color);
+        for (segments, opacity) in self.root_trail.faded_segments() {
+            self.line_renderer.draw(
+                &segments,
+                color * Vec4::new(1., 1., 1., opacity),
+                self.overlay_line_width,
+                self.viewport_size,
+            );
+        }
+    }
+
+    /// Draws the blob shadow below the root of the model (it follows the root motion), on the bottom of its bounds
+    fn draw_blob_shadow(&self, bounds: &Aabb, gui_state: &Gui) {
+        let position = self.skin_root.unwrap_or_else(|| bounds.center());
+        self.blob_shadow
+            .draw(bounds, position, gui_state.blob_shadow_opacity);
+
+        unsafe {
+            if gui_state.face_culling {
+                gl::Enable(gl::CULL_FACE);
+            }
+            set_polygon_mode(gui_state);
+        }
+    }
+
+    /// Draws the bounding boxes collected in the current frame, colored by their visibility
+    fn draw_debug_boxes(&self, gui_state: &Gui) {
+        let groups = [
+            (true, gui_state.bounds_color),
+            (false, gui_state.culled_bounds_color),
+        ];
+
+        for (in_frustum, color) in groups {
+            let boxes: Vec<Aabb> = self
+                .debug_boxes
+                .iter()
+                .filter(|(_, visible)| *visible == in_frustum)
+                .map(|(aabb, _)| *aabb)
+                .collect();
+
+            let segments = bounds_mesh::box_segments(&boxes);
+            self.line_renderer.draw(
+                &segments,
+                Vec4::from(color),
+                self.overlay_line_width,
+                self.viewport_size,
+            );
+        }
     }
 
     /// Recursive - traverses the node hierarchy and handles each node.
     fn render_node(&mut self, node: &mut Node, outer_transform: Mat4, gui_state: &Gui) {
+        self.apply_node_transforms(node);
+
         let next_level_transform = outer_transform * node.transform;
 
+        // Every skin has its own matrices, they are uploaded before drawing the mesh of the node
         if let Some(joints) = &mut node.joints {
-            self.recalc_skin_matrices(&mut joints.joints, next_level_transform, gui_state);
+            self.recalc_skin_matrices(joints, next_level_transform, gui_state);
         }
 
-        if gui_state.mesh_visible {
+        if gui_state.mesh_visible || self.wireframe {
             if let Some(mesh) = &node.mesh {
                 let do_skinning = node.joints.is_some();
                 self.settings.inner.do_skinning = do_skinning;
                 self.settings.update();
 
-                self.render_mesh(mesh, next_level_transform);
+                let skin_determinant = do_skinning.then_some(self.skin_determinant);
+
+                if let Some(front_face) = mesh_front_face(skin_determinant, next_level_transform) {
+                    // Mirrored transforms flip the winding order of the triangles,
+                    // the state is only changed for them (the default is CCW)
+                    let mirrored = front_face == gl::CW;
+                    if mirrored {
+                        unsafe {
+                            gl::FrontFace(front_face);
+                        }
+
+                        if self.is_main_pass() {
+                            self.state_changes.front_face_flips += 1;
+                        }
+                    }
+
+                    self.render_mesh(
+                        mesh,
+                        node.index,
+                        next_level_transform,
+                        do_skinning,
+                        gui_state,
+                    );
+
+                    if mirrored {
+                        unsafe {
+                            gl::FrontFace(gl::CCW);
+                        }
+                    }
+                }
             }
         }
 
@@ -142,115 +956,396 @@ impl Renderer {
     }
 
     /// Renders the mesh of a node
-    fn render_mesh(&mut self, mesh: &Mesh, node_transform: Mat4) {
+    fn render_mesh(
+        &mut self,
+        mesh: &Mesh,
+        node: usize,
+        node_transform: Mat4,
+        skinned: bool,
+        gui_state: &Gui,
+    ) {
         self.transforms.inner.model = node_transform;
         self.transforms.update();
 
-        let draw_mesh = |vao: u32, prim: &Primitive| unsafe {
-            gl::BindVertexArray(vao);
+        for (primitive, prim) in mesh.primitives.iter().enumerate() {
+            let bounds = self.primitive_bounds(prim, node_transform, skinned);
+            let in_frustum = bounds
+                .as_ref()
+                .is_none_or(|bounds| bounds.intersects(&self.frustum));
 
-            gl::DrawElements(
-                gl::TRIANGLES,
-                prim.indices.len() as i32,
-                prim.indices.gl_type(),
-                ptr::null(),
-            );
+            if gui_state.draw_primitive_bounds && self.is_main_pass() {
+                if let Some(bounds) = &bounds {
+                    self.debug_boxes.push((bounds.aabb(), in_frustum));
+                }
+            }
 
-            gl::BindVertexArray(0);
-        };
+            if self.frustum_culling && !in_frustum {
+                if self.is_main_pass() {
+                    self.culled_primitives += 1;
+                }
+
+                continue;
+            }
+
+            if self.id_pass {
+                if gui_state.mesh_visible {
+                    self.render_primitive_id(prim, PickTarget { node, primitive });
+                }
 
-        for prim in &mesh.primitives {
-            match prim.texture_info {
-                PrimitiveTexture::None { base_color_factor } => {
-                    self.material.inner.base_color_factor = base_color_factor;
-                    self.material.update();
+                continue;
+            }
 
-                    self.color_shader.render(|| {
-                        draw_mesh(prim.vao, prim);
+            if self.shadow_pass {
+                if gui_state.mesh_visible {
+                    self.shadow_shader.render(|| {
+                        draw_primitive(prim);
                     });
                 }
-                PrimitiveTexture::Some {
-                    gl_id,
-                    base_color_factor,
-                } => {
-                    self.material.inner.base_color_factor = base_color_factor;
-                    self.material.update();
-
-                    unsafe {
-                        gl::BindTexture(gl::TEXTURE_2D, gl_id);
-                    }
 
-                    self.texture_shader.render(|| {
-                        draw_mesh(prim.vao, prim);
-                    });
+                continue;
+            }
+
+            if gui_state.mesh_visible {
+                self.render_primitive(prim);
+            }
+
+            if self.wireframe {
+                self.render_wireframe(prim, gui_state);
+            }
+        }
+    }
+
+    /// World space bounds of the primitive in the current frame, None if they are unknown.
+    ///
+    /// Static primitives are bounded by their bounding box, skinned primitives by a bounding sphere
+    /// around the current pose of the joints.
+    fn primitive_bounds(
+        &self,
+        prim: &Primitive,
+        node_transform: Mat4,
+        skinned: bool,
+    ) -> Option<Bounds> {
+        match (&prim.skin, skinned) {
+            (_, false) => Some(Bounds::Box(prim.aabb.transformed(node_transform))),
+            (Some(skin), true) => self
+                .skin_bounding_sphere(skin)
+                .map(|(center, radius)| Bounds::Sphere { center, radius }),
+            // The vertices are transformed by the joints, but their influences are unknown
+            (None, true) => None,
+        }
+    }
+
+    /// Bounding sphere of the skinned primitive in the current pose of the joints.
+    ///
+    /// A skinned vertex is a weighted average of the vertex transformed by its joints,
+    /// so it's inside of the union of the spheres around the influencing joints.
+    fn skin_bounding_sphere(&self, skin: &PrimSkin) -> Option<(Vec3, f32)> {
+        let spheres = || {
+            skin.joint_radii.iter().filter_map(|(joint, radius)| {
+                let transform = self.joint_world_transforms.get(*joint)?;
+                let scale = transform
+                    .x_axis
+                    .truncate()
+                    .length()
+                    .max(transform.y_axis.truncate().length())
+                    .max(transform.z_axis.truncate().length());
+
+                Some((transform.w_axis.truncate(), radius * scale))
+            })
+        };
+
+        let mut aabb = Aabb::new_empty();
+        for (center, radius) in spheres() {
+            aabb.extend(center - Vec3::splat(radius));
+            aabb.extend(center + Vec3::splat(radius));
+        }
+
+        if aabb.is_empty() {
+            return None;
+        }
+
+        let center = aabb.center();
+        let radius = spheres().fold(0f32, |max, (c, r)| max.max(c.distance(center) + r));
+
+        Some((center, radius))
+    }
+
+    /// Renders the primitive with its material
+    fn render_primitive(&mut self, prim: &Primitive) {
+        self.bind_pbr_material(&prim.pbr);
+
+        // The other primitives keep the blending, the masked ones are either opaque or covered partially
+        let alpha_to_coverage =
+            self.settings.inner.alpha_to_coverage && prim.pbr.alpha_cutoff.is_some();
+        if alpha_to_coverage {
+            unsafe {
+                gl::Enable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+                gl::Disable(gl::BLEND);
+            }
+        }
+
+        let animated_base_color =
+            self.animated_material_value(prim.pbr.material, MaterialProperty::BaseColorFactor);
+
+        match prim.texture_info {
+            PrimitiveTexture::None { base_color_factor } => {
+                self.material.inner.base_color_factor =
+                    animated_base_color.unwrap_or(base_color_factor);
+                self.update_material();
+
+                self.color_shader.render(|| {
+                    draw_primitive(prim);
+                });
+            }
+            PrimitiveTexture::Some {
+                gl_id,
+                base_color_factor,
+            } => {
+                self.material.inner.base_color_factor =
+                    animated_base_color.unwrap_or(base_color_factor);
+                self.update_material();
+
+                if self.bind_texture(0, gl_id) {
+                    if let Some(anisotropy) = self.anisotropy {
+                        unsafe {
+                            gl::TexParameterf(
+                                gl::TEXTURE_2D,
+                                ogl::TEXTURE_MAX_ANISOTROPY,
+                                anisotropy,
+                            );
+                        }
+                    }
                 }
-            };
+
+                self.texture_shader.render(|| {
+                    draw_primitive(prim);
+                });
+            }
+        };
+
+        if alpha_to_coverage {
+            unsafe {
+                gl::Disable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+                gl::Enable(gl::BLEND);
+            }
+        }
+    }
+
+    /// Renders the primitive with a new object ID
+    fn render_primitive_id(&mut self, prim: &Primitive, target: PickTarget) {
+        self.pick_targets.push(target);
+        let id = self.pick_targets.len() as u32;
+
+        self.id_shader.render(|| {
+            self.id_shader.set_u32(id, "objectId\0");
+            draw_primitive(prim);
+        });
+    }
+
+    /// The animated value of the material property in the current frame, None if it isn't animated
+    fn animated_material_value(
+        &self,
+        material: Option<usize>,
+        property: MaterialProperty,
+    ) -> Option<Vec4> {
+        let material = material?;
+
+        self.material_animation_values
+            .iter()
+            .find(|value| value.material == material && value.property == property)
+            .map(|value| value.value)
+    }
+
+    /// Sets the material parameters and binds the textures to texture units 1, 2 and 3.
+    ///
+    /// The material uniform buffer is updated together with the base color.
+    fn bind_pbr_material(&mut self, pbr: &PbrMaterial) {
+        let emissive_factor = self
+            .animated_material_value(pbr.material, MaterialProperty::EmissiveFactor)
+            .map(|value| value.truncate())
+            .unwrap_or(pbr.emissive_factor);
+
+        let material = &mut self.material.inner;
+        material.metallic_factor = pbr.metallic_factor;
+        material.roughness_factor = pbr.roughness_factor;
+        material.has_metallic_roughness_texture = pbr.metallic_roughness_texture.is_some();
+        material.has_occlusion_texture = pbr.occlusion_texture.is_some();
+        material.occlusion_strength = pbr.occlusion_strength;
+        material.has_normal_texture = pbr.normal_texture.is_some();
+        material.normal_scale = pbr.normal_scale;
+        material.emissive_factor = emissive_factor;
+        material.unlit = pbr.unlit;
+        material.alpha_cutoff = pbr.alpha_cutoff.unwrap_or(-1.);
+
+        let textures = [
+            (1, pbr.metallic_roughness_texture),
+            (2, pbr.occlusion_texture),
+            (3, pbr.normal_texture),
+        ];
+
+        for (unit, texture) in textures {
+            if let Some(gl_id) = texture {
+                self.bind_texture(unit, gl_id);
+            }
         }
     }
 
+    /// Updates the material uniform buffer if the material differs from the last uploaded one
+    fn update_material(&mut self) {
+        if self.uploaded_material.as_ref() == Some(&self.material.inner) {
+            self.state_changes.skipped_material_uploads += 1;
+            return;
+        }
+
+        self.material.update();
+        self.uploaded_material = Some(self.material.inner.clone());
+        self.state_changes.material_uploads += 1;
+    }
+
+    /// Binds the texture to the texture unit if it isn't bound already.
+    ///
+    /// Returns true if the texture was bound. Texture unit 0 stays active.
+    fn bind_texture(&mut self, unit: usize, gl_id: u32) -> bool {
+        if self.bound_textures[unit] == Some(gl_id) {
+            self.state_changes.skipped_texture_binds += 1;
+            return false;
+        }
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+            gl::BindTexture(gl::TEXTURE_2D, gl_id);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        self.bound_textures[unit] = Some(gl_id);
+        self.state_changes.texture_binds += 1;
+        true
+    }
+
+    /// Renders the edges of the primitive with the wireframe color.
+    ///
+    /// The lines are offset towards the camera, so they are drawn over the filled mesh.
+    fn render_wireframe(&mut self, prim: &Primitive, gui_state: &Gui) {
+        let shading_mode = self.settings.inner.shading_mode;
+
+        self.settings.inner.shading_mode = ShadingMode::Unlit;
+        self.settings.update();
+
+        self.material.inner.base_color_factor = Vec4::from(gui_state.wireframe_color);
+        self.update_material();
+
+        let [min_width, max_width] = self.line_width_range;
+
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+            gl::LineWidth(gui_state.wireframe_width.clamp(min_width, max_width));
+            gl::Enable(gl::POLYGON_OFFSET_LINE);
+        }
+
+        self.color_shader.render(|| {
+            draw_primitive(prim);
+        });
+
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_LINE);
+            gl::LineWidth(1.);
+            set_polygon_mode(gui_state);
+        }
+
+        self.settings.inner.shading_mode = shading_mode;
+        self.settings.update();
+    }
+
     /// Recalculates the skin matrices for each joint
     pub fn recalc_skin_matrices(
         &mut self,
-        joints: &mut [Joint],
+        joints: &mut Joints,
         outer_transform: Mat4,
         gui_state: &Gui,
     ) {
-        self.apply_joint_transforms(joints);
+        joints.apply_animation(&self.node_animation_transforms);
 
         // world transforms of each joint after applying the animation for the current frame
-        let mut world_transforms = vec![Mat4::IDENTITY; joints.len()];
-
-        // Cascade transformation from parent joints to child joints.
-        // Parent joints are always placed before child joints in the buffer, so they are processed first.
-        for i in 0..joints.len() {
-            let transform = match joints[i].parent {
-                Some(parent_index) => world_transforms[parent_index] * joints[i].transform.matrix(),
-                None => outer_transform * joints[i].transform.matrix(),
-            };
+        let mut world_transforms = std::mem::take(&mut self.joint_world_transforms);
+        joints.world_transforms(
+            outer_transform,
+            self.look_at.as_ref(),
+            &mut world_transforms,
+        );
 
-            world_transforms[i] = transform;
+        if gui_state.draw_skeleton && self.is_main_pass() {
+            self.debug_joints(&world_transforms, &joints.joints, gui_state.selected_joint);
         }
 
-        if gui_state.draw_skeleton {
-            self.debug_joints(&world_transforms, joints);
-        }
+        joints.joint_matrices(&world_transforms, &mut self.joint_transforms.inner.matrices);
+        self.joint_transforms.update();
 
-        let joint_matrices = &mut self.joint_transforms.inner.matrices;
-        joint_matrices.clear();
+        self.skin_determinant = joints.determinant(&self.joint_transforms.inner.matrices);
 
-        for (i, joint) in joints.iter().enumerate() {
-            let mat = world_transforms[i] * joint.inverse_bind_matrix;
-            joint_matrices.push(mat);
-        }
+        let joints = &joints.joints;
 
-        self.joint_transforms.update();
+        // The selected joint is highlighted by comparing it with the joint indices of the vertices
+        self.settings.inner.selected_joint = joints
+            .get(gui_state.selected_joint)
+            .map_or(u32::MAX, |joint| joint.skin_index as u32);
+        self.settings.update();
+
+        // The trail follows the first skin of the model
+        if self.skin_root.is_none() {
+            self.skin_root = world_transforms
+                .first()
+                .map(|root| root.transform_point3(Vec3::ZERO));
+        }
+        self.joint_world_transforms = world_transforms;
     }
 
-    /// Draws a debug view of the skeleton
-    fn debug_joints(&mut self, world_transforms: &[Mat4], joints: &[Joint]) {
+    /// Draws a debug view of the skeleton, the selected joint is highlighted
+    fn debug_joints(&mut self, world_transforms: &[Mat4], joints: &[Joint], selected_joint: usize) {
+        let shading_mode = self.settings.inner.shading_mode;
+
         self.settings.inner.do_skinning = false;
+        self.settings.inner.shading_mode = ShadingMode::Unlit;
         self.settings.update();
 
         self.material.inner.base_color_factor = Vec4::new(0.85, 0.08, 0.7, 1.0);
-        self.material.update();
+        self.update_material();
 
         let tmp = self.transforms.inner.model;
         self.transforms.inner.model = Mat4::IDENTITY;
         self.transforms.update();
 
-        skeleton_mesh::draw_joints(world_transforms, &self.color_shader);
+        skeleton_mesh::draw_joints(world_transforms, 4., &self.color_shader);
 
-        self.material.inner.base_color_factor = Vec4::new(0.1, 0.3, 0.7, 1.0);
-        self.material.update();
+        if selected_joint < world_transforms.len() {
+            self.material.inner.base_color_factor = Vec4::new(1.0, 0.85, 0.1, 1.0);
+            self.update_material();
 
-        skeleton_mesh::draw_bones(world_transforms, joints, &self.color_shader);
+            let selected = &world_transforms[selected_joint..=selected_joint];
+            skeleton_mesh::draw_joints(selected, 9., &self.color_shader);
+        }
+
+        let bones = skeleton_mesh::bone_segments(world_transforms, joints);
+        self.line_renderer.draw(
+            &bones,
+            Vec4::new(0.1, 0.3, 0.7, 1.0),
+            self.overlay_line_width,
+            self.viewport_size,
+        );
 
         self.transforms.inner.model = tmp;
         self.transforms.update();
+
+        self.settings.inner.shading_mode = shading_mode;
+        self.settings.update();
     }
 
     /// Recalculates the animation transform for the current time / animation
-    fn recalculate_animation(&mut self, model: &mut Model) {
+    fn recalculate_animation(&mut self, model: &mut Model, gui_state: &Gui) {
+        if model.animations.blend_tree.enabled && self.sample_blend_tree(model) {
+            self.substitute_disabled_channels(model, gui_state);
+            return;
+        }
+
         let active_animation = match model.animations.animation_control {
             AnimationControl::Loop {
                 active_animation,
@@ -258,83 +1353,463 @@ impl Renderer {
             } => {
                 let anim = &mut model.animations.animations[active_animation];
 
-                // Calculate current time inside the animation
-                let mut since_start = Instant::now().duration_since(start_time).as_secs_f32();
-                if since_start > anim.end_time {
-                    since_start %= anim.end_time;
+                // Calculate current time inside the animation (the fixed timestep advances it once per frame instead)
+                if gui_state.fixed_timestep().is_none() {
+                    let since_start = Instant::now().duration_since(start_time).as_secs_f32();
+                    anim.current_time = anim.loop_time(since_start);
                 }
-
-                anim.current_time = since_start;
                 active_animation
             }
             AnimationControl::Controllable { active_animation } => active_animation,
-            AnimationControl::Static => return,
+            AnimationControl::Static => {
+                model.animations.emit_marker_events();
+                return;
+            }
         };
 
+        model.animations.emit_marker_events();
+
         self.node_animation_transforms.clear();
         let anim = &model.animations.animations[active_animation];
-        let current_time = anim.current_time;
+        anim.sample_into(
+            anim.current_time,
+            self.interpolation,
+            &mut self.node_animation_transforms,
+        );
+        Self::sample_material_channels(
+            anim,
+            anim.current_time,
+            self.interpolation,
+            &mut self.material_animation_values,
+        );
+
+        self.apply_crossfade(model);
+        self.substitute_disabled_channels(model, gui_state);
+    }
 
-        // Interpolate the animation transforms
-        for channel in &anim.channels {
-            let keyframe_times = &channel.keyframe_times;
+    /// Replaces the transforms of the channels disabled in the GUI with the rest pose of the nodes
+    fn substitute_disabled_channels(&mut self, model: &Model, gui_state: &Gui) {
+        if !(gui_state.disable_translation || gui_state.disable_rotation || gui_state.disable_scale)
+        {
+            return;
+        }
 
-            'inner: for i in 0..keyframe_times.len() {
-                let start_time = keyframe_times[i];
+        let rest_transforms = model.rest_transforms();
 
-                // If the current time is before the start time of this specific channel, take the first transform.
-                if (i == keyframe_times.len() - 1) || (i == 0 && current_time < start_time) {
-                    let transform = channel.get_fixed_transform(i);
-                    self.node_animation_transforms
-                        .push(NodeAnimationTransform::new(channel.node, transform));
-                    break 'inner;
+        for nat in &mut self.node_animation_transforms {
+            let rest = match rest_transforms.get(&nat.node) {
+                Some(rest) => rest,
+                None => continue,
+            };
+
+            let substitute = match nat.transform {
+                AnimationTransform::Translation(_) if gui_state.disable_translation => {
+                    AnimationTransform::Translation(rest.translation)
+                }
+                AnimationTransform::Rotation(_) if gui_state.disable_rotation => {
+                    AnimationTransform::Rotation(rest.rotation)
                 }
+                AnimationTransform::Scale(_) if gui_state.disable_scale => {
+                    AnimationTransform::Scale(rest.scale)
+                }
+                _ => continue,
+            };
+
+            nat.transform = substitute;
+        }
+    }
 
-                let end_time = keyframe_times[i + 1];
+    /// Blends the current animation transforms with the previous animation if the animations are being crossfaded
+    fn apply_crossfade(&mut self, model: &mut Model) {
+        let animations = &mut model.animations;
 
-                if start_time <= current_time && end_time > current_time {
-                    let coeff = (current_time - start_time) / (end_time - start_time);
+        let crossfade = match &animations.crossfade {
+            Some(crossfade) => crossfade,
+            None => return,
+        };
 
-                    let transform = channel.interpolate_transforms(i, coeff);
+        let elapsed = Instant::now()
+            .duration_since(crossfade.start_time)
+            .as_secs_f32();
 
-                    self.node_animation_transforms
-                        .push(NodeAnimationTransform::new(channel.node, transform));
-                    break 'inner;
-                }
+        if elapsed >= crossfade.duration {
+            animations.crossfade = None;
+            return;
+        }
+
+        let from_anim = &animations.animations[crossfade.from_animation];
+
+        // The previous animation keeps playing while it's being faded out
+        let from_time = match animations.animation_control {
+            AnimationControl::Loop { .. } => {
+                from_anim.loop_time(crossfade.from_time - from_anim.trim_start + elapsed)
             }
+            _ => crossfade.from_time,
+        };
+
+        let mut from_transforms = Vec::new();
+        from_anim.sample_into(from_time, self.interpolation, &mut from_transforms);
+
+        let coeff = elapsed / crossfade.duration;
+        Self::blend_from(&mut self.node_animation_transforms, &from_transforms, coeff);
+    }
+
+    /// Samples the clips of the blend tree and blends them by the blend parameter.
+    ///
+    /// Returns false if the blend tree doesn't reference any valid clips.
+    fn sample_blend_tree(&mut self, model: &mut Model) -> bool {
+        let animations = &mut model.animations;
+
+        let sample = match animations.blend_tree.advance(&animations.animations) {
+            Some(sample) => sample,
+            None => return false,
+        };
+
+        let (from_index, from_time) = sample.from;
+        let (to_index, to_time) = sample.to;
+
+        self.node_animation_transforms.clear();
+        let to_anim = &animations.animations[to_index];
+        to_anim.sample_into(
+            to_time,
+            self.interpolation,
+            &mut self.node_animation_transforms,
+        );
+        // Material properties aren't blended, they are taken from the dominant clip
+        Self::sample_material_channels(
+            to_anim,
+            to_time,
+            self.interpolation,
+            &mut self.material_animation_values,
+        );
+
+        if from_index != to_index {
+            let mut from_transforms = Vec::new();
+            let from_anim = &animations.animations[from_index];
+            from_anim.sample_into(from_time, self.interpolation, &mut from_transforms);
+
+            Self::blend_from(
+                &mut self.node_animation_transforms,
+                &from_transforms,
+                sample.coeff,
+            );
         }
+
+        true
     }
 
-    /// Appplies the current animatoin transforms to the joints
-    fn apply_joint_transforms(&self, joints: &mut [Joint]) {
-        for joint in joints {
-            for nat in &self.node_animation_transforms {
-                if joint.node_index == nat.node {
-                    match nat.transform {
-                        AnimationTransform::Translation(trans) => {
-                            joint.transform.translation = trans;
-                        }
-                        AnimationTransform::Rotation(rot) => {
-                            joint.transform.rotation = rot;
-                        }
-                        AnimationTransform::Scale(scale) => joint.transform.scale = scale,
-                    }
-                }
+    /// Blends the transforms from the 'from' transforms to the 'to' transforms by the coefficient.
+    ///
+    /// Transforms that don't have a counterpart in 'from' are kept unchanged.
+    fn blend_from(to: &mut [NodeAnimationTransform], from: &[NodeAnimationTransform], coeff: f32) {
+        for nat in to {
+            let blended = from
+                .iter()
+                .filter(|from| from.node == nat.node)
+                .find_map(|from| from.transform.blend(&nat.transform, coeff));
+
+            if let Some(blended) = blended {
+                nat.transform = blended;
+            }
+        }
+    }
+
+    /// Samples the material channels of the animation at a specific time
+    fn sample_material_channels(
+        anim: &Animation,
+        current_time: f32,
+        interpolation: Option<Interpolation>,
+        material_animation_values: &mut Vec<MaterialAnimationValue>,
+    ) {
+        material_animation_values.clear();
+
+        for channel in &anim.material_channels {
+            material_animation_values.push(MaterialAnimationValue {
+                material: channel.material,
+                property: channel.property,
+                value: channel
+                    .sample_with(current_time, interpolation.unwrap_or(channel.interpolation)),
+            });
+        }
+    }
+
+    /// Applies the current animation transforms to a node that isn't a joint (rigid node animation).
+    ///
+    /// Channels missing from the animation use the rest transform,
+    /// nodes that aren't targeted by the animation at all keep their current transform.
+    fn apply_node_transforms(&self, node: &mut Node) {
+        if node.is_joint {
+            return;
+        }
+
+        let mut transform = node.rest_transform;
+        let mut animated = false;
+
+        for nat in &self.node_animation_transforms {
+            if nat.node == node.index {
+                transform.apply_animation(&nat.transform);
+                animated = true;
+            }
+        }
+
+        if animated {
+            node.transform = transform.matrix();
+        }
+    }
+}
+
+/// Number of the samples per pixel of the bound framebuffer (0 if it isn't multisampled)
+fn target_samples() -> i32 {
+    let mut samples = 0;
+    unsafe {
+        gl::GetIntegerv(gl::SAMPLES, &mut samples);
+    }
+    samples
+}
+
+/// Issues the draw call for the primitive
+fn draw_primitive(prim: &Primitive) {
+    unsafe {
+        if let Some((vao, vertex_count)) = prim.flat_vao() {
+            gl::BindVertexArray(vao);
+            gl::DrawArrays(prim.mode, 0, vertex_count as i32);
+            gl::BindVertexArray(0);
+            return;
+        }
+
+        gl::BindVertexArray(prim.vao);
+
+        // The size of the points is written by the vertex shader
+        let points = prim.mode == gl::POINTS;
+        if points {
+            gl::Enable(gl::PROGRAM_POINT_SIZE);
+        }
+
+        match prim.index_type() {
+            Some(index_type) => {
+                gl::DrawElements(prim.mode, prim.draw_count() as i32, index_type, ptr::null())
             }
+            None => gl::DrawArrays(prim.mode, 0, prim.draw_count() as i32),
+        }
+
+        if points {
+            gl::Disable(gl::PROGRAM_POINT_SIZE);
         }
+
+        gl::BindVertexArray(0);
     }
 }
 
-/// A struct that holds which transforms should be aplied to which nodes for the current frame
-struct NodeAnimationTransform {
-    /// Index of the node
-    node: usize,
-    /// Transform that should overwrite the node's current transform
-    transform: AnimationTransform,
+/// Sets the depth range of the clip space ([0, 1] or [-1, 1]) and the direction of the depth test.
+///
+/// Reversed-Z needs the [0, 1] depth range, which requires glClipControl (OpenGL 4.5).
+/// Without it the defaults are kept.
+unsafe fn set_depth_range(zero_to_one: bool, reversed_z: bool) {
+    if !gl::ClipControl::is_loaded() {
+        return;
+    }
+
+    let depth_mode = match zero_to_one {
+        true => gl::ZERO_TO_ONE,
+        false => gl::NEGATIVE_ONE_TO_ONE,
+    };
+    gl::ClipControl(gl::LOWER_LEFT, depth_mode);
+
+    if reversed_z {
+        gl::DepthFunc(gl::GREATER);
+        gl::ClearDepth(0.0);
+    } else {
+        gl::DepthFunc(gl::LESS);
+        gl::ClearDepth(1.0);
+    }
 }
 
-impl NodeAnimationTransform {
-    fn new(node: usize, transform: AnimationTransform) -> Self {
-        Self { node, transform }
+/// The winding order of the front faces of a mesh, None if the mesh isn't drawn.
+///
+/// The transform of skinned meshes is ignored, the determinant of their joints (see `Joints::determinant`)
+/// is used instead and they are always drawn. Zero-scaled meshes aren't visible, their normals can't be computed.
+fn mesh_front_face(skin_determinant: Option<f32>, transform: Mat4) -> Option<GLenum> {
+    let determinant = skin_determinant.unwrap_or_else(|| transform.determinant());
+
+    if determinant.abs() < MIN_DETERMINANT {
+        None
+    } else if determinant < 0. {
+        Some(gl::CW)
+    } else {
+        Some(gl::CCW)
+    }
+}
+
+/// Sets the face culling and polygon mode of the filled meshes.
+///
+/// When the skeleton is drawn, the back faces are drawn as lines so that the joints are visible.
+unsafe fn set_polygon_mode(gui_state: &Gui) {
+    if gui_state.draw_skeleton {
+        gl::CullFace(gl::FRONT);
+        gl::PolygonMode(gl::BACK, gl::LINE);
+    } else {
+        gl::CullFace(gui_state.culled_face.gl_enum());
+        gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+    }
+}
+
+/// Which faces are discarded when face culling is enabled
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CulledFace {
+    Back,
+    Front,
+    FrontAndBack,
+}
+
+impl CulledFace {
+    pub const ALL: [CulledFace; 3] = [
+        CulledFace::Back,
+        CulledFace::Front,
+        CulledFace::FrontAndBack,
+    ];
+
+    /// The corresponding glCullFace mode
+    fn gl_enum(self) -> GLenum {
+        match self {
+            CulledFace::Back => gl::BACK,
+            CulledFace::Front => gl::FRONT,
+            CulledFace::FrontAndBack => gl::FRONT_AND_BACK,
+        }
+    }
+}
+
+/// A framebuffer the scene is rendered into
+#[derive(Clone, Copy)]
+pub struct RenderTarget {
+    /// Framebuffer object, 0 is the default framebuffer of the window
+    pub framebuffer: u32,
+    /// Width of the viewport
+    pub width: u32,
+    /// Height of the viewport
+    pub height: u32,
+}
+
+impl RenderTarget {
+    /// The default framebuffer with the size of the window
+    pub fn window(window: &MyWindow) -> Self {
+        Self {
+            framebuffer: 0,
+            width: window.width,
+            height: window.height,
+        }
+    }
+
+    /// The whole offscreen framebuffer
+    pub fn framebuffer(framebuffer: &Framebuffer) -> Self {
+        Self {
+            framebuffer: framebuffer.id,
+            width: framebuffer.width,
+            height: framebuffer.height,
+        }
+    }
+}
+
+/// Numbers of the uniform buffer uploads and texture binds of the materials,
+/// used to verify that unchanged materials aren't rebound
+#[derive(Clone, Copy, Default)]
+pub struct StateChanges {
+    pub material_uploads: u32,
+    /// Uploads skipped because the material didn't change
+    pub skipped_material_uploads: u32,
+    pub texture_binds: u32,
+    /// Binds skipped because the texture was already bound
+    pub skipped_texture_binds: u32,
+    /// Meshes drawn with the clockwise front faces (mirrored by their transforms)
+    pub front_face_flips: u32,
+}
+
+/// A primitive found by picking
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PickTarget {
+    /// Index of the node containing the mesh
+    pub node: usize,
+    /// Index of the primitive inside of the mesh
+    pub primitive: usize,
+}
+
+/// World space bounds of a primitive in the current frame
+enum Bounds {
+    Box(Aabb),
+    Sphere { center: Vec3, radius: f32 },
+}
+
+impl Bounds {
+    /// If the bounds are at least partially inside of the view frustum
+    fn intersects(&self, frustum: &Frustum) -> bool {
+        match self {
+            Bounds::Box(aabb) => frustum.intersects_aabb(aabb),
+            Bounds::Sphere { center, radius } => frustum.intersects_sphere(*center, *radius),
+        }
+    }
+
+    /// The bounding box of the bounds
+    fn aabb(&self) -> Aabb {
+        match *self {
+            Bounds::Box(aabb) => aabb,
+            Bounds::Sphere { center, radius } => Aabb {
+                min: center - Vec3::splat(radius),
+                max: center + Vec3::splat(radius),
+            },
+        }
+    }
+}
+
+/// The value of an animated material property for the current frame
+struct MaterialAnimationValue {
+    /// Index of the gltf material
+    material: usize,
+    property: MaterialProperty,
+    /// Emissive factors have a W component of 0
+    value: Vec4,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::model::{DataBundle, ImportOptions};
+
+    #[test]
+    fn mirrored_meshes_flip_the_winding() {
+        let path = Path::new("resources/mirrored_instances/MirroredInstances.gltf");
+        let mut progress = |_, _| {};
+        let (gltf, mut bundle) =
+            DataBundle::import(path, ImportOptions::default(), &mut progress).unwrap();
+        let scene = gltf.scenes().next().unwrap();
+
+        // The instances are in the root of the scene, their transforms are the world transforms
+        let instances: Vec<gltf::Node> = scene.nodes().filter(|n| n.mesh().is_some()).collect();
+        assert_eq!(instances.len(), 4);
+
+        for node in instances {
+            let transform = Mat4::from_cols_array_2d(&node.transform().matrix());
+
+            // The skinning matrices are calculated the same way as in `recalc_skin_matrices`
+            let skin_determinant = node.skin().map(|skin| {
+                let joints = Joints::from_gltf(&mut bundle, &skin, &scene).unwrap();
+                let mut world_transforms = Vec::new();
+                joints.world_transforms(transform, None, &mut world_transforms);
+                let mut matrices = Vec::new();
+                joints.joint_matrices(&world_transforms, &mut matrices);
+                joints.determinant(&matrices)
+            });
+
+            let expected = match node.name() {
+                Some("Original") => gl::CCW,
+                _ => gl::CW,
+            };
+            assert_eq!(
+                mesh_front_face(skin_determinant, transform),
+                Some(expected),
+                "{:?}",
+                node.name()
+            );
+        }
     }
 }
@@ -0,0 +1,159 @@
+use eyre::{Result, WrapErr};
+use glam::{Mat4, Vec3};
+
+use crate::ogl::shader::Shader;
+
+/// An equirectangular HDR image drawn behind the models, sampled by the direction of the view.
+///
+/// The image is only a backdrop, it doesn't light the models.
+pub struct Background {
+    shader: Shader,
+    /// An empty VAO, the vertices of the fullscreen triangle are generated in the shader
+    vao: u32,
+    /// The loaded texture and the path of its image
+    image: Option<(String, u32)>,
+    /// The path of the image that couldn't be loaded (it isn't retried every frame)
+    failed_path: Option<String>,
+}
+
+impl Background {
+    pub fn new() -> Result<Self> {
+        let shader = Shader::from_file("shaders/vs_background.vert", "shaders/fs_background.frag")?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+
+        Ok(Self {
+            shader,
+            vao,
+            image: None,
+            failed_path: None,
+        })
+    }
+
+    /// Loads the image if the path differs from the loaded one, None removes the background.
+    ///
+    /// The images that can't be loaded are reported once and the background stays empty.
+    pub fn set_image(&mut self, path: Option<&str>) {
+        let loaded_path = self.image.as_ref().map(|(path, _)| path.as_str());
+        if path == loaded_path || (path.is_some() && path == self.failed_path.as_deref()) {
+            return;
+        }
+
+        if let Some((_, texture)) = self.image.take() {
+            unsafe {
+                gl::DeleteTextures(1, &texture);
+            }
+        }
+
+        let path = match path {
+            Some(path) => path,
+            None => return,
+        };
+
+        match Self::load_texture(path) {
+            Ok(texture) => {
+                self.image = Some((path.to_string(), texture));
+                self.failed_path = None;
+            }
+            Err(e) => {
+                eprintln!("WARN: couldn't load the background: {e:?}");
+                self.failed_path = Some(path.to_string());
+            }
+        }
+    }
+
+    /// If an image is loaded
+    pub fn is_loaded(&self) -> bool {
+        self.image.is_some()
+    }
+
+    /// Decodes the HDR image and uploads it into a floating-point texture
+    fn load_texture(path: &str) -> Result<u32> {
+        let image = image::open(path)
+            .wrap_err_with(|| format!("Couldn't open the image '{path}'"))?
+            .into_rgb32f();
+
+        let mut texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as i32,
+                image.width() as i32,
+                image.height() as i32,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                image.as_raw().as_ptr() as _,
+            );
+
+            // The image wraps around horizontally
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        println!(
+            "Loaded the background '{path}' ({}x{})",
+            image.width(),
+            image.height()
+        );
+
+        Ok(texture)
+    }
+
+    /// Draws the image over the whole viewport without the depth test, the texture is bound to unit 0.
+    ///
+    /// The rotation is in degrees around the Y axis, the exposure in stops.
+    pub fn draw(&self, projection: Mat4, view: Mat4, rotation: f32, exposure: f32) {
+        let texture = match &self.image {
+            Some((_, texture)) => *texture,
+            None => return,
+        };
+
+        let mut view_rotation = view;
+        view_rotation.w_axis = Vec3::ZERO.extend(1.);
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::DepthMask(gl::FALSE);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+
+            self.shader.render(|| {
+                self.shader
+                    .set_mat4(projection.inverse(), "inverseProjection\0");
+                self.shader
+                    .set_mat4(view_rotation.inverse(), "inverseViewRotation\0");
+                self.shader.set_f32(rotation.to_radians(), "rotation\0");
+                self.shader.set_f32(exposure, "exposure\0");
+
+                gl::BindVertexArray(self.vao);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                gl::BindVertexArray(0);
+            });
+
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+impl Drop for Background {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some((_, texture)) = &self.image {
+                gl::DeleteTextures(1, texture);
+            }
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use eyre::Result;
+use glam::{Vec2, Vec3};
+
+use crate::{model::Aabb, ogl::shader::Shader};
+
+/// How much larger than the footprint of the bounding box the blob is (it fades out towards the edge)
+const FOOTPRINT_SCALE: f32 = 0.75;
+
+/// A soft dark ellipse on the ground plane below a model, a cheap alternative to the shadow maps
+pub struct BlobShadow {
+    shader: Shader,
+    /// An empty VAO, the vertices of the quad are generated in the shader
+    vao: u32,
+}
+
+impl BlobShadow {
+    pub fn new() -> Result<Self> {
+        let shader = Shader::from_file("shaders/vs_blob.vert", "shaders/fs_blob.frag")?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+
+        Ok(Self { shader, vao })
+    }
+
+    /// The shader program of the blob
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// Draws the blob at the bottom of the world bounds of the model, centered below `position`.
+    ///
+    /// The transforms uniform buffer has to contain the projection and the view of the frame.
+    /// The blob doesn't write depth, so it's hidden only by the geometry drawn before it.
+    /// Face culling is disabled and the polygons are filled afterwards.
+    pub fn draw(&self, bounds: &Aabb, position: Vec3, opacity: f32) {
+        if bounds.is_empty() {
+            return;
+        }
+
+        let center = Vec3::new(position.x, bounds.min.y, position.z);
+        let size = bounds.size();
+        // Thin models (a single plane) still cast a visible blob
+        let min_radius = size.length() * 0.1;
+        let radii = Vec2::new(size.x, size.z) * FOOTPRINT_SCALE;
+        let radii = radii.max(Vec2::splat(min_radius));
+
+        unsafe {
+            gl::Disable(gl::CULL_FACE);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            gl::DepthMask(gl::FALSE);
+
+            self.shader.render(|| {
+                self.shader.set_vec3(center, "center\0");
+                self.shader.set_vec2(radii, "radii\0");
+                self.shader.set_f32(opacity, "opacity\0");
+
+                gl::BindVertexArray(self.vao);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                gl::BindVertexArray(0);
+            });
+
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+impl Drop for BlobShadow {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use glam::Vec3;
+
+use crate::model::Aabb;
+
+/// Pairs of the corners (indices into Aabb::corners) connected by the edges of a box
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// The line segments of the edges of the bounding boxes (pairs of world space points)
+pub fn box_segments(boxes: &[Aabb]) -> Vec<Vec3> {
+    let mut positions = Vec::new();
+
+    for aabb in boxes.iter().filter(|aabb| !aabb.is_empty()) {
+        let corners = aabb.corners();
+
+        for (start, end) in EDGES {
+            positions.push(corners[start]);
+            positions.push(corners[end]);
+        }
+    }
+
+    positions
+}
@@ -0,0 +1,54 @@
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::model::Aabb;
+
+/// The side planes of the view frustum, used to skip the primitives outside of the view.
+///
+/// The near and far planes aren't tested, their extraction depends on the depth range
+/// of the projection (reversed-Z, [0, 1] vs [-1, 1]) and the planes are usually fitted to the scene.
+pub struct Frustum {
+    /// (normal, distance) of the left, right, bottom and top planes, the normals point inside
+    planes: [Vec4; 4],
+}
+
+impl Frustum {
+    /// Extracts the planes from the view-projection matrix (Gribb-Hartmann)
+    pub fn from_matrix(view_projection: Mat4) -> Self {
+        let row = |i| view_projection.row(i);
+
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+        ]
+        .map(|plane| plane / plane.truncate().length().max(f32::EPSILON));
+
+        Self { planes }
+    }
+
+    /// If the sphere is at least partially inside of the frustum
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+
+    /// If the bounding box is at least partially inside of the frustum.
+    ///
+    /// Boxes that are outside of the frustum, but intersect multiple planes near the corners,
+    /// are reported as visible.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return true;
+        }
+
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // The corner farthest in the direction of the normal
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+
+            normal.dot(corner) + plane.w >= 0.
+        })
+    }
+}
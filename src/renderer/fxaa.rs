@@ -0,0 +1,201 @@
+use eyre::{eyre, Result};
+use glam::Vec2;
+
+use crate::ogl::shader::Shader;
+
+use super::RenderTarget;
+
+/// Anti-aliasing as a post-process, the scene is rendered into an offscreen texture and filtered into the target.
+///
+/// It's cheaper than MSAA, but it only sees the final colors, so it also softens the sharp details of the textures.
+pub struct Fxaa {
+    shader: Shader,
+    /// An empty VAO, the vertices of the fullscreen triangle are generated in the shader
+    vao: u32,
+    /// The offscreen framebuffer of the scene, recreated when the size of the target changes
+    scene: Option<SceneFramebuffer>,
+    /// The size the framebuffer couldn't be created with (it isn't retried every frame)
+    failed_size: Option<(u32, u32)>,
+}
+
+impl Fxaa {
+    pub fn new() -> Result<Self> {
+        let shader = Shader::from_file("shaders/vs_fxaa.vert", "shaders/fs_fxaa.frag")?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+
+        Ok(Self {
+            shader,
+            vao,
+            scene: None,
+            failed_size: None,
+        })
+    }
+
+    /// The offscreen target the scene should be rendered into, None if it couldn't be created
+    pub fn scene_target(&mut self, width: u32, height: u32) -> Option<RenderTarget> {
+        let resized = self
+            .scene
+            .as_ref()
+            .map(|scene| scene.width != width || scene.height != height)
+            .unwrap_or(true);
+
+        if resized && self.failed_size != Some((width, height)) {
+            // The old framebuffer is deleted before the new one is allocated
+            self.scene = None;
+
+            match SceneFramebuffer::new(width, height) {
+                Ok(scene) => {
+                    self.scene = Some(scene);
+                    self.failed_size = None;
+                }
+                Err(e) => {
+                    eprintln!("WARN: couldn't create the FXAA framebuffer: {e:?}");
+                    self.failed_size = Some((width, height));
+                }
+            }
+        }
+
+        let scene = self.scene.as_ref()?;
+        Some(RenderTarget {
+            framebuffer: scene.framebuffer,
+            width: scene.width,
+            height: scene.height,
+        })
+    }
+
+    /// Filters the rendered scene into the target, the target stays bound.
+    ///
+    /// The depth test, face culling and blending are disabled and the polygons are filled afterwards.
+    pub fn resolve(&self, target: &RenderTarget) {
+        let scene = match &self.scene {
+            Some(scene) => scene,
+            None => return,
+        };
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+            gl::Viewport(0, 0, target.width as i32, target.height as i32);
+
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Disable(gl::BLEND);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, scene.color);
+
+            self.shader.render(|| {
+                let size = Vec2::new(scene.width as f32, scene.height as f32);
+                self.shader.set_vec2(size.recip(), "inverseScreenSize\0");
+
+                gl::BindVertexArray(self.vao);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                gl::BindVertexArray(0);
+            });
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+}
+
+impl Drop for Fxaa {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// A framebuffer with a color texture (sampled by the filter) and a depth renderbuffer
+struct SceneFramebuffer {
+    framebuffer: u32,
+    color: u32,
+    depth: u32,
+    width: u32,
+    height: u32,
+}
+
+impl SceneFramebuffer {
+    fn new(width: u32, height: u32) -> Result<Self> {
+        let mut framebuffer = 0;
+        let mut color = 0;
+        let mut depth = 0;
+
+        let status = unsafe {
+            gl::GenTextures(1, &mut color);
+            gl::BindTexture(gl::TEXTURE_2D, color);
+            gl::TexStorage2D(gl::TEXTURE_2D, 1, gl::RGBA8, width as i32, height as i32);
+
+            // The filter samples between the pixels
+            let params = [
+                (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+            ];
+            for (name, value) in params {
+                gl::TexParameteri(gl::TEXTURE_2D, name, value as i32);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            gl::GenRenderbuffers(1, &mut depth);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH_COMPONENT24,
+                width as i32,
+                height as i32,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                color,
+                0,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                depth,
+            );
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            status
+        };
+
+        let scene = Self {
+            framebuffer,
+            color,
+            depth,
+            width,
+            height,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(eyre!("Framebuffer is incomplete: '{status:#x}'"));
+        }
+
+        Ok(scene)
+    }
+}
+
+impl Drop for SceneFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteRenderbuffers(1, &self.depth);
+            gl::DeleteTextures(1, &self.color);
+        }
+    }
+}
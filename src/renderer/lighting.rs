@@ -1,38 +1,220 @@
 use std::{mem::size_of, ptr};
 
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
 
-use crate::ogl::uniform_buffer::UniformBufferElement;
+use crate::{
+    model::LightKind,
+    ogl::uniform_buffer::{BlockLayout, UniformBufferElement},
+};
+
+/// Maximum number of the punctual lights, has to match the size of the arrays in the shaders
+pub const MAX_LIGHTS: usize = 8;
 
 /// Uniform buffer element that stores the lighing data
 pub struct Lighting {
     pub light_pos: Vec3,
+    /// Color of the constant ambient term
+    pub ambient_color: Vec3,
+    /// Multiplier of the ambient color
+    pub ambient_intensity: f32,
+    pub fog_mode: FogMode,
+    /// Color the distant fragments fade into (in the space of the output, like the background)
+    pub fog_color: Vec3,
+    /// Density of the exponential fog
+    pub fog_density: f32,
+    /// Distance from the camera where the linear fog starts
+    pub fog_start: f32,
+    /// Distance from the camera where the linear fog fully covers the fragments
+    pub fog_end: f32,
+    /// If the light at `light_pos` is used (the asset lights replace it)
+    pub viewer_light: bool,
+    /// The lights of the asset and the spot lights of the user, only the first MAX_LIGHTS are used
+    pub lights: Vec<PunctualLight>,
+    /// Index of the light casting the shadows, None is the light at `light_pos`
+    pub shadow_light: Option<usize>,
+}
+
+/// A light of the asset or of the user in the world space
+#[derive(Clone, Copy)]
+pub struct PunctualLight {
+    pub kind: LightKind,
+    /// Position of the light (ignored by the directional lights)
+    pub pos: Vec3,
+    /// Direction the light is shining in (ignored by the point lights)
+    pub dir: Vec3,
+    /// The color multiplied by the intensity
+    pub radiance: Vec3,
+    /// Distance where the light fades out completely, None is an unlimited range
+    pub range: Option<f32>,
+}
+
+impl PunctualLight {
+    /// The scale and the offset mapping the cosine of the angle from the direction to the spot attenuation
+    fn spot_scale_offset(&self) -> (f32, f32) {
+        match self.kind {
+            LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                let (cos_inner, cos_outer) = (inner_cone_angle.cos(), outer_cone_angle.cos());
+                let scale = 1. / (cos_inner - cos_outer).max(0.001);
+                (scale, -cos_outer * scale)
+            }
+            _ => (0., 1.),
+        }
+    }
 }
 
 impl Lighting {
     pub fn new(light_pos: Vec3) -> Self {
-        Self { light_pos }
+        Self {
+            light_pos,
+            ambient_color: Vec3::ONE,
+            ambient_intensity: 0.4,
+            fog_mode: FogMode::Off,
+            fog_color: Vec3::from(super::BACKGROUND_COLOR),
+            fog_density: 0.05,
+            fog_start: 10.,
+            fog_end: 50.,
+            viewer_light: true,
+            lights: Vec::new(),
+            shadow_light: None,
+        }
     }
 }
 
+/// How the fragments fade into the fog color with their distance from the camera
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum FogMode {
+    Off = 0,
+    /// Between the start and the end distance
+    Linear = 1,
+    /// Visibility of exp(-density * distance)
+    Exponential = 2,
+}
+
+/// A spot light placed by the user in the world space
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpotLight {
+    /// The disabled lights aren't uploaded to the shaders
+    pub enabled: bool,
+    pub pos: Vec3,
+    /// Direction the light is shining in
+    pub dir: Vec3,
+    /// Linear color of the light
+    pub color: [f32; 3],
+    /// Candela, like the spot lights of gltf
+    pub intensity: f32,
+    /// Distance where the light fades out completely, 0 is an unlimited range
+    pub range: f32,
+    /// Angle from the direction in degrees where the light starts to fade
+    pub inner_angle: f32,
+    /// Angle from the direction in degrees where the light is faded out completely
+    pub outer_angle: f32,
+    /// If the light casts the shadows instead of the main light (only one light casts them)
+    pub cast_shadows: bool,
+}
+
+impl SpotLight {
+    /// A spot light shining from the position in the direction
+    pub fn new(pos: Vec3, dir: Vec3) -> Self {
+        Self {
+            pos,
+            dir,
+            ..Default::default()
+        }
+    }
+
+    /// The light in the form uploaded to the shaders
+    pub fn punctual_light(&self) -> PunctualLight {
+        let outer_angle = self.outer_angle.clamp(0.1, 89.9);
+
+        PunctualLight {
+            kind: LightKind::Spot {
+                inner_cone_angle: self.inner_angle.clamp(0., outer_angle).to_radians(),
+                outer_cone_angle: outer_angle.to_radians(),
+            },
+            pos: self.pos,
+            dir: self.dir.normalize_or_zero(),
+            radiance: Vec3::from(self.color) * self.intensity,
+            range: (self.range > 0.).then_some(self.range),
+        }
+    }
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pos: Vec3::new(0., 3., 3.),
+            dir: Vec3::new(0., -1., -1.).normalize(),
+            color: [1., 1., 1.],
+            intensity: 10.,
+            range: 0.,
+            inner_angle: 15.,
+            outer_angle: 30.,
+            cast_shadows: false,
+        }
+    }
+}
+
+impl FogMode {
+    pub const ALL: [FogMode; 3] = [FogMode::Off, FogMode::Linear, FogMode::Exponential];
+}
+
 impl UniformBufferElement for Lighting {
     fn update(&self) {
-        // GLSL vec3 has an alignment of 16 bytes (4 floats)
-        let size = 4 * size_of::<f32>();
-        let buf = self.light_pos.extend(0.).to_array();
+        // GLSL vec3 has an alignment of 16 bytes (4 floats),
+        // the ambient intensity and the fog density fill the padding after the colors.
+        // The lights are stored in arrays of vec4s, packing the scalar values into the 4th components.
+        let mut buf = [0f32; SIZE / size_of::<f32>()];
+        buf[0..3].copy_from_slice(&self.light_pos.to_array());
+        buf[4..7].copy_from_slice(&self.ambient_color.to_array());
+        buf[7] = self.ambient_intensity;
+        buf[8..11].copy_from_slice(&self.fog_color.to_array());
+        buf[11] = self.fog_density;
+        buf[12] = f32::from_bits(self.fog_mode as u32);
+        buf[13] = self.fog_start;
+        buf[14] = self.fog_end;
+
+        let lights = &self.lights[..self.lights.len().min(MAX_LIGHTS)];
+        let shadow_light = match self.shadow_light {
+            Some(light) if light < lights.len() => light as i32,
+            _ => -1,
+        };
+        buf[15] = f32::from_bits(lights.len() as u32);
+        buf[16] = f32::from_bits(shadow_light as u32);
+        buf[17] = f32::from_bits(self.viewer_light as u32);
+
+        for (i, light) in lights.iter().enumerate() {
+            let kind = match light.kind {
+                LightKind::Directional => 0.,
+                LightKind::Point => 1.,
+                LightKind::Spot { .. } => 2.,
+            };
+            let (spot_scale, spot_offset) = light.spot_scale_offset();
+
+            let at = |array: usize| 20 + (array * MAX_LIGHTS + i) * 4;
+            buf[at(0)..at(0) + 4].copy_from_slice(&light.pos.extend(kind).to_array());
+            buf[at(1)..at(1) + 4]
+                .copy_from_slice(&light.dir.extend(light.range.unwrap_or(0.)).to_array());
+            buf[at(2)..at(2) + 3].copy_from_slice(&light.radiance.to_array());
+            buf[at(3)] = spot_scale;
+            buf[at(3) + 1] = spot_offset;
+        }
 
         unsafe {
-            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, size as isize, buf.as_ptr() as _);
+            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, SIZE as isize, buf.as_ptr() as _);
         }
     }
 
     fn init_buffer(&self) {
-        let size = 4 * size_of::<f32>();
-
         unsafe {
             gl::BufferData(
                 gl::UNIFORM_BUFFER,
-                size as isize,
+                SIZE as isize,
                 ptr::null() as _,
                 gl::DYNAMIC_DRAW,
             );
@@ -40,4 +222,30 @@ impl UniformBufferElement for Lighting {
     }
 
     const BINDING: u32 = 5;
+
+    // The offsets of the values in the buffer written by 'update'
+    const LAYOUT: Option<BlockLayout> = Some(BlockLayout {
+        block_name: "Lighting",
+        size: SIZE,
+        members: &[
+            ("lightPos", 0),
+            ("ambientColor", 16),
+            ("ambientIntensity", 28),
+            ("fogColor", 32),
+            ("fogDensity", 44),
+            ("fogMode", 48),
+            ("fogStart", 52),
+            ("fogEnd", 56),
+            ("lightCount", 60),
+            ("shadowLight", 64),
+            ("viewerLight", 68),
+            ("lightPositions[0]", 80),
+            ("lightDirections[0]", 80 + MAX_LIGHTS * 16),
+            ("lightColors[0]", 80 + MAX_LIGHTS * 32),
+            ("lightSpots[0]", 80 + MAX_LIGHTS * 48),
+        ],
+    });
 }
+
+/// Size of the uniform block in bytes, the scalars are followed by 4 arrays of vec4s
+const SIZE: usize = (20 + MAX_LIGHTS * 16) * size_of::<f32>();
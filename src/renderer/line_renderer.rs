@@ -0,0 +1,64 @@
+use eyre::Result;
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::ogl::{self, shader::Shader};
+
+/// Draws antialiased lines of a constant width in pixels, used by all of the debug overlays.
+///
+/// The segments are expanded into screen-space quads in a geometry shader and the edges
+/// of the quads fade out over one pixel, so the lines don't depend on the MSAA setting.
+pub struct LineRenderer {
+    shader: Shader,
+}
+
+impl LineRenderer {
+    pub fn new() -> Result<Self> {
+        let shader = Shader::from_file_with_geometry(
+            "shaders/vs_line.vert",
+            "shaders/gs_line.geom",
+            "shaders/fs_line.frag",
+        )?;
+
+        Ok(Self { shader })
+    }
+
+    /// The shader program of the lines
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    // TODO: do not create a new buffer every frame
+    /// Draws the line segments, every pair of world space points is a single segment.
+    ///
+    /// The width is in pixels of the viewport.
+    pub fn draw(&self, segments: &[Vec3], color: Vec4, width: f32, viewport_size: Vec2) {
+        if segments.len() < 2 {
+            return;
+        }
+
+        let mut vao = 0;
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            let positions = ogl::create_float_buf(segments, 3, ogl::POS_INDEX, gl::FLOAT);
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            self.shader.render(|| {
+                self.shader.set_vec4(color, "lineColor\0");
+                self.shader.set_f32(width, "lineWidth\0");
+                self.shader.set_vec2(viewport_size, "viewportSize\0");
+
+                gl::BindVertexArray(vao);
+                gl::DrawArrays(gl::LINES, 0, (segments.len() / 2 * 2) as i32);
+                gl::BindVertexArray(0);
+            });
+
+            gl::DeleteVertexArrays(1, &vao);
+            gl::DeleteBuffers(1, &positions);
+        }
+    }
+}
@@ -1,26 +1,76 @@
 use std::{mem::size_of, ptr};
 
-use glam::Vec4;
+use glam::{Vec3, Vec4};
 
-use crate::ogl::uniform_buffer::UniformBufferElement;
+use crate::ogl::uniform_buffer::{BlockLayout, UniformBufferElement};
 
 /// Uniform buffer element that stores the material settings
+#[derive(Clone, PartialEq)]
 pub struct Material {
     pub base_color_factor: Vec4,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// If the metallic-roughness texture is bound to texture unit 1
+    pub has_metallic_roughness_texture: bool,
+    /// If the occlusion texture is bound to texture unit 2
+    pub has_occlusion_texture: bool,
+    /// How much the occlusion texture darkens the ambient light (0 = not at all)
+    pub occlusion_strength: f32,
+    /// Scale of the X and Y components of the normal texture
+    pub normal_scale: f32,
+    /// If the tangent space normal texture is bound to texture unit 3
+    pub has_normal_texture: bool,
+    /// Color of the emitted light, added to the lit color
+    pub emissive_factor: Vec3,
+    /// If the material is shaded with the base color only (KHR_materials_unlit)
+    pub unlit: bool,
+    /// Cutoff of the MASK alpha mode, negative if the material isn't masked
+    pub alpha_cutoff: f32,
 }
 
 impl Material {
     pub fn new() -> Self {
         Self {
             base_color_factor: Vec4::splat(1.),
+            metallic_factor: 1.,
+            roughness_factor: 1.,
+            has_metallic_roughness_texture: false,
+            has_occlusion_texture: false,
+            occlusion_strength: 1.,
+            normal_scale: 1.,
+            has_normal_texture: false,
+            emissive_factor: Vec3::ZERO,
+            unlit: false,
+            alpha_cutoff: -1.,
         }
     }
 }
 
 impl UniformBufferElement for Material {
     fn update(&self) {
-        let size = 4 * size_of::<f32>();
-        let buf = self.base_color_factor.to_array();
+        // vec4 + 2 floats + 2 ints + 2 floats + int + float + vec3 + int,
+        // the floats and ints are stored as their bits
+        let size = size_of::<[u32; 16]>();
+        let color = self.base_color_factor.to_array().map(f32::to_bits);
+        let emissive = self.emissive_factor.to_array().map(f32::to_bits);
+        let buf = [
+            color[0],
+            color[1],
+            color[2],
+            color[3],
+            self.metallic_factor.to_bits(),
+            self.roughness_factor.to_bits(),
+            self.has_metallic_roughness_texture as u32,
+            self.has_occlusion_texture as u32,
+            self.occlusion_strength.to_bits(),
+            self.normal_scale.to_bits(),
+            self.has_normal_texture as u32,
+            self.alpha_cutoff.to_bits(),
+            emissive[0],
+            emissive[1],
+            emissive[2],
+            self.unlit as u32,
+        ];
 
         unsafe {
             gl::BufferSubData(gl::UNIFORM_BUFFER, 0, size as isize, buf.as_ptr() as _);
@@ -28,7 +78,7 @@ impl UniformBufferElement for Material {
     }
 
     fn init_buffer(&self) {
-        let size = 4 * size_of::<f32>();
+        let size = size_of::<[u32; 16]>();
 
         unsafe {
             gl::BufferData(
@@ -41,4 +91,23 @@ impl UniformBufferElement for Material {
     }
 
     const BINDING: u32 = 4;
+
+    // The offsets of the values in the buffer written by 'update'
+    const LAYOUT: Option<BlockLayout> = Some(BlockLayout {
+        block_name: "Material",
+        size: size_of::<[u32; 16]>(),
+        members: &[
+            ("texBaseColorFactor", 0),
+            ("metallicFactor", 16),
+            ("roughnessFactor", 20),
+            ("hasMetallicRoughnessTex", 24),
+            ("hasOcclusionTex", 28),
+            ("occlusionStrength", 32),
+            ("normalScale", 36),
+            ("hasNormalTex", 40),
+            ("alphaCutoff", 44),
+            ("emissiveFactor", 48),
+            ("isUnlit", 60),
+        ],
+    });
 }
@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+/// Number of the parts of the trail drawn with a different opacity
+const FADE_STEPS: usize = 8;
+
+/// The recent world positions of the root of the selected model, drawn as a fading polyline.
+///
+/// Makes the movement of the animations that translate the root (and its drift) visible.
+pub struct RootTrail {
+    /// The recorded positions, the newest is at the back
+    positions: VecDeque<Vec3>,
+    /// The selected model, the active animation and its time in the last recorded frame
+    playhead: Option<(usize, Option<usize>, f32)>,
+}
+
+impl RootTrail {
+    pub fn new() -> Self {
+        Self {
+            positions: VecDeque::new(),
+            playhead: None,
+        }
+    }
+
+    /// Adds the position of the current frame and drops the positions over the maximum length.
+    ///
+    /// The trail is cleared when another model is selected, another animation is activated
+    /// or the animation restarts (its time goes back, including the wrap-around of a loop).
+    pub fn record(
+        &mut self,
+        pos: Vec3,
+        model: usize,
+        animation: Option<(usize, f32)>,
+        max_len: usize,
+    ) {
+        let (active_animation, time) = match animation {
+            Some((index, time)) => (Some(index), time),
+            None => (None, 0.),
+        };
+
+        let restarted = match self.playhead {
+            Some((last_model, last_animation, last_time)) => {
+                last_model != model || last_animation != active_animation || time < last_time
+            }
+            None => true,
+        };
+
+        if restarted {
+            self.positions.clear();
+        }
+        self.playhead = Some((model, active_animation, time));
+
+        // The position doesn't change while the animation is paused
+        if self.positions.back() != Some(&pos) {
+            self.positions.push_back(pos);
+        }
+
+        while self.positions.len() > max_len {
+            self.positions.pop_front();
+        }
+    }
+
+    /// Removes all of the recorded positions
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.playhead = None;
+    }
+
+    /// The line segments of the trail split into parts of increasing age,
+    /// together with the opacity of the part (the newest part is opaque)
+    pub fn faded_segments(&self) -> Vec<(Vec<Vec3>, f32)> {
+        let count = self.positions.len();
+        if count < 2 {
+            return Vec::new();
+        }
+
+        let step_len = count.div_ceil(FADE_STEPS).max(1);
+        let positions: Vec<Vec3> = self.positions.iter().copied().collect();
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        while start + 1 < count {
+            let end = (start + step_len).min(count - 1);
+
+            let segments = positions[start..=end]
+                .windows(2)
+                .flat_map(|w| [w[0], w[1]])
+                .collect();
+
+            // The position of the middle of the part in the trail (0 = oldest, 1 = newest)
+            let recency = ((start + end) as f32 / 2.) / (count - 1) as f32;
+            parts.push((segments, 0.15 + 0.85 * recency));
+
+            start = end;
+        }
+
+        parts
+    }
+}
@@ -1,35 +1,135 @@
-use std::{mem::size_of, ptr};
+use std::{
+    mem::{offset_of, size_of},
+    ptr, slice,
+};
 
-use crate::ogl::uniform_buffer::UniformBufferElement;
+use serde::{Deserialize, Serialize};
+
+use crate::ogl::uniform_buffer::{BlockLayout, UniformBufferElement};
 
 /// Uniform buffer element that stores the rendering 'settings' (controls)
 pub struct Settings {
     pub do_skinning: bool,
+    pub shading_mode: ShadingMode,
+    /// Index of the joint whose weights are displayed in the WeightPaint shading mode
+    pub selected_joint: u32,
+    /// If the V texture coordinate is inverted (for textures with the opposite convention than glTF)
+    pub flip_v: bool,
+    /// If the output is encoded to sRGB, so the lighting and the vertex colors are computed in linear space.
+    ///
+    /// Otherwise the base color textures (decoded by the hardware) are encoded back to sRGB when sampled.
+    pub gamma_correct: bool,
+    /// If the base color is multiplied by the vertex colors (COLOR_0)
+    pub apply_vertex_colors: bool,
+    /// If the masked materials write their alpha as the coverage of the samples (only with MSAA)
+    pub alpha_to_coverage: bool,
+    /// Number of the cells of the UV checkerboard replacing the base color per UV unit, 0 is disabled
+    pub uv_checker_scale: f32,
+    /// Size of the points of the POINTS primitives in pixels
+    pub point_size: f32,
+    /// If the size of the points is divided by their distance from the camera (it's the size at 1 unit)
+    pub point_size_attenuation: bool,
 }
 
 impl Settings {
     pub fn new() -> Self {
-        Self { do_skinning: false }
+        Self {
+            do_skinning: false,
+            shading_mode: ShadingMode::Lit,
+            selected_joint: 0,
+            flip_v: false,
+            gamma_correct: false,
+            apply_vertex_colors: true,
+            alpha_to_coverage: false,
+            uv_checker_scale: 0.,
+            point_size: 4.,
+            point_size_attenuation: false,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Settings {
+    /// All of the settings packed in the layout of the uniform block
+    pub fn std140(&self) -> SettingsStd140 {
+        SettingsStd140 {
+            do_skinning: self.do_skinning as i32,
+            shading_mode: self.shading_mode as i32,
+            selected_joint: self.selected_joint as i32,
+            flip_v: self.flip_v as i32,
+            gamma_correct: self.gamma_correct as i32,
+            apply_vertex_colors: self.apply_vertex_colors as i32,
+            alpha_to_coverage: self.alpha_to_coverage as i32,
+            uv_checker_scale: self.uv_checker_scale,
+            point_size: self.point_size,
+            point_size_attenuation: self.point_size_attenuation as i32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// The std140 layout of the 'Settings' uniform block in the shaders.
+///
+/// Scalars (bool, int, uint, float) take 4 bytes and are aligned to 4 bytes,
+/// vec3 and vec4 are aligned to 16 bytes. Bools are stored as ints.
+/// The size of the block is rounded up to a multiple of 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SettingsStd140 {
+    /// Offset 0
+    pub do_skinning: i32,
+    /// Offset 4
+    pub shading_mode: i32,
+    /// Offset 8
+    pub selected_joint: i32,
+    /// Offset 12
+    pub flip_v: i32,
+    /// Offset 16
+    pub gamma_correct: i32,
+    /// Offset 20
+    pub apply_vertex_colors: i32,
+    /// Offset 24
+    pub alpha_to_coverage: i32,
+    /// Offset 28
+    pub uv_checker_scale: f32,
+    /// Offset 32
+    pub point_size: f32,
+    /// Offset 36
+    pub point_size_attenuation: i32,
+    /// Rounds the size up to 48 bytes
+    pub _padding: [i32; 2],
+}
+
+impl SettingsStd140 {
+    /// The raw bytes uploaded to the uniform buffer
+    pub fn as_bytes(&self) -> &[u8] {
+        // The struct only contains 4-byte scalars, so there are no uninitialized padding bytes
+        unsafe { slice::from_raw_parts(self as *const Self as *const u8, size_of::<Self>()) }
     }
 }
 
 impl UniformBufferElement for Settings {
     fn update(&self) {
-        let size = size_of::<i32>();
-        let num = if self.do_skinning { 1 } else { 0 };
+        let size = size_of::<SettingsStd140>();
+        let block = self.std140();
 
         unsafe {
             gl::BufferSubData(
                 gl::UNIFORM_BUFFER,
                 0,
                 size as isize,
-                &num as *const i32 as _,
+                &block as *const SettingsStd140 as _,
             );
         }
     }
 
     fn init_buffer(&self) {
-        let size = size_of::<i32>();
+        let size = size_of::<SettingsStd140>();
 
         unsafe {
             gl::BufferData(
@@ -42,4 +142,61 @@ impl UniformBufferElement for Settings {
     }
 
     const BINDING: u32 = 3;
+
+    const LAYOUT: Option<BlockLayout> = Some(BlockLayout {
+        block_name: "Settings",
+        size: size_of::<SettingsStd140>(),
+        members: &[
+            ("doSkinning", offset_of!(SettingsStd140, do_skinning)),
+            ("shadingMode", offset_of!(SettingsStd140, shading_mode)),
+            ("selectedJoint", offset_of!(SettingsStd140, selected_joint)),
+            ("flipV", offset_of!(SettingsStd140, flip_v)),
+            ("gammaCorrect", offset_of!(SettingsStd140, gamma_correct)),
+            (
+                "applyVertexColors",
+                offset_of!(SettingsStd140, apply_vertex_colors),
+            ),
+            (
+                "alphaToCoverage",
+                offset_of!(SettingsStd140, alpha_to_coverage),
+            ),
+            (
+                "uvCheckerScale",
+                offset_of!(SettingsStd140, uv_checker_scale),
+            ),
+            ("pointSize", offset_of!(SettingsStd140, point_size)),
+            (
+                "pointSizeAttenuation",
+                offset_of!(SettingsStd140, point_size_attenuation),
+            ),
+        ],
+    });
+}
+
+/// Debug shading modes, the values have to match the SHADING_* constants in the fragment shaders
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ShadingMode {
+    /// Regular shading with lighting
+    Lit = 0,
+    /// Only the base color
+    Unlit = 1,
+    /// Normals (in world space) as colors
+    Normals = 2,
+    /// Texture coordinates as colors
+    Uvs = 3,
+    /// Every joint gets a unique color, vertices are colored by their most influential joint
+    Weights = 4,
+    /// Vertices are colored by the weight of the selected joint (red = 1, blue = 0)
+    WeightPaint = 5,
+}
+
+impl ShadingMode {
+    pub const ALL: [ShadingMode; 6] = [
+        ShadingMode::Lit,
+        ShadingMode::Unlit,
+        ShadingMode::Normals,
+        ShadingMode::Uvs,
+        ShadingMode::Weights,
+        ShadingMode::WeightPaint,
+    ];
 }
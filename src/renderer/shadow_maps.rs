@@ -0,0 +1,235 @@
+use eyre::{eyre, Result};
+use glam::{Mat4, Vec3};
+
+use crate::{camera::Camera, model::Aabb};
+
+use super::shadows::MAX_CASCADES;
+
+/// Width and height of the shadow map of every cascade
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+
+/// The depth textures of the shadow cascades (layers of a 2D array texture) and the framebuffer rendering into them
+pub struct ShadowMaps {
+    /// The array texture with a layer for each cascade, sampled with depth comparison
+    pub texture: u32,
+    framebuffer: u32,
+}
+
+impl ShadowMaps {
+    pub fn new() -> Result<Self> {
+        let mut texture = 0;
+        let mut framebuffer = 0;
+
+        let status = unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, texture);
+            gl::TexStorage3D(
+                gl::TEXTURE_2D_ARRAY,
+                1,
+                gl::DEPTH_COMPONENT32F,
+                SHADOW_MAP_SIZE as i32,
+                SHADOW_MAP_SIZE as i32,
+                MAX_CASCADES as i32,
+            );
+
+            // Hardware depth comparison with bilinear filtering of the results
+            let params = [
+                (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE),
+                (gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL),
+            ];
+            for (name, value) in params {
+                gl::TexParameteri(gl::TEXTURE_2D_ARRAY, name, value as i32);
+            }
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture, 0, 0);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            status
+        };
+
+        let shadow_maps = Self {
+            texture,
+            framebuffer,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(eyre!("Shadow map framebuffer is incomplete: '{status:#x}'"));
+        }
+
+        Ok(shadow_maps)
+    }
+
+    /// Binds the framebuffer to the layer of the cascade and clears its depth
+    pub fn begin_cascade(&self, cascade: usize) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::FramebufferTextureLayer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                self.texture,
+                0,
+                cascade as i32,
+            );
+            gl::Viewport(0, 0, SHADOW_MAP_SIZE as i32, SHADOW_MAP_SIZE as i32);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+    }
+}
+
+impl Drop for ShadowMaps {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// The far distances of the cascades, the practical split scheme.
+///
+/// `lambda` blends between the uniform (0) and the logarithmic (1) distribution of the splits.
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let fraction = i as f32 / count as f32;
+            let logarithmic = near * (far / near).powf(fraction);
+            let uniform = near + (far - near) * fraction;
+
+            lambda * logarithmic + (1. - lambda) * uniform
+        })
+        .collect()
+}
+
+/// The view-projection matrix of the light covering the slice of the view frustum between the distances.
+///
+/// The slice is enclosed by a sphere, so the size of the shadow map doesn't change when the camera rotates,
+/// and the center is snapped to the texels, so the edges of the shadows don't shimmer when it moves.
+/// The depth range is extended towards the light to include the casters in `bounds`.
+pub fn cascade_matrix(
+    camera: &Camera,
+    view: Mat4,
+    aspect_ratio: f32,
+    (near, far): (f32, f32),
+    light_dir: Vec3,
+    bounds: &Aabb,
+) -> Mat4 {
+    let corners = slice_corners(camera, aspect_ratio, near, far);
+    let inverse_view = view.inverse();
+    let corners = corners.map(|corner| inverse_view.transform_point3(corner));
+
+    let center =
+        corners.iter().fold(Vec3::ZERO, |sum, corner| sum + *corner) / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .fold(0f32, |max, corner| max.max(corner.distance(center)));
+    // Rounded, so the precision errors don't change the size of the texels
+    let radius = (radius * 16.).ceil() / 16.;
+
+    // The light looks along its direction, from the origin
+    let up = match light_dir.abs().dot(Vec3::Y) > 0.99 {
+        true => Vec3::Z,
+        false => Vec3::Y,
+    };
+    let light_view = Mat4::look_at_rh(Vec3::ZERO, -light_dir, up);
+
+    let mut center = light_view.transform_point3(center);
+    let texel = 2. * radius / SHADOW_MAP_SIZE as f32;
+    center.x = (center.x / texel).floor() * texel;
+    center.y = (center.y / texel).floor() * texel;
+
+    // The view space of the light looks in the -Z direction
+    let caster_top = match bounds.is_empty() {
+        true => f32::MIN,
+        false => bounds
+            .corners()
+            .iter()
+            .map(|corner| light_view.transform_point3(*corner).z)
+            .fold(f32::MIN, f32::max),
+    };
+    let z_near = -(center.z + radius).max(caster_top);
+    let z_far = -(center.z - radius);
+
+    let projection = Mat4::orthographic_rh(
+        center.x - radius,
+        center.x + radius,
+        center.y - radius,
+        center.y + radius,
+        z_near,
+        z_far,
+    );
+
+    projection * light_view
+}
+
+/// The view-projection matrix of the spot light covering its cone.
+///
+/// The depth range is fitted to the `bounds` (and limited by the range of the light) for the best precision.
+pub fn spot_matrix(
+    pos: Vec3,
+    dir: Vec3,
+    outer_cone_angle: f32,
+    range: Option<f32>,
+    bounds: &Aabb,
+) -> Mat4 {
+    let up = match dir.abs().dot(Vec3::Y) > 0.99 {
+        true => Vec3::Z,
+        false => Vec3::Y,
+    };
+    let light_view = Mat4::look_at_rh(pos, pos + dir, up);
+
+    let (nearest, farthest) = match bounds.is_empty() {
+        true => (0., range.unwrap_or(100.)),
+        false => {
+            let farthest = bounds
+                .corners()
+                .iter()
+                .map(|corner| corner.distance(pos))
+                .fold(0f32, f32::max);
+            (pos.clamp(bounds.min, bounds.max).distance(pos), farthest)
+        }
+    };
+
+    let far = farthest.min(range.unwrap_or(f32::MAX)).max(1e-3);
+    // The near plane can't be too close, the precision of the depth is concentrated in front of it
+    let near = nearest.clamp(far * 0.01, far * 0.5);
+    // A margin, so the filtering of the shadows doesn't reach the edge of the map
+    let fov = (outer_cone_angle * 2. * 1.05).min(179f32.to_radians());
+
+    Mat4::perspective_rh(fov, 1., near, far) * light_view
+}
+
+/// The corners of the slice of the view frustum in the view space of the camera
+fn slice_corners(camera: &Camera, aspect_ratio: f32, near: f32, far: f32) -> [Vec3; 8] {
+    let half_size = |distance: f32| match camera.orthographic {
+        true => (
+            camera.ortho_half_height * aspect_ratio,
+            camera.ortho_half_height,
+        ),
+        false => {
+            let tan = (camera.fov.to_radians() / 2.).tan();
+            (tan * distance * aspect_ratio, tan * distance)
+        }
+    };
+
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, distance) in [near, far].into_iter().enumerate() {
+        let (w, h) = half_size(distance);
+        corners[i * 4] = Vec3::new(-w, -h, -distance);
+        corners[i * 4 + 1] = Vec3::new(w, -h, -distance);
+        corners[i * 4 + 2] = Vec3::new(-w, h, -distance);
+        corners[i * 4 + 3] = Vec3::new(w, h, -distance);
+    }
+
+    corners
+}
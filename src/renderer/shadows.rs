@@ -0,0 +1,87 @@
+use std::{mem::size_of, ptr};
+
+use glam::{Mat4, Vec4};
+
+use crate::ogl::uniform_buffer::{BlockLayout, UniformBufferElement};
+
+/// Maximum number of the shadow cascades, has to match the size of the arrays in the shaders
+pub const MAX_CASCADES: usize = 4;
+
+/// Uniform buffer element that stores the cascades of the shadow maps
+pub struct Shadows {
+    /// Transforms from the world space to the clip space of the light for each cascade
+    pub light_space: [Mat4; MAX_CASCADES],
+    /// The far view-space distance of each cascade
+    pub cascade_splits: Vec4,
+    /// Number of the used cascades, 0 disables the shadows
+    pub cascade_count: u32,
+    /// If the fragments are tinted by the color of their cascade
+    pub cascade_debug: bool,
+    /// The depth bias of the shadow comparison (scaled by the slope of the surface)
+    pub bias: f32,
+    /// If the depth range of the clip space is [0, 1] (glClipControl), otherwise it's [-1, 1]
+    pub zero_to_one_depth: bool,
+}
+
+impl Shadows {
+    pub fn new() -> Self {
+        Self {
+            light_space: [Mat4::IDENTITY; MAX_CASCADES],
+            cascade_splits: Vec4::ZERO,
+            cascade_count: 0,
+            cascade_debug: false,
+            bias: 0.002,
+            zero_to_one_depth: true,
+        }
+    }
+}
+
+impl UniformBufferElement for Shadows {
+    fn update(&self) {
+        // The matrices take 64 bytes each, the scalars follow the vec4 of the splits
+        let mut buf = [0f32; SIZE / size_of::<f32>()];
+        for (i, matrix) in self.light_space.iter().enumerate() {
+            buf[i * 16..(i + 1) * 16].copy_from_slice(&matrix.to_cols_array());
+        }
+
+        buf[64..68].copy_from_slice(&self.cascade_splits.to_array());
+        buf[68] = f32::from_bits(self.cascade_count);
+        buf[69] = f32::from_bits(self.cascade_debug as u32);
+        buf[70] = self.bias;
+        buf[71] = f32::from_bits(self.zero_to_one_depth as u32);
+
+        unsafe {
+            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, SIZE as isize, buf.as_ptr() as _);
+        }
+    }
+
+    fn init_buffer(&self) {
+        unsafe {
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                SIZE as isize,
+                ptr::null() as _,
+                gl::DYNAMIC_DRAW,
+            );
+        }
+    }
+
+    const BINDING: u32 = 6;
+
+    // The offsets of the values in the buffer written by 'update'
+    const LAYOUT: Option<BlockLayout> = Some(BlockLayout {
+        block_name: "Shadows",
+        size: SIZE,
+        members: &[
+            ("lightSpace[0]", 0),
+            ("cascadeSplits", 256),
+            ("cascadeCount", 272),
+            ("cascadeDebug", 276),
+            ("shadowBias", 280),
+            ("zeroToOneDepth", 284),
+        ],
+    });
+}
+
+/// Size of the uniform block in bytes
+const SIZE: usize = (MAX_CASCADES * 16 + 8) * size_of::<f32>();
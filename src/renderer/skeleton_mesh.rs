@@ -7,7 +7,7 @@ use crate::{
 
 // TODO: do not create a new buffer every frame
 /// Draws the joints by their current world transforms
-pub fn draw_joints(world_transforms: &[Mat4], shader: &Shader) {
+pub fn draw_joints(world_transforms: &[Mat4], point_size: f32, shader: &Shader) {
     let mut positions = Vec::new();
     let texcoords = vec![Vec2::ZERO; world_transforms.len()];
     let normals = vec![Vec3::ZERO; world_transforms.len()];
@@ -32,7 +32,7 @@ pub fn draw_joints(world_transforms: &[Mat4], shader: &Shader) {
 
         shader.render(|| {
             gl::BindVertexArray(vao);
-            gl::PointSize(4.);
+            gl::PointSize(point_size);
             gl::DrawArrays(gl::POINTS, 0, positions.len() as i32);
             gl::BindVertexArray(0);
         });
@@ -44,8 +44,8 @@ pub fn draw_joints(world_transforms: &[Mat4], shader: &Shader) {
     }
 }
 
-/// Drwas the bones of the joints specified by the joints array and their world_transforms array
-pub fn draw_bones(world_transforms: &[Mat4], joints: &[Joint], shader: &Shader) {
+/// The line segments of the bones between the joints and their parents (pairs of world space points)
+pub fn bone_segments(world_transforms: &[Mat4], joints: &[Joint]) -> Vec<Vec3> {
     let mut positions = Vec::new();
 
     for (i, joint) in joints.iter().enumerate() {
@@ -58,31 +58,5 @@ pub fn draw_bones(world_transforms: &[Mat4], joints: &[Joint], shader: &Shader)
         }
     }
 
-    let texcoords = vec![Vec2::ZERO; positions.len()];
-    let normals = vec![Vec3::ZERO; positions.len()];
-
-    let mut vao = 0;
-
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-
-        let _positions = ogl::create_float_buf(&positions, 3, ogl::POS_INDEX, gl::FLOAT);
-        let _texcoords = ogl::create_float_buf(&texcoords, 2, ogl::TEXCOORDS_INDEX, gl::FLOAT);
-        let _normals = ogl::create_float_buf(&normals, 3, ogl::NORMALS_INDEX, gl::FLOAT);
-
-        gl::BindVertexArray(0);
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-        shader.render(|| {
-            gl::BindVertexArray(vao);
-            gl::DrawArrays(gl::LINES, 0, positions.len() as i32);
-            gl::BindVertexArray(0);
-        });
-
-        gl::DeleteVertexArrays(1, &vao);
-
-        let bufs = [_positions, _texcoords, _normals];
-        gl::DeleteBuffers(bufs.len() as _, bufs.as_ptr());
-    }
+    positions
 }
@@ -1,8 +1,11 @@
 use std::{mem::size_of, ptr};
 
-use glam::Mat4;
+use glam::{Mat3, Mat4};
 
-use crate::ogl::uniform_buffer::UniformBufferElement;
+use crate::{
+    model::MIN_DETERMINANT,
+    ogl::uniform_buffer::{BlockLayout, UniformBufferElement},
+};
 
 /// Uniform buffer element that stores the transformation matrices
 pub struct Transforms {
@@ -19,11 +22,25 @@ impl Transforms {
             model: Mat4::IDENTITY,
         }
     }
+
+    /// The inverse-transpose of the upper 3x3 of the model matrix, which keeps the normals
+    /// perpendicular to the surface under non-uniform scale and skew
+    fn normal_matrix(&self) -> Mat4 {
+        let model = Mat3::from_mat4(self.model);
+
+        // Zero-scaled meshes aren't drawn
+        if model.determinant().abs() < MIN_DETERMINANT {
+            return Mat4::IDENTITY;
+        }
+
+        Mat4::from_mat3(model.inverse().transpose())
+    }
 }
 
 impl UniformBufferElement for Transforms {
     fn update(&self) {
-        let buf: Vec<f32> = [self.projection, self.view, self.model]
+        // The normal matrix is stored as a mat4, mat3 columns are padded to vec4 in std140 anyway
+        let buf: Vec<f32> = [self.projection, self.view, self.model, self.normal_matrix()]
             .iter()
             .flat_map(|mat| mat.to_cols_array())
             .collect();
@@ -42,7 +59,7 @@ impl UniformBufferElement for Transforms {
         unsafe {
             gl::BufferData(
                 gl::UNIFORM_BUFFER,
-                3 * size_of::<[f32; 16]>() as isize,
+                4 * size_of::<[f32; 16]>() as isize,
                 ptr::null() as _,
                 gl::DYNAMIC_DRAW,
             );
@@ -50,4 +67,16 @@ impl UniformBufferElement for Transforms {
     }
 
     const BINDING: u32 = 1;
+
+    // The offsets of the matrices in the buffer written by 'update'
+    const LAYOUT: Option<BlockLayout> = Some(BlockLayout {
+        block_name: "Transforms",
+        size: 4 * size_of::<[f32; 16]>(),
+        members: &[
+            ("projection", 0),
+            ("view", 64),
+            ("model", 128),
+            ("normalMatrix", 192),
+        ],
+    });
 }
@@ -0,0 +1,257 @@
+use std::{fs, path::Path};
+
+use eyre::{eyre, Context, Result};
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::Camera,
+    gui::Gui,
+    model::{AnimationControl, Handedness, ImportOptions, Model},
+    renderer::{CulledFace, ShadingMode},
+};
+
+/// A snapshot of the whole scene, used to reproduce exactly what is on the screen.
+///
+/// Models are referenced by the paths of their gltf files.
+#[derive(Serialize, Deserialize)]
+pub struct SceneState {
+    pub models: Vec<ModelState>,
+    pub selected_model: usize,
+    pub camera: CameraState,
+    pub light: LightState,
+    pub render_settings: RenderSettingsState,
+}
+
+/// The state of a single model in the scene
+#[derive(Serialize, Deserialize)]
+pub struct ModelState {
+    pub path: String,
+    pub transform: Mat4,
+    pub wireframe: bool,
+    #[serde(default)]
+    pub flip_v: bool,
+    /// The conversions the model was loaded with
+    #[serde(default)]
+    pub handedness: Handedness,
+    /// The loaded subtree of the scene, None if the whole scene was loaded
+    #[serde(default)]
+    pub root_node: Option<String>,
+    /// The active animation, None if the model is in the rest pose
+    pub animation: Option<AnimationState>,
+}
+
+/// The state of the active animation of a model
+#[derive(Serialize, Deserialize)]
+pub struct AnimationState {
+    pub index: usize,
+    pub current_time: f32,
+    pub trim_start: f32,
+    pub trim_end: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CameraState {
+    pub pos: Vec3,
+    pub dir: Vec3,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    pub reversed_z: bool,
+    #[serde(default)]
+    pub orthographic: bool,
+    #[serde(default = "default_ortho_half_height")]
+    pub ortho_half_height: f32,
+}
+
+fn default_ortho_half_height() -> f32 {
+    1.
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LightState {
+    pub ambient_color: [f32; 3],
+    pub ambient_intensity: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RenderSettingsState {
+    pub shading_mode: ShadingMode,
+    pub selected_joint: usize,
+    pub draw_skeleton: bool,
+    pub mesh_visible: bool,
+    pub wireframe: bool,
+    pub wireframe_color: [f32; 4],
+    pub wireframe_width: f32,
+    pub depth_test: bool,
+    pub face_culling: bool,
+    pub culled_face: CulledFace,
+}
+
+impl SceneState {
+    /// Captures the current state of the scene
+    pub fn capture(scene: &[Model], camera: &Camera, gui: &Gui) -> Self {
+        let models = scene
+            .iter()
+            .map(|model| {
+                let animations = &model.animations;
+                let animation = animations.active_animation().map(|index| {
+                    let animation = &animations.animations[index];
+
+                    AnimationState {
+                        index,
+                        current_time: animation.current_time,
+                        trim_start: animation.trim_start,
+                        trim_end: animation.trim_end,
+                    }
+                });
+
+                ModelState {
+                    path: model.path.clone(),
+                    transform: model.transform,
+                    wireframe: model.wireframe,
+                    flip_v: model.flip_v,
+                    handedness: model.handedness,
+                    root_node: model.root_node.clone(),
+                    animation,
+                }
+            })
+            .collect();
+
+        Self {
+            models,
+            selected_model: gui.selected_model,
+            camera: CameraState {
+                pos: camera.pos(),
+                dir: camera.dir(),
+                fov: camera.fov,
+                near: camera.near,
+                far: camera.far,
+                reversed_z: camera.reversed_z,
+                orthographic: camera.orthographic,
+                ortho_half_height: camera.ortho_half_height,
+            },
+            light: LightState {
+                ambient_color: gui.ambient_color,
+                ambient_intensity: gui.ambient_intensity,
+            },
+            render_settings: RenderSettingsState {
+                shading_mode: gui.shading_mode,
+                selected_joint: gui.selected_joint,
+                draw_skeleton: gui.draw_skeleton,
+                mesh_visible: gui.mesh_visible,
+                wireframe: gui.wireframe,
+                wireframe_color: gui.wireframe_color,
+                wireframe_width: gui.wireframe_width,
+                depth_test: gui.depth_test,
+                face_culling: gui.face_culling,
+                culled_face: gui.culled_face,
+            },
+        }
+    }
+
+    /// Saves the state into a JSON file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string_pretty(self).wrap_err("Couldn't serialize the scene")?;
+
+        fs::write(path, text)
+            .wrap_err_with(|| format!("Couldn't save the scene file '{}'", path.display()))
+    }
+
+    /// Loads the state from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Couldn't read the scene file '{}'", path.display()))?;
+
+        serde_json::from_str(&text)
+            .wrap_err_with(|| format!("Couldn't parse the scene file '{}'", path.display()))
+    }
+
+    /// Replaces the scene with the models of the state and restores the camera and the GUI state.
+    ///
+    /// Models that can't be loaded are skipped with a warning.
+    /// The animations are paused at the saved times.
+    pub fn apply(self, scene: &mut Vec<Model>, camera: &mut Camera, gui: &mut Gui) -> Result<()> {
+        let mut models = Vec::new();
+
+        for state in self.models {
+            let options = ImportOptions {
+                handedness: state.handedness,
+                root_node: state.root_node,
+                ..ImportOptions::default()
+            };
+
+            let mut model = match Model::from_gltf_with_options(&state.path, options) {
+                Ok(model) => model,
+                Err(e) => {
+                    eprintln!(
+                        "WARN: skipping the model '{}' of the scene: {e:?}",
+                        state.path
+                    );
+                    continue;
+                }
+            };
+
+            model.transform = state.transform;
+            model.wireframe = state.wireframe;
+            model.flip_v = state.flip_v;
+
+            if let Some(animation_state) = state.animation {
+                let animations = &mut model.animations;
+
+                match animations.animations.get_mut(animation_state.index) {
+                    Some(animation) => {
+                        animation.set_trim(animation_state.trim_start, animation_state.trim_end);
+                        animation.current_time = animation_state
+                            .current_time
+                            .clamp(animation.trim_start, animation.trim_end);
+
+                        animations.animation_control = AnimationControl::Controllable {
+                            active_animation: animation_state.index,
+                        };
+                    }
+                    None => eprintln!(
+                        "WARN: the model '{}' doesn't have animation {}",
+                        state.path, animation_state.index
+                    ),
+                }
+            }
+
+            models.push(model);
+        }
+
+        if models.is_empty() {
+            return Err(eyre!("None of the models of the scene could be loaded"));
+        }
+
+        gui.selected_model = self.selected_model.min(models.len() - 1);
+        gui.picked = None;
+        *scene = models;
+
+        camera.set_pos(self.camera.pos);
+        camera.set_dir(self.camera.dir);
+        camera.fov = self.camera.fov;
+        camera.near = self.camera.near;
+        camera.far = self.camera.far;
+        camera.reversed_z = self.camera.reversed_z;
+        camera.orthographic = self.camera.orthographic;
+        camera.ortho_half_height = self.camera.ortho_half_height;
+
+        gui.ambient_color = self.light.ambient_color;
+        gui.ambient_intensity = self.light.ambient_intensity;
+
+        let settings = self.render_settings;
+        gui.shading_mode = settings.shading_mode;
+        gui.selected_joint = settings.selected_joint;
+        gui.draw_skeleton = settings.draw_skeleton;
+        gui.mesh_visible = settings.mesh_visible;
+        gui.wireframe = settings.wireframe;
+        gui.wireframe_color = settings.wireframe_color;
+        gui.wireframe_width = settings.wireframe_width;
+        gui.depth_test = settings.depth_test;
+        gui.face_culling = settings.face_culling;
+        gui.culled_face = settings.culled_face;
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use std::time::Instant;
 use egui::CtxRef;
 use egui_backend::{painter::Painter, DpiScaling, EguiStateHandler};
 use egui_sdl2_gl::ShaderVersion;
-use eyre::{eyre, Result};
+use eyre::{eyre, Context, Result};
 use sdl2::{
     event::{Event, WindowEvent},
     video::Window,
@@ -13,6 +13,13 @@ use sdl2::{
 
 use egui_sdl2_gl as egui_backend;
 
+use crate::config::WindowConfig;
+
+/// The minimal required OpenGL version (core profile).
+///
+/// 4.2 is needed for the explicit uniform buffer bindings in the shaders, 4.3 for the debug output.
+pub const GL_VERSION: (u8, u8) = (4, 3);
+
 /// A component that handles the window creation and egui drawing
 pub struct MyWindow {
     /// SDL2 context
@@ -37,39 +44,93 @@ pub struct MyWindow {
     pub width: u32,
     /// Height of the window
     pub height: u32,
+    /// If the window has the keyboard focus
+    focused: bool,
+    /// If the window is minimized
+    minimized: bool,
 }
 
 impl MyWindow {
     /// Create the window with the specific title.
     ///
     /// Initializes the OpenGL context, the egui renderer and the SDL2 video subsystem.
-    pub fn new(title: &str) -> Result<Self> {
-        let sdl_context = sdl2::init().map_err(|e| eyre!("{e}"))?;
-        let video_subsystem = sdl_context.video().map_err(|e| eyre!("{e}"))?;
+    /// The size and position are restored from 'saved' if it's set.
+    /// The default framebuffer has 'msaa_samples' samples per pixel (0 disables the multisampling).
+    pub fn new(title: &str, saved: Option<WindowConfig>, msaa_samples: u8) -> Result<Self> {
+        Self::create(title, None, saved, msaa_samples)
+    }
 
-        let size = video_subsystem
-            .display_bounds(0)
-            .map_err(|e| eyre!("{e}"))?;
+    /// Create a hidden window of a specific size (used for rendering without user interaction)
+    pub fn new_hidden(title: &str, width: u32, height: u32) -> Result<Self> {
+        Self::create(title, Some((width, height)), None, 0)
+    }
 
-        let width = (size.width() as f32 * 0.7) as u32;
-        let height = (size.height() as f32 * 0.7) as u32;
+    /// Creates the window, the size is derived from the display size if neither 'hidden_size'
+    /// nor 'saved' is set
+    fn create(
+        title: &str,
+        hidden_size: Option<(u32, u32)>,
+        saved: Option<WindowConfig>,
+        msaa_samples: u8,
+    ) -> Result<Self> {
+        let sdl_context = sdl2::init().map_err(|e| eyre!("Couldn't initialize SDL2: {e}"))?;
+        let video_subsystem = sdl_context
+            .video()
+            .map_err(|e| eyre!("Couldn't initialize the SDL2 video subsystem: {e}"))?;
 
-        let window = video_subsystem
-            .window(title, width, height)
-            .opengl()
-            .resizable()
-            .position_centered()
-            .allow_highdpi()
-            .build()?;
+        let saved_size = saved.map(|saved| (saved.width.max(200), saved.height.max(200)));
 
-        // Init OpenGL
-        let gl_ctx = window.gl_create_context().map_err(|e| eyre!("{e}"))?;
+        let (width, height) = match hidden_size.or(saved_size) {
+            Some(size) => size,
+            None => {
+                let size = video_subsystem
+                    .display_bounds(0)
+                    .map_err(|e| eyre!("{e}"))?;
+
+                let width = (size.width() as f32 * 0.7) as u32;
+                let height = (size.height() as f32 * 0.7) as u32;
+                (width, height)
+            }
+        };
+
+        // The attributes have to be set before the window and the context are created
         let gl_attr = video_subsystem.gl_attr();
-        gl_attr.set_context_major_version(4);
-        gl_attr.set_context_minor_version(2);
+        gl_attr.set_context_major_version(GL_VERSION.0);
+        gl_attr.set_context_minor_version(GL_VERSION.1);
         gl_attr.set_context_profile(GLProfile::Core);
         gl_attr.set_context_flags().debug().set();
         gl_attr.set_double_buffer(true);
+        if msaa_samples > 1 {
+            gl_attr.set_multisample_buffers(1);
+            gl_attr.set_multisample_samples(msaa_samples);
+        }
+
+        let mut window_builder = video_subsystem.window(title, width, height);
+        window_builder.opengl().allow_highdpi();
+
+        match saved {
+            Some(saved) => window_builder.position(saved.x, saved.y),
+            None => window_builder.position_centered(),
+        };
+
+        if hidden_size.is_some() {
+            window_builder.hidden();
+        } else {
+            window_builder.resizable();
+        }
+
+        let window = window_builder
+            .build()
+            .wrap_err("Couldn't create an OpenGL window")?;
+
+        // Init OpenGL
+        let gl_ctx = window.gl_create_context().map_err(|e| {
+            let (major, minor) = GL_VERSION;
+            eyre!("No OpenGL {major}.{minor} core profile context available: {e}")
+        })?;
+
+        gl::load_with(|name| video_subsystem.gl_get_proc_address(name) as _);
+        Self::check_gl_version()?;
 
         window
             .subsystem()
@@ -106,9 +167,55 @@ impl MyWindow {
             start_time: Instant::now(),
             width,
             height,
+            focused: true,
+            minimized: false,
         })
     }
 
+    /// Checks that the created context has at least the requested OpenGL version.
+    ///
+    /// Some drivers create an older context instead of failing.
+    fn check_gl_version() -> Result<()> {
+        let (mut major, mut minor) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        }
+
+        let (req_major, req_minor) = GL_VERSION;
+        if (major, minor) < (req_major as i32, req_minor as i32) {
+            return Err(eyre!(
+                "No OpenGL {req_major}.{req_minor} core profile context available: the driver provides {major}.{minor}"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The current size and position of the window
+    pub fn config(&self) -> WindowConfig {
+        let (x, y) = self.window.position();
+
+        WindowConfig {
+            width: self.width,
+            height: self.height,
+            x,
+            y,
+        }
+    }
+
+    /// Enables or disables synchronizing the buffer swaps with the display refresh rate
+    pub fn set_vsync(&self, vsync: bool) {
+        let interval = match vsync {
+            true => SwapInterval::VSync,
+            false => SwapInterval::Immediate,
+        };
+
+        if let Err(e) = self.window.subsystem().gl_set_swap_interval(interval) {
+            eprintln!("WARN: couldn't set the swap interval: {e}");
+        }
+    }
+
     /// Resets state at the beginning of a frame
     pub fn begin_frame(&mut self) {
         self.egui_state.input.time = Some(self.start_time.elapsed().as_secs_f64());
@@ -146,21 +253,71 @@ impl MyWindow {
                 .paint_jobs(None, paint_jobs, &self.egui_ctx.font_image());
             self.window.gl_swap_window();
         }
-        for event in self.event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => return true,
-                Event::Window {
-                    timestamp: _,
-                    window_id: _,
-                    win_event: WindowEvent::Resized(new_width, new_height),
-                } => {
-                    self.width = new_width as u32;
-                    self.height = new_height as u32;
-                }
-                _ => {
-                    self.egui_state
-                        .process_input(&self.window, event, &mut self.painter);
+        self.handle_events()
+    }
+
+    /// If the window is minimized or doesn't have the focus, nothing has to be rendered
+    pub fn is_inactive(&self) -> bool {
+        self.minimized || !self.focused
+    }
+
+    /// Blocks until an event arrives (or the timeout in milliseconds expires) and handles the pending events.
+    ///
+    /// Used instead of rendering while the window is inactive. Returns if the render loop should terminate.
+    pub fn wait_for_events(&mut self, timeout: u32) -> bool {
+        if let Some(event) = self.event_pump.wait_event_timeout(timeout) {
+            if self.handle_event(event) {
+                return true;
+            }
+        }
+
+        self.handle_events()
+    }
+
+    /// Handles the pending events and returns if the render loop should terminate
+    fn handle_events(&mut self) -> bool {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+
+        for event in events {
+            if self.handle_event(event) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Handles a single event, other events than the window events are passed to egui.
+    ///
+    /// Returns if the render loop should terminate.
+    fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Quit { .. } => return true,
+            Event::Window {
+                timestamp: _,
+                window_id: _,
+                win_event: WindowEvent::Resized(new_width, new_height),
+            } => {
+                self.width = new_width as u32;
+                self.height = new_height as u32;
+            }
+            Event::Window { win_event, .. } => {
+                match win_event {
+                    WindowEvent::FocusGained => self.focused = true,
+                    WindowEvent::FocusLost => self.focused = false,
+                    WindowEvent::Minimized | WindowEvent::Hidden => self.minimized = true,
+                    WindowEvent::Restored | WindowEvent::Maximized | WindowEvent::Shown => {
+                        self.minimized = false
+                    }
+                    _ => {}
                 }
+
+                self.egui_state
+                    .process_input(&self.window, event, &mut self.painter);
+            }
+            _ => {
+                self.egui_state
+                    .process_input(&self.window, event, &mut self.painter);
             }
         }
 